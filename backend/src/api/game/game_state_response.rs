@@ -5,9 +5,9 @@ use crate::{
     server_error::ServerError,
 };
 use hive_lib::{
-    bug::Bug, color::Color, game_control::GameControl, game_status::GameStatus,
-    game_status::GameStatus::Finished, game_type::GameType, history::History, piece::Piece,
-    position::Position, state::State,
+    bug::Bug, color::Color, features::Features, game_control::GameControl, game_status::GameStatus,
+    game_status::GameStatus::Finished, game_type::GameType, history::History, last_turn::LastTurn,
+    piece::Piece, position::Position, state::State,
 };
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
@@ -21,6 +21,7 @@ pub struct GameStateResponse {
     pub turn: usize,
     pub game_status: GameStatus,
     pub game_type: GameType,
+    pub last_turn: LastTurn,
     pub tournament_queen_rule: bool,
     pub white_player: UserResponse,
     pub black_player: UserResponse,
@@ -29,6 +30,7 @@ pub struct GameStateResponse {
     pub spawns: Vec<Position>,
     pub reserve_black: HashMap<Bug, Vec<String>>,
     pub reserve_white: HashMap<Bug, Vec<String>>,
+    pub features: Features,
     pub history: Vec<(String, String)>,
     pub game_control_history: Vec<(i32, GameControl)>,
     pub white_rating: Option<f64>,
@@ -39,7 +41,8 @@ pub struct GameStateResponse {
 
 impl GameStateResponse {
     pub async fn new_from_db(game: &Game, pool: &DbPool) -> Result<Self, ServerError> {
-        let history = History::new_from_str(game.history.clone())?;
+        let mut history = History::new_from_str(game.history.clone())?;
+        history.game_type = GameType::from_str(&game.game_type)?;
         let state = State::new_from_history(&history)?;
         GameStateResponse::new_from(game, &state, pool).await
     }
@@ -66,21 +69,21 @@ impl GameStateResponse {
             game_id: game.id,
             game_status: GameStatus::from_str(&game.game_status)?,
             game_type: GameType::from_str(&game.game_type)?,
-            tournament_queen_rule: state.tournament,
+            last_turn: state.last_turn.clone(),
+            tournament_queen_rule: state.rules.tournament_opening,
             turn: state.turn,
             white_player: UserResponse::from_uid(&game.white_uid, pool).await?,
             black_player: UserResponse::from_uid(&game.black_uid, pool).await?,
-            moves: GameStateResponse::moves_as_string(state.board.moves(state.turn_color)),
+            moves: GameStateResponse::moves_as_string(
+                state.board.moves(state.turn_color, &state.rules),
+            ),
             spawns: state
                 .board
                 .spawnable_positions(state.turn_color)
                 .collect::<Vec<_>>(),
-            reserve_black: state
-                .board
-                .reserve(Color::Black, game.game_type.parse().unwrap()),
-            reserve_white: state
-                .board
-                .reserve(Color::White, game.game_type.parse().unwrap()),
+            reserve_black: state.board.reserve(Color::Black, &state.rules),
+            reserve_white: state.board.reserve(Color::White, &state.rules),
+            features: Features::new(state),
             history: state.history.moves.clone(),
             game_control_history: Self::gc_history(&game.game_control_history),
             white_rating,
@@ -9,7 +9,7 @@ use actix_web::{
 };
 use hive_lib::{
     color::Color, game_control::GameControl, game_result::GameResult, game_status::GameStatus,
-    game_type::GameType, history::History, position::Position, state::State,
+    game_type::GameType, history::History, last_turn::LastTurn, position::Position, state::State,
 };
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
@@ -60,13 +60,18 @@ async fn play_turn(
     } else {
         auth_user.authorize(&game.black_uid)?;
     }
-    let history = History::new_from_str(game.history.clone())?;
+    let mut history = History::new_from_str(game.history.clone())?;
+    history.game_type = GameType::from_str(&game.game_type)?;
     let mut state = State::new_from_history(&history)?;
-    state.game_type = GameType::from_str(&game.game_type)?;
+    // don't wait for a player who can't move, the pass is stored like a played one
+    state.rules.automatic_pass = true;
     let piece = piece.parse()?;
     let position = Position::from_string(&pos, &state.board)?;
     state.play_turn(piece, position)?;
-    let board_move = format!("{piece} {pos}");
+    let mut board_move = format!("{piece} {pos};");
+    if state.last_turn == LastTurn::Shutout {
+        board_move.push_str("pass;");
+    }
     let game = game
         .make_move(board_move, state.game_status.clone(), pool)
         .await?;
@@ -229,7 +234,8 @@ async fn handle_abort(
     game_control: GameControl,
     pool: &DbPool,
 ) -> Result<GameStateResponse, ServerError> {
-    let history = History::new_from_str(game.history.clone())?;
+    let mut history = History::new_from_str(game.history.clone())?;
+    history.game_type = GameType::from_str(&game.game_type)?;
     let state = State::new_from_history(&history)?;
     let mut returned_game = (*game).clone();
     game.delete(pool).await?;
@@ -260,7 +266,8 @@ async fn handle_takeback_accept(
     moves.pop();
     let mut new_history = moves.join(";");
     new_history.push(';');
-    let history = History::new_from_str(new_history.clone())?;
+    let mut history = History::new_from_str(new_history.clone())?;
+    history.game_type = GameType::from_str(&game.game_type)?;
     let state = State::new_from_history(&history)?;
     let game = game
         .accept_takeback(
@@ -281,7 +288,8 @@ async fn handle_takeback_reject(
     ensure_game_control(game, game_control.clone())?;
     let game = game.write_game_control(game_control, pool).await?;
 
-    let history = History::new_from_str(game.history.clone())?;
+    let mut history = History::new_from_str(game.history.clone())?;
+    history.game_type = GameType::from_str(&game.game_type)?;
     let state = State::new_from_history(&history)?;
     GameStateResponse::new_from(&game, &state, pool).await
 }
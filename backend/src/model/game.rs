@@ -75,6 +75,8 @@ impl Game {
         if board_move.chars().last().unwrap_or(' ') != ';' {
             board_move = format!("{board_move};");
         }
+        // a shutout adds a pass to the move
+        let turns_played = board_move.matches(';').count() as i32;
         let mut game_control_string = String::new();
         if self.has_unanswered_game_control() {
             let gc = match self.last_game_control() {
@@ -117,7 +119,7 @@ impl Game {
                     let game = diesel::update(games::table.find(self.id))
                         .set((
                             history.eq(history.concat(board_move)),
-                            turn.eq(turn + 1),
+                            turn.eq(turn + turns_played),
                             game_status.eq(new_game_status.to_string()),
                             game_control_history
                                 .eq(game_control_history.concat(game_control_string)),
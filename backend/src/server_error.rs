@@ -4,14 +4,16 @@ use crate::{
 };
 use actix_web::{error::ResponseError, http::StatusCode, HttpResponse};
 use diesel::result::Error as DieselError;
-use hive_lib::game_error::GameError;
+use hive_lib::{game_error::GameError, illegal_move_reason::IllegalMoveReason};
 use serde::Serialize;
 use thiserror::Error;
 
+#[serde_with::skip_serializing_none]
 #[derive(Serialize)]
 pub struct ErrorResponse {
     code: u16,
     message: String,
+    reason: Option<IllegalMoveReason>,
 }
 
 #[derive(Debug, Error)]
@@ -65,9 +67,14 @@ impl ResponseError for ServerError {
     fn error_response(&self) -> HttpResponse {
         let status_code = self.status_code();
         // TODO: don't send a message for 500s
+        let reason = match self {
+            Self::GameError(GameError::InvalidMove { reason, .. }) => Some(*reason),
+            _ => None,
+        };
         let error_response = ErrorResponse {
             code: status_code.as_u16(),
             message: self.to_string(),
+            reason,
         };
         HttpResponse::build(status_code).json(error_response)
     }
@@ -0,0 +1,48 @@
+/* C API of the hive engine, see engine/src/ffi.rs. Functions never panic into the caller, an
+ * internal error is reported like any other error. The declarations are written by hand,
+ * tests_header_matches_exports in ffi.rs checks them against the exports. */
+#ifndef HIVE_H
+#define HIVE_H
+
+#include <stdbool.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Opaque game state */
+typedef struct HiveState HiveState;
+
+/* Creates a new game, game_type is e.g. "Base+MLP" and may be NULL for Base.
+ * Returns NULL on error, the state has to be released with hive_state_free. */
+HiveState *hive_state_new(const char *game_type, bool tournament);
+
+void hive_state_free(HiveState *state);
+
+/* Plays a move in notation, e.g. piece "bA1" and position "wQ/", or piece "pass".
+ * Returns 0 on success and -1 on error, see hive_last_error, the state is unchanged then. */
+int hive_state_play(HiveState *state, const char *piece, const char *position);
+
+/* Returns the legal moves, spawns and reserve of the player to move as JSON.
+ * The string has to be released with hive_string_free. */
+char *hive_state_moves_json(const HiveState *state);
+
+/* Returns the game status, e.g. "InProgress" or "Finished(Winner(w))".
+ * The string has to be released with hive_string_free. */
+char *hive_state_status(const HiveState *state);
+
+/* Returns turn, status, history and all stacks on the board as JSON.
+ * The string has to be released with hive_string_free. */
+char *hive_state_board_json(const HiveState *state);
+
+/* Returns the message of the last error on this thread or NULL.
+ * The string is owned by the library and valid until the next call into it. */
+const char *hive_last_error(void);
+
+void hive_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* HIVE_H */
@@ -0,0 +1,39 @@
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "hive.h"
+
+int main(void) {
+    HiveState *state = hive_state_new("Base+MLP", false);
+    assert(state != NULL);
+
+    assert(hive_state_play(state, "wL", ".") == 0);
+    assert(hive_state_play(state, "bL", "wL-") == 0);
+    assert(hive_state_play(state, "wQ", "-wL") == 0);
+    assert(hive_state_play(state, "bQ", "bL-") == 0);
+
+    /* black is not to move */
+    assert(hive_state_play(state, "bA1", "bQ-") == -1);
+    assert(hive_last_error() != NULL);
+    printf("expected error: %s\n", hive_last_error());
+
+    char *status = hive_state_status(state);
+    assert(strcmp(status, "InProgress") == 0);
+    hive_string_free(status);
+
+    char *moves = hive_state_moves_json(state);
+    assert(moves != NULL && strstr(moves, "\"spawns\"") != NULL);
+    printf("moves: %s\n", moves);
+    hive_string_free(moves);
+
+    char *board = hive_state_board_json(state);
+    assert(board != NULL && strstr(board, "\"stacks\"") != NULL);
+    printf("board: %s\n", board);
+    hive_string_free(board);
+
+    hive_state_free(state);
+    assert(hive_state_new("Chess", false) == NULL);
+    printf("ok\n");
+    return 0;
+}
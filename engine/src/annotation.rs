@@ -0,0 +1,278 @@
+use crate::game_error::GameError;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr, time::Duration};
+
+// Numeric annotation glyphs, the first six have a symbol
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Nag {
+    Good,
+    Mistake,
+    Brilliant,
+    Blunder,
+    Interesting,
+    Dubious,
+    Other(u8),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Annotation {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nags: Vec<Nag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    // engine evaluation in hundredths, positive is good for white, [%eval 1.25] is 125
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evaluation: Option<i32>,
+    // time left on the mover's clock after the move
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<Duration>,
+}
+
+impl Nag {
+    pub fn from_number(number: u8) -> Self {
+        match number {
+            1 => Nag::Good,
+            2 => Nag::Mistake,
+            3 => Nag::Brilliant,
+            4 => Nag::Blunder,
+            5 => Nag::Interesting,
+            6 => Nag::Dubious,
+            other => Nag::Other(other),
+        }
+    }
+
+    pub fn number(&self) -> u8 {
+        match self {
+            Nag::Good => 1,
+            Nag::Mistake => 2,
+            Nag::Brilliant => 3,
+            Nag::Blunder => 4,
+            Nag::Interesting => 5,
+            Nag::Dubious => 6,
+            Nag::Other(number) => *number,
+        }
+    }
+
+    pub fn symbol(&self) -> Option<&'static str> {
+        match self {
+            Nag::Good => Some("!"),
+            Nag::Mistake => Some("?"),
+            Nag::Brilliant => Some("!!"),
+            Nag::Blunder => Some("??"),
+            Nag::Interesting => Some("!?"),
+            Nag::Dubious => Some("?!"),
+            Nag::Other(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Nag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "${}", self.number())
+    }
+}
+
+// Accepts both glyphs like `$5` and symbols like `!?`
+impl FromStr for Nag {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(number) = s.strip_prefix('$') {
+            if let Ok(number) = number.parse() {
+                return Ok(Nag::from_number(number));
+            }
+        }
+        (1..=6)
+            .map(Nag::from_number)
+            .find(|nag| nag.symbol() == Some(s))
+            .ok_or(GameError::ParsingError {
+                found: s.to_string(),
+                typ: "annotation glyph".to_string(),
+            })
+    }
+}
+
+impl Annotation {
+    pub fn is_empty(&self) -> bool {
+        *self == Annotation::default()
+    }
+
+    // Later glyphs are added, comments are joined and evaluation and clock are replaced
+    pub fn merge(&mut self, other: Annotation) {
+        self.nags.extend(other.nags);
+        self.comment = match (self.comment.take(), other.comment) {
+            (Some(first), Some(second)) => Some(format!("{first} {second}")),
+            (first, second) => first.or(second),
+        };
+        self.evaluation = other.evaluation.or(self.evaluation);
+        self.clock = other.clock.or(self.clock);
+    }
+
+    // Parses the inside of a `{}` comment, [%eval] and [%clk] commands are taken out of the text
+    pub fn from_comment(comment: &str) -> Self {
+        lazy_static! {
+            static ref COMMAND: Regex =
+                Regex::new(r"\[%(eval|clk)\s+([^\]\s]+)\s*\]").expect("This regex should compile");
+        }
+        let mut annotation = Annotation::default();
+        let text = COMMAND.replace_all(comment, |caps: &regex::Captures| {
+            let value = &caps[2];
+            let parsed = match &caps[1] {
+                "eval" => parse_evaluation(value).map(|eval| annotation.evaluation = Some(eval)),
+                _ => parse_clock(value).map(|clock| annotation.clock = Some(clock)),
+            };
+            // commands that don't parse stay part of the comment
+            match parsed {
+                Some(()) => String::new(),
+                None => caps[0].to_string(),
+            }
+        });
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() {
+            annotation.comment = Some(text);
+        }
+        annotation
+    }
+
+    // The PGN suffix of a move, e.g. ` $5 {a comment [%eval 1.25] [%clk 0:04:55]}`
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        for nag in self.nags.iter() {
+            pgn += &format!(" {nag}");
+        }
+        let mut comment = Vec::new();
+        if let Some(text) = &self.comment {
+            comment.push(text.replace('}', ")"));
+        }
+        if let Some(evaluation) = self.evaluation {
+            comment.push(format!("[%eval {}]", evaluation_string(evaluation)));
+        }
+        if let Some(clock) = self.clock {
+            comment.push(format!("[%clk {}]", clock_string(clock)));
+        }
+        if !comment.is_empty() {
+            pgn += &format!(" {{{}}}", comment.join(" "));
+        }
+        pgn
+    }
+}
+
+fn parse_evaluation(value: &str) -> Option<i32> {
+    let (sign, value) = match value.strip_prefix('-') {
+        Some(value) => (-1, value),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if fraction.len() > 2 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let whole = whole.parse::<i32>().ok()?;
+    let fraction = format!("{fraction:0<2}").parse::<i32>().ok()?;
+    Some(sign * (whole * 100 + fraction))
+}
+
+fn evaluation_string(evaluation: i32) -> String {
+    let sign = if evaluation < 0 { "-" } else { "" };
+    let evaluation = evaluation.unsigned_abs();
+    format!("{sign}{}.{:02}", evaluation / 100, evaluation % 100)
+}
+
+// H:MM:SS with an optional fraction of a second
+fn parse_clock(value: &str) -> Option<Duration> {
+    let (time, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let parts = time
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if parts.is_empty() || parts.len() > 3 || parts.iter().skip(1).any(|part| *part >= 60) {
+        return None;
+    }
+    let seconds = parts.iter().fold(0, |total, part| total * 60 + part);
+    if fraction.len() > 3 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let millis = format!("{fraction:0<3}").parse::<u64>().ok()?;
+    Some(Duration::from_secs(seconds) + Duration::from_millis(millis))
+}
+
+fn clock_string(clock: Duration) -> String {
+    let seconds = clock.as_secs();
+    let time = format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    match clock.subsec_millis() {
+        0 => time,
+        millis => format!("{time}.{}", format!("{millis:03}").trim_end_matches('0')),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_nag() {
+        for symbol in ["!", "?", "!!", "??", "!?", "?!"] {
+            let nag = symbol.parse::<Nag>().unwrap();
+            assert_eq!(nag.symbol(), Some(symbol));
+            assert_eq!(nag.to_string().parse::<Nag>().unwrap(), nag);
+        }
+        assert_eq!("$4".parse::<Nag>().unwrap(), Nag::Blunder);
+        assert_eq!("$14".parse::<Nag>().unwrap(), Nag::Other(14));
+        assert!("!!!".parse::<Nag>().is_err());
+        assert!("$x".parse::<Nag>().is_err());
+    }
+
+    #[test]
+    fn tests_from_comment() {
+        let annotation =
+            Annotation::from_comment(" queen is  trapped [%eval -1.5] [%clk 0:04:55.2] ");
+        assert_eq!(annotation.comment.as_deref(), Some("queen is trapped"));
+        assert_eq!(annotation.evaluation, Some(-150));
+        assert_eq!(annotation.clock, Some(Duration::from_millis(295_200)));
+        let annotation = Annotation::from_comment("[%clk 1:75:00] [%eval #3]");
+        assert_eq!(
+            annotation.comment.as_deref(),
+            Some("[%clk 1:75:00] [%eval #3]")
+        );
+        assert_eq!(annotation.evaluation, None);
+        assert!(Annotation::from_comment("  ").is_empty());
+    }
+
+    #[test]
+    fn tests_to_pgn() {
+        let annotation = Annotation {
+            nags: vec![Nag::Interesting, Nag::Other(14)],
+            comment: Some("a {nested} comment".to_string()),
+            evaluation: Some(-5),
+            clock: Some(Duration::from_millis(3_723_040)),
+        };
+        let pgn = annotation.to_pgn();
+        assert_eq!(
+            pgn,
+            " $5 $14 {a {nested) comment [%eval -0.05] [%clk 1:02:03.04]}"
+        );
+        let parsed = Annotation::from_comment("a {nested) comment [%eval -0.05] [%clk 1:02:03.04]");
+        assert_eq!(parsed.evaluation, annotation.evaluation);
+        assert_eq!(parsed.clock, annotation.clock);
+        assert_eq!(Annotation::default().to_pgn(), "");
+    }
+
+    #[test]
+    fn tests_merge() {
+        let mut annotation = Annotation::from_comment("first [%eval 1]");
+        annotation.merge(Annotation {
+            nags: vec![Nag::Good],
+            ..Annotation::from_comment("second [%clk 10]")
+        });
+        assert_eq!(annotation.nags, vec![Nag::Good]);
+        assert_eq!(annotation.comment.as_deref(), Some("first second"));
+        assert_eq!(annotation.evaluation, Some(100));
+        assert_eq!(annotation.clock, Some(Duration::from_secs(10)));
+    }
+}
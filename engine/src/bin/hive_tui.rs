@@ -0,0 +1,736 @@
+use clap::Parser;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{self, ContentStyle, Stylize},
+    terminal,
+};
+use hive_lib::{
+    bug::Bug, color::Color, direction::Direction, game_result::GameResult, game_status::GameStatus,
+    game_type::GameType, history::History, move_list::Move, piece::Piece, position::Position,
+    search::TranspositionTable, self_play::Engine, state::State, turn::Turn,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+#[derive(Parser)]
+#[command(name = "hive_tui", about = "Play hive in the terminal")]
+struct Cli {
+    /// Play against a bot instead of hot-seat, e.g. `search:depth=2` or `random`
+    #[arg(long)]
+    bot: Option<Engine>,
+    /// The color the bot plays
+    #[arg(long, default_value_t = Color::Black)]
+    bot_color: Color,
+    /// Time the bot has per move in milliseconds
+    #[arg(long, default_value_t = 2000)]
+    time_ms: u64,
+    #[arg(long, default_value_t = GameType::MLP)]
+    game_type: GameType,
+    /// PGN file to continue
+    #[arg(long)]
+    load: Option<String>,
+}
+
+// Width of a hex in characters and height of a row in lines
+const HEX_WIDTH: usize = 4;
+const ROW_HEIGHT: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Focus {
+    Board,
+    Reserve(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PromptKind {
+    Save,
+    Load,
+}
+
+struct Prompt {
+    kind: PromptKind,
+    input: String,
+}
+
+struct App {
+    state: State,
+    game_type: GameType,
+    bot: Option<(Engine, Color)>,
+    time_per_move: Duration,
+    table: TranspositionTable,
+    rng: StdRng,
+    // the states before every turn played since the game was started or loaded
+    undo: Vec<State>,
+    cursor: Position,
+    focus: Focus,
+    selected: Option<Piece>,
+    targets: Vec<Position>,
+    prompt: Option<Prompt>,
+    message: String,
+    file: String,
+    quit: bool,
+}
+
+type Cell = (char, ContentStyle);
+
+impl App {
+    fn new(game_type: GameType, bot: Option<(Engine, Color)>, time_per_move: Duration) -> Self {
+        let mut state = State::new(game_type, true);
+        state.history.game_type = game_type;
+        App {
+            state,
+            game_type,
+            bot,
+            time_per_move,
+            table: TranspositionTable::new(),
+            rng: StdRng::seed_from_u64(0),
+            undo: Vec::new(),
+            cursor: Position::initial_spawn_position(),
+            focus: Focus::Reserve(0),
+            selected: None,
+            targets: Vec::new(),
+            prompt: None,
+            message: String::from("Select a piece from your reserve to start"),
+            file: String::from("game.pgn"),
+            quit: false,
+        }
+    }
+
+    fn finished(&self) -> bool {
+        matches!(self.state.game_status, GameStatus::Finished(_))
+    }
+
+    fn bot_to_move(&self) -> bool {
+        !self.finished()
+            && self
+                .bot
+                .as_ref()
+                .is_some_and(|(_, color)| *color == self.state.turn_color)
+    }
+
+    // The bugs the side to move still has in reserve, with the piece that would be placed
+    fn reserve(&self, color: Color) -> Vec<(Piece, usize)> {
+        let reserve = self.state.board.reserve(color, &self.state.rules);
+        Bug::all()
+            .filter_map(|bug| {
+                let pieces = reserve.get(&bug)?;
+                let piece = pieces.first()?.parse::<Piece>().ok()?;
+                Some((piece, pieces.len()))
+            })
+            .collect()
+    }
+
+    fn destinations(&self, piece: Piece) -> Vec<Position> {
+        self.state
+            .legal_turns()
+            .into_iter()
+            .filter(|turn| turn.piece() == Some(piece))
+            .filter_map(|turn| turn.to())
+            .collect()
+    }
+
+    fn select(&mut self, piece: Piece) {
+        self.targets = self.destinations(piece);
+        if self.targets.is_empty() {
+            self.message = format!("{} has no legal move", piece.long_name());
+            self.selected = None;
+            return;
+        }
+        self.message = format!(
+            "{} selected, pick one of the highlighted destinations",
+            piece.long_name()
+        );
+        self.selected = Some(piece);
+        self.focus = Focus::Board;
+        if !self.targets.contains(&self.cursor) {
+            self.cursor = self.targets[0];
+        }
+    }
+
+    fn deselect(&mut self) {
+        self.selected = None;
+        self.targets.clear();
+    }
+
+    fn play(&mut self, turn: Turn) {
+        let description = self.state.describe_turn(turn);
+        let before = self.state.clone();
+        match self.state.apply_turn(turn) {
+            Ok(()) => {
+                self.undo.push(before);
+                self.message = description.unwrap_or_default();
+                self.deselect();
+                self.after_turn();
+            }
+            Err(e) => self.message = e.to_string(),
+        }
+    }
+
+    fn after_turn(&mut self) {
+        if let GameStatus::Finished(result) = &self.state.game_status {
+            self.message = match result {
+                GameResult::Winner(color) => format!("{} wins!", color.title()),
+                GameResult::Draw => String::from("The game is a draw"),
+                GameResult::Unknown => String::from("The game is over"),
+            };
+        }
+        self.focus = match self.reserve(self.state.turn_color).is_empty() {
+            true => Focus::Board,
+            false => Focus::Reserve(0),
+        };
+    }
+
+    fn play_bot(&mut self) {
+        let Some((engine, _)) = self.bot else {
+            return;
+        };
+        let deadline = Instant::now() + self.time_per_move;
+        let turn = engine
+            .choose(&self.state, deadline, &self.table, &mut self.rng)
+            .or_else(|| self.state.legal_turns().first().copied());
+        match turn {
+            Some(turn) => self.play(turn),
+            None => self.message = String::from("The bot has no legal turn"),
+        }
+    }
+
+    // Goes back to the last turn the human played, against a bot that's two plies
+    fn undo(&mut self) {
+        let Some(mut previous) = self.undo.pop() else {
+            self.message = String::from("Nothing to undo");
+            return;
+        };
+        while self
+            .bot
+            .as_ref()
+            .is_some_and(|(_, color)| *color == previous.turn_color)
+        {
+            match self.undo.pop() {
+                Some(state) => previous = state,
+                None => break,
+            }
+        }
+        self.state = previous;
+        self.deselect();
+        self.after_turn();
+        self.message = String::from("Undone");
+    }
+
+    fn save(&mut self, file: &str) {
+        let (white, black) = match &self.bot {
+            Some((engine, Color::White)) => (engine.to_string(), String::from("human")),
+            Some((engine, Color::Black)) => (String::from("human"), engine.to_string()),
+            None => (String::from("human"), String::from("human")),
+        };
+        let pgn = self
+            .state
+            .history
+            .to_pgn(&[("White", white.as_str()), ("Black", black.as_str())]);
+        self.message = match fs::write(file, pgn) {
+            Ok(()) => format!("Saved to {file}"),
+            Err(e) => format!("Could not save to {file}: {e}"),
+        };
+        self.file = file.to_string();
+    }
+
+    fn load(&mut self, file: &str) {
+        let loaded = History::from_filepath(file).and_then(|history| {
+            let state = State::new_from_history(&history)?;
+            Ok((history.game_type, state))
+        });
+        match loaded {
+            Ok((game_type, state)) => {
+                self.game_type = game_type;
+                self.state = state;
+                self.undo.clear();
+                self.deselect();
+                self.after_turn();
+                self.cursor = self
+                    .state
+                    .board
+                    .last_move
+                    .map_or(Position::initial_spawn_position(), |last_move| last_move.to);
+                self.message = format!("Loaded {file}");
+            }
+            Err(e) => self.message = format!("Could not load {file}: {e}"),
+        }
+        self.file = file.to_string();
+    }
+
+    fn move_cursor(&mut self, key: KeyCode) {
+        let odd = self.cursor.r.rem_euclid(2) == 1;
+        // up and down zigzag so the cursor stays in the same column
+        let direction = match key {
+            KeyCode::Left | KeyCode::Char('h') => Direction::W,
+            KeyCode::Right | KeyCode::Char('l') => Direction::E,
+            KeyCode::Up | KeyCode::Char('k') if odd => Direction::NW,
+            KeyCode::Up | KeyCode::Char('k') => Direction::NE,
+            KeyCode::Down | KeyCode::Char('j') if odd => Direction::SW,
+            KeyCode::Down | KeyCode::Char('j') => Direction::SE,
+            KeyCode::Char('y') => Direction::NW,
+            KeyCode::Char('u') => Direction::NE,
+            KeyCode::Char('b') => Direction::SW,
+            KeyCode::Char('n') => Direction::SE,
+            _ => return,
+        };
+        self.cursor = self.cursor.to(direction);
+    }
+
+    fn handle_prompt_key(&mut self, key: KeyEvent) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Backspace => {
+                prompt.input.pop();
+            }
+            KeyCode::Char(c) => prompt.input.push(c),
+            KeyCode::Enter => {
+                let Prompt { kind, input } = self.prompt.take().expect("The prompt is open");
+                match kind {
+                    PromptKind::Save => self.save(input.trim()),
+                    PromptKind::Load => self.load(input.trim()),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if self.prompt.is_some() {
+            self.handle_prompt_key(key);
+            return;
+        }
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.quit = true;
+            return;
+        }
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc => {
+                self.deselect();
+                self.message.clear();
+            }
+            KeyCode::Char('z') => self.undo(),
+            KeyCode::Char('s') => {
+                self.prompt = Some(Prompt {
+                    kind: PromptKind::Save,
+                    input: self.file.clone(),
+                })
+            }
+            KeyCode::Char('o') => {
+                self.prompt = Some(Prompt {
+                    kind: PromptKind::Load,
+                    input: self.file.clone(),
+                })
+            }
+            _ if self.finished() || self.bot_to_move() => {}
+            KeyCode::Char('p') => self.play(Turn::Pass),
+            KeyCode::Tab => {
+                self.deselect();
+                self.focus = match self.focus {
+                    Focus::Board if !self.reserve(self.state.turn_color).is_empty() => {
+                        Focus::Reserve(0)
+                    }
+                    _ => Focus::Board,
+                };
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => self.confirm(),
+            code => match self.focus {
+                Focus::Reserve(index) => {
+                    let len = self.reserve(self.state.turn_color).len();
+                    self.focus = match code {
+                        KeyCode::Left | KeyCode::Char('h') => {
+                            Focus::Reserve((index + len - 1) % len.max(1))
+                        }
+                        KeyCode::Right | KeyCode::Char('l') => {
+                            Focus::Reserve((index + 1) % len.max(1))
+                        }
+                        _ => Focus::Reserve(index),
+                    };
+                }
+                Focus::Board => self.move_cursor(code),
+            },
+        }
+    }
+
+    fn confirm(&mut self) {
+        match self.focus {
+            Focus::Reserve(index) => {
+                if let Some((piece, _)) = self.reserve(self.state.turn_color).get(index) {
+                    self.select(*piece);
+                }
+            }
+            Focus::Board => match self.selected {
+                Some(piece) if self.targets.contains(&self.cursor) => {
+                    let turn = match self.state.board.position_of_piece(piece) {
+                        Some(from) => Turn::Move(Move {
+                            piece,
+                            from,
+                            to: self.cursor,
+                        }),
+                        None => Turn::Spawn(piece, self.cursor),
+                    };
+                    self.play(turn);
+                }
+                _ => match self.state.board.top_piece(self.cursor) {
+                    Some(piece) => self.select(piece),
+                    None => self.deselect(),
+                },
+            },
+        }
+    }
+
+    // The hexes around the hive, the targets and the cursor as lines of styled characters
+    fn board_lines(&self) -> Vec<Vec<Cell>> {
+        let mut positions = self
+            .state
+            .board
+            .positions
+            .iter()
+            .flatten()
+            .copied()
+            .collect::<HashSet<_>>();
+        positions.extend(self.targets.iter().copied());
+        positions.insert(self.cursor);
+        let cells = positions
+            .iter()
+            .map(|position| offset(*position))
+            .collect::<Vec<_>>();
+        let min_row = cells.iter().map(|(row, _)| *row).min().unwrap_or(0) - 1;
+        let max_row = cells.iter().map(|(row, _)| *row).max().unwrap_or(0) + 1;
+        let min_column = cells.iter().map(|(_, column)| *column).min().unwrap_or(0) - 1;
+        let max_column = cells.iter().map(|(_, column)| *column).max().unwrap_or(0) + 1;
+        let height = (max_row - min_row + 1) as usize * ROW_HEIGHT + 1;
+        let width = (max_column - min_column + 1) as usize * HEX_WIDTH + HEX_WIDTH / 2 + 1;
+        let mut lines = vec![vec![(' ', ContentStyle::new()); width]; height];
+        let edge = ContentStyle::new().dark_grey();
+        let selected = self
+            .selected
+            .and_then(|piece| self.state.board.position_of_piece(piece));
+        for row in min_row..=max_row {
+            for column in min_column..=max_column {
+                let y = (row - min_row) as usize * ROW_HEIGHT;
+                let x = (column - min_column) as usize * HEX_WIDTH
+                    + row.rem_euclid(2) as usize * HEX_WIDTH / 2;
+                let position = from_offset(row, column);
+                for (dx, dy, c) in [(1, 0, '/'), (3, 0, '\\'), (0, 1, '|'), (4, 1, '|')] {
+                    lines[y + dy][x + dx] = (c, edge);
+                }
+                for (dx, c) in [(1, '\\'), (3, '/')] {
+                    lines[y + 2][x + dx] = (c, edge);
+                }
+                let (text, mut content) = match self.state.board.top_piece(position) {
+                    Some(piece) => {
+                        let mut content = match piece.color() {
+                            Color::White => ContentStyle::new().white().bold(),
+                            Color::Black => ContentStyle::new().magenta().bold(),
+                        };
+                        if self.state.board.level(position) > 1 {
+                            content = content.underlined();
+                        }
+                        (format!("{piece:<3}"), content)
+                    }
+                    None => (String::from("   "), ContentStyle::new()),
+                };
+                if self.targets.contains(&position) {
+                    content = content.on_dark_green();
+                }
+                if Some(position) == selected {
+                    content = content.on_dark_cyan();
+                }
+                if position == self.cursor && self.focus == Focus::Board {
+                    content = content.reverse();
+                }
+                for (dx, c) in text.chars().take(3).enumerate() {
+                    lines[y + 1][x + 1 + dx] = (c, content);
+                }
+            }
+        }
+        lines
+    }
+
+    fn status_lines(&self) -> Vec<Vec<Cell>> {
+        let mut lines = Vec::new();
+        let plain = ContentStyle::new();
+        let to_move = match self.finished() {
+            true => String::from("game over"),
+            false => format!("{} to move", self.state.turn_color.title()),
+        };
+        lines.push(text(
+            &format!("{}, turn {}, {to_move}", self.game_type, self.state.turn),
+            plain.bold(),
+        ));
+        for color in [Color::White, Color::Black] {
+            let mut line = text(&format!("{:<6} ", color.title()), plain);
+            for (i, (piece, count)) in self.reserve(color).iter().enumerate() {
+                let mut style = plain;
+                if color == self.state.turn_color && self.focus == Focus::Reserve(i) {
+                    style = style.reverse();
+                }
+                if Some(*piece) == self.selected {
+                    style = style.on_dark_cyan();
+                }
+                line.extend(text(&format!("{}x{count}", piece.bug()), style));
+                line.push((' ', plain));
+            }
+            lines.push(line);
+        }
+        let stack = self.state.board.board.get(self.cursor);
+        let stack = stack.pieces[..stack.len()]
+            .iter()
+            .map(Piece::long_name)
+            .collect::<Vec<_>>();
+        let Position { q, r } = self.cursor;
+        lines.push(text(
+            &format!("cursor {q},{r}: {}", stack.join(" under ")),
+            plain,
+        ));
+        let last = self.state.describe_last_turn().unwrap_or_default();
+        lines.push(text(&format!("last turn: {last}"), plain));
+        match &self.prompt {
+            Some(prompt) => {
+                let label = match prompt.kind {
+                    PromptKind::Save => "save to",
+                    PromptKind::Load => "load from",
+                };
+                lines.push(text(&format!("{label}: {}_", prompt.input), plain.yellow()));
+            }
+            None => lines.push(text(&self.message, plain.yellow())),
+        }
+        lines.push(text(
+            "arrows/hjkl yubn move  tab board/reserve  enter select/play  esc cancel  p pass  z undo  s save  o load  q quit",
+            plain.dark_grey(),
+        ));
+        lines
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        queue!(out, terminal::Clear(terminal::ClearType::All))?;
+        let lines = self.status_lines().into_iter().chain(self.board_lines());
+        for (y, line) in lines.enumerate() {
+            queue!(out, cursor::MoveTo(0, y as u16))?;
+            for (c, style) in line {
+                queue!(out, style::PrintStyledContent(style.apply(c)))?;
+            }
+        }
+        out.flush()
+    }
+}
+
+fn text(s: &str, style: ContentStyle) -> Vec<Cell> {
+    s.chars().map(|c| (c, style)).collect()
+}
+
+// Rows and columns, odd rows are shifted right by half a hex
+fn offset(position: Position) -> (i32, i32) {
+    (position.r, position.q + position.r.div_euclid(2))
+}
+
+fn from_offset(row: i32, column: i32) -> Position {
+    Position::new(column - row.div_euclid(2), row)
+}
+
+// Restores the terminal, also when the game panics
+struct Terminal;
+
+impl Terminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Terminal)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn run(mut app: App) -> io::Result<()> {
+    let _terminal = Terminal::enter()?;
+    let mut out = io::stdout();
+    while !app.quit {
+        app.draw(&mut out)?;
+        if app.bot_to_move() && app.prompt.is_none() {
+            app.play_bot();
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Release {
+                app.handle_key(key);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let bot = cli.bot.map(|engine| (engine, cli.bot_color));
+    let mut app = App::new(cli.game_type, bot, Duration::from_millis(cli.time_ms));
+    if let Some(file) = cli.load {
+        app.load(&file);
+        if !app.message.starts_with("Loaded") {
+            eprintln!("{}", app.message);
+            return ExitCode::FAILURE;
+        }
+    }
+    match run(app) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\t' => KeyCode::Tab,
+                c => KeyCode::Char(c),
+            };
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+    }
+
+    fn screen(lines: &[Vec<Cell>]) -> String {
+        lines
+            .iter()
+            .map(|line| line.iter().map(|(c, _)| c).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_hot_seat() {
+        let mut app = App::new(GameType::Base, None, Duration::from_millis(10));
+        // the first bug in the reserve is the ant, it can only go to the center
+        press(&mut app, "\n");
+        assert_eq!(app.selected, Some("wA1".parse().unwrap()));
+        assert_eq!(app.targets, vec![Position::initial_spawn_position()]);
+        press(&mut app, "\n");
+        assert_eq!(app.state.turn, 1);
+        assert_eq!(app.message, "White Ant 1 is placed as the first piece");
+        // black places a beetle, the second bug in its reserve
+        press(&mut app, "l\n");
+        assert_eq!(app.selected, Some("bB1".parse().unwrap()));
+        assert_eq!(app.targets.len(), 6);
+        let target = app.targets[0];
+        app.cursor = target;
+        press(&mut app, "\n");
+        assert_eq!(
+            app.state.board.top_piece(target),
+            Some("bB1".parse().unwrap())
+        );
+        assert_eq!(app.state.turn, 2);
+        press(&mut app, "z");
+        assert_eq!(app.state.turn, 1);
+        press(&mut app, "zz");
+        assert_eq!(app.state.turn, 0);
+        assert_eq!(app.message, "Nothing to undo");
+    }
+
+    #[test]
+    fn test_cursor_zigzag() {
+        let mut app = App::new(GameType::Base, None, Duration::from_millis(10));
+        app.focus = Focus::Board;
+        let start = offset(app.cursor);
+        press(&mut app, "jj");
+        assert_eq!(offset(app.cursor), (start.0 + 2, start.1));
+        press(&mut app, "kk");
+        assert_eq!(offset(app.cursor), start);
+        press(&mut app, "yn");
+        assert_eq!(offset(app.cursor), start);
+        for position in [Position::new(3, -5), Position::new(-2, 7)] {
+            let (row, column) = offset(position);
+            assert_eq!(from_offset(row, column), position);
+        }
+    }
+
+    #[test]
+    fn test_board_lines() {
+        let mut app = App::new(GameType::Base, None, Duration::from_millis(10));
+        press(&mut app, "\n\n");
+        press(&mut app, "\n");
+        let lines = app.board_lines();
+        let screen = screen(&lines);
+        assert!(screen.contains("|wA1|"), "{screen}");
+        // the black ant is still in the reserve, the board highlights its six targets and the
+        // cursor sits on the first of them
+        let cells = lines.iter().flatten().collect::<Vec<_>>();
+        let count = |f: &dyn Fn(&ContentStyle) -> bool| cells.iter().filter(|(_, s)| f(s)).count();
+        assert_eq!(count(&|style| style.background_color.is_some()), 6 * 3);
+        assert_eq!(
+            count(&|style| style.background_color == Some(style::Color::DarkGreen)),
+            6 * 3
+        );
+        assert_eq!(
+            count(&|style| style.attributes.has(style::Attribute::Reverse)),
+            3
+        );
+        assert!(cells
+            .iter()
+            .filter(|(_, style)| style.attributes.has(style::Attribute::Reverse))
+            .all(|(_, style)| style.background_color == Some(style::Color::DarkGreen)));
+        assert_eq!(app.cursor, app.targets[0]);
+        assert!(app.status_lines().len() > 3);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let file = std::env::temp_dir().join("hive_tui_test_save_and_load.pgn");
+        let file = file.to_str().unwrap().to_string();
+        let mut app = App::new(GameType::MLP, None, Duration::from_millis(10));
+        press(&mut app, "\n\n\n");
+        let target = app.targets[0];
+        app.cursor = target;
+        press(&mut app, "\n");
+        app.save(&file);
+        assert_eq!(app.message, format!("Saved to {file}"));
+        let mut loaded = App::new(GameType::Base, None, Duration::from_millis(10));
+        loaded.load(&file);
+        assert_eq!(loaded.message, format!("Loaded {file}"));
+        assert_eq!(loaded.game_type, GameType::MLP);
+        assert_eq!(loaded.state.board, app.state.board);
+        loaded.load("does/not/exist.pgn");
+        assert!(loaded.message.starts_with("Could not load"));
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_bot() {
+        let engine = "random:seed=3".parse::<Engine>().unwrap();
+        let mut app = App::new(
+            GameType::Base,
+            Some((engine, Color::Black)),
+            Duration::from_millis(10),
+        );
+        press(&mut app, "\n\n");
+        assert!(app.bot_to_move());
+        // keys are ignored while the bot is thinking
+        press(&mut app, "p");
+        assert_eq!(app.state.turn, 1);
+        app.play_bot();
+        assert_eq!(app.state.turn, 2);
+        assert!(!app.bot_to_move());
+        // undo takes back the bot's turn and the human's
+        press(&mut app, "z");
+        assert_eq!(app.state.turn, 0);
+    }
+}
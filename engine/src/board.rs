@@ -1,12 +1,21 @@
 use crate::{
-    bug::Bug, bug_stack::BugStack, color::Color, game_error::GameError, game_result::GameResult,
-    game_type::GameType, piece::Piece, position::Position, torus_array::TorusArray,
+    bug::Bug,
+    bug_stack::BugStack,
+    color::Color,
+    game_error::GameError,
+    game_result::GameResult,
+    hex_grid::HexGrid,
+    illegal_move_reason::IllegalMoveReason,
+    last_move::LastMove,
+    move_list::MoveList,
+    piece::Piece,
+    position::{Orientation, Position},
+    rule_set::RuleSet,
 };
-use itertools::Itertools;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::fmt::{self, Write};
-
-pub const BOARD_SIZE: i32 = 32;
+use std::hash::{Hash, Hasher};
 
 #[derive(Clone, Debug)]
 pub struct DfsInfo {
@@ -28,29 +37,30 @@ impl fmt::Display for DfsInfo {
 pub struct MidMoveBoard<'this> {
     pub board: &'this Board,
     pub position_in_flight: Position,
-    pub neighbor_count: TorusArray<u8>,
     pub piece_in_flight: Piece,
 }
 
 impl<'this> MidMoveBoard<'this> {
     pub fn new(board: &'this Board, piece: Piece, position: Position) -> Self {
-        let mut neighbor_count = board.neighbor_count.clone();
         debug_assert_eq!(board.level(position), 1);
-
-        for pos in position.positions_around() {
-            *neighbor_count.get_mut(pos) -= 1;
-        }
-
         Self {
             board,
             piece_in_flight: piece,
             position_in_flight: position,
-            neighbor_count,
+        }
+    }
+
+    // The counts of the board without the piece in flight, derived instead of copying the grid
+    pub fn neighbor_count(&self, position: Position) -> u8 {
+        let count = *self.board.neighbor_count.get(position);
+        match position.is_neighbor(self.position_in_flight) {
+            true => count - 1,
+            false => count,
         }
     }
 
     pub fn is_negative_space(&self, position: Position) -> bool {
-        *self.neighbor_count.get(position) > 0 && self.get(position).size == 0
+        self.neighbor_count(position) > 0 && self.get(position).size == 0
     }
 
     pub fn gated(&self, level: usize, from: Position, to: Position) -> bool {
@@ -80,9 +90,9 @@ impl Default for Board {
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Board {
-    pub board: TorusArray<BugStack>,
-    pub neighbor_count: TorusArray<u8>,
-    pub last_moved: Option<(Piece, Position)>,
+    pub board: HexGrid<BugStack>,
+    pub neighbor_count: HexGrid<u8>,
+    pub last_move: Option<LastMove>,
     pub positions: [Option<Position>; 48],
     pinned: [bool; 48],
 }
@@ -90,9 +100,9 @@ pub struct Board {
 impl Board {
     pub fn new() -> Self {
         Self {
-            board: TorusArray::new(BugStack::new()),
-            neighbor_count: TorusArray::new(0),
-            last_moved: None,
+            board: HexGrid::new(BugStack::new()),
+            neighbor_count: HexGrid::new(0),
+            last_move: None,
             positions: [None; 48],
             pinned: [false; 48],
         }
@@ -141,16 +151,54 @@ impl Board {
                 from: current.to_string(),
                 to: target.to_string(),
                 turn,
-                reason: "Trying to move a covered piece".to_string(),
+                reason: IllegalMoveReason::CoveredPiece,
             });
         }
 
         let removed_piece = self.remove(current);
         debug_assert_eq!(removed_piece, piece);
         self.insert(target, piece);
+        self.last_move = Some(LastMove::new_move(piece, current, target));
         Ok(())
     }
 
+    // The pillbug or mosquito of `color` that has to throw the piece from `current` to `target`,
+    // None if it's a piece of `color` that can get there on its own
+    pub fn thrower(
+        &self,
+        color: Color,
+        piece: Piece,
+        current: Position,
+        target: Position,
+        rules: &RuleSet,
+    ) -> Option<Piece> {
+        if piece.is_color(color)
+            && Bug::available_moves(current, self, rules)
+                .get(&current)
+                .is_some_and(|positions| positions.contains(&target))
+        {
+            return None;
+        }
+        self.positions_taken_around(current)
+            .filter(|pos| pos.is_neighbor(target))
+            .filter_map(|pos| self.top_piece(pos).map(|thrower| (pos, thrower)))
+            .filter(|(pos, thrower)| {
+                thrower.is_color(color) && !self.is_stunned(*thrower, *pos) && *thrower != piece
+            })
+            .find(|(pos, _)| {
+                Bug::available_abilities(*pos, self, rules)
+                    .get(&current)
+                    .is_some_and(|positions| positions.contains(&target))
+            })
+            .map(|(_, thrower)| thrower)
+    }
+
+    // Pieces that just moved can't move, be thrown or use their ability on this turn
+    pub fn is_stunned(&self, piece: Piece, position: Position) -> bool {
+        self.last_move
+            .is_some_and(|last_move| last_move.stuns(piece, position))
+    }
+
     pub fn remove(&mut self, position: Position) -> Piece {
         let bug_stack = self.board.get_mut(position);
         let piece = bug_stack.pop_piece();
@@ -165,19 +213,17 @@ impl Board {
         // issues on every turn
         //true
         // for this remove the return true and then implement your check in the loop
-        for r in 0..32 {
-            for q in 0..32 {
-                let position = Position::new(q, r);
-                let hex = self.board.get(position);
-                let neighbor_count = *self.neighbor_count.get(position);
-                let counted = self.positions_taken_around(position).count();
-                if counted != neighbor_count as usize {
-                    println!("Calculated: {counted} hashed: {neighbor_count}");
-                    println!("pos: {position}");
-                    println!("hex: {hex:?}");
-                    println!("{}", self);
-                    return false;
-                }
+        // every occupied position's neighbors have been counted, so they're all in the grid
+        for position in self.neighbor_count.positions() {
+            let hex = self.board.get(position);
+            let neighbor_count = *self.neighbor_count.get(position);
+            let counted = self.positions_taken_around(position).count();
+            if counted != neighbor_count as usize {
+                println!("Calculated: {counted} hashed: {neighbor_count}");
+                println!("pos: {position}");
+                println!("hex: {hex:?}");
+                println!("{}", self);
+                return false;
             }
         }
         true
@@ -319,50 +365,37 @@ impl Board {
         piece: Piece,
         current_position: Position,
         target_position: Position,
+        rules: &RuleSet,
     ) -> bool {
-        return match self.moves(color).get(&(piece, current_position)) {
+        match self.moves(color, rules).get(&(piece, current_position)) {
             None => false,
             Some(positions) => positions.contains(&target_position),
-        };
+        }
     }
 
-    pub fn moves(&self, color: Color) -> HashMap<(Piece, Position), Vec<Position>> {
+    pub fn moves(
+        &self,
+        color: Color,
+        rules: &RuleSet,
+    ) -> HashMap<(Piece, Position), Vec<Position>> {
+        let mut move_list = MoveList::new();
+        self.generate_moves(color, rules, &mut move_list);
         let mut moves: HashMap<(Piece, Position), Vec<Position>> = HashMap::default();
-        if !self.queen_played(color) {
-            return moves;
-        }
-        for pos in self.positions.iter().flatten() {
-            if let Some(piece) = self.top_piece(*pos) {
-                if piece.is_color(color) {
-                    // let's make sure pieces that were just moved cannot be moved again
-                    if let Some(last_moved) = self.last_moved {
-                        if last_moved == (piece, *pos) {
-                            // now we skip it
-                            continue;
-                        }
-                    }
-                    for (start_pos, target_positions) in Bug::available_moves(*pos, self) {
-                        if let Some(piece) = self.top_piece(start_pos) {
-                            if !target_positions.is_empty() {
-                                moves
-                                    .entry((piece, start_pos))
-                                    .or_default()
-                                    .append(&mut target_positions.clone());
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        if let Some(last_moved) = self.last_moved {
-            moves.remove(&last_moved);
+        for mv in move_list.iter() {
+            moves.entry((mv.piece, mv.from)).or_default().push(mv.to);
         }
         moves
     }
 
+    // Writes all moves of `color` into `moves`, reuse the list to avoid allocations
+    pub fn generate_moves(&self, color: Color, rules: &RuleSet, moves: &mut MoveList) {
+        moves.generate(self, color, rules);
+    }
+
     pub fn spawnable_positions(&self, color: Color) -> impl Iterator<Item = Position> + '_ {
+        // once a piece is on the board the initial position is only spawnable as negative space
         std::iter::once(Position::initial_spawn_position())
+            .filter(move |_| self.positions.iter().all(Option::is_none))
             .chain(self.negative_space())
             .filter(move |pos| self.spawnable(color, *pos))
     }
@@ -371,14 +404,8 @@ impl Board {
         self.piece_already_played(Piece::new_from(Bug::Queen, color, 0))
     }
 
-    pub fn queen_required(&self, turn: usize, color: Color) -> bool {
-        if turn == 6 && color == Color::White && !self.queen_played(Color::White) {
-            return true;
-        }
-        if turn == 7 && color == Color::Black && !self.queen_played(Color::Black) {
-            return true;
-        }
-        false
+    pub fn queen_required(&self, turn: usize, color: Color, rules: &RuleSet) -> bool {
+        rules.queen_required(turn) && !self.queen_played(color)
     }
 
     pub fn update_pinned(&mut self) {
@@ -452,26 +479,85 @@ impl Board {
             .filter_map(|pos| self.board.get(pos).top_piece())
     }
 
-    pub fn reserve(&self, color: Color, game_type: GameType) -> HashMap<Bug, Vec<String>> {
+    pub fn reserve(&self, color: Color, rules: &RuleSet) -> HashMap<Bug, Vec<String>> {
         let mut res = HashMap::<Bug, Vec<String>>::new();
         let start = 24 * color as usize;
         let end = 24 + start;
-        let bugs_for_game_type = Bug::bugs_count(game_type);
         for (i, maybe_pos) in self.positions[start..end].iter().enumerate() {
             if maybe_pos.is_none() {
                 let piece = self.offset_to_piece(i + 24 * color as usize);
-                if let Some(number_of_bugs) = bugs_for_game_type.get(&piece.bug()) {
-                    if (*number_of_bugs as usize) > (i % 3) {
-                        res.entry(piece.bug())
-                            .or_insert(Vec::new())
-                            .push(piece.to_string());
-                    }
+                if rules.bug_count(piece.bug()) > (i % 3) {
+                    res.entry(piece.bug()).or_default().push(piece.to_string());
                 }
             }
         }
         res
     }
 
+    pub fn position_key(&self) -> u64 {
+        // the positions of all pieces plus the order within stacks identify a position
+        let mut hasher = DefaultHasher::new();
+        self.positions.hash(&mut hasher);
+        for pos in self.positions.iter().flatten() {
+            let bug_stack = self.board.get(*pos);
+            if bug_stack.size > 1 {
+                bug_stack.pieces[..bug_stack.len()].hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+
+    // The occupied cells as (r, q, stack) with the board turned into `orientation`, sorted. Stacks
+    // are bottom to top, pieces are told apart by `key`.
+    pub fn oriented_cells<K: Ord>(
+        &self,
+        orientation: &Orientation,
+        key: impl Fn(Piece) -> K,
+    ) -> Vec<(i32, i32, Vec<K>)> {
+        let mut positions = self.all_taken_positions().collect::<Vec<_>>();
+        positions.sort();
+        positions.dedup();
+        let mut cells = positions
+            .into_iter()
+            .map(|position| {
+                let bug_stack = self.board.get(position);
+                let stack = bug_stack.pieces[..bug_stack.len()]
+                    .iter()
+                    .map(|piece| key(*piece))
+                    .collect();
+                let oriented = orientation.apply(position);
+                (oriented.r, oriented.q, stack)
+            })
+            .collect::<Vec<_>>();
+        cells.sort();
+        cells
+    }
+
+    // Of the 12 symmetries of the hex grid the one with the smallest cells, shifted so the
+    // smallest q and r of the hive are 0. The same position gets the same cells no matter how it
+    // is turned or where it is on the grid.
+    pub fn canonical_orientation<K: Ord>(&self, key: impl Fn(Piece) -> K) -> Orientation {
+        let positions = self.all_taken_positions().collect::<Vec<_>>();
+        (0..12)
+            .map(|symmetry| {
+                let (rotation, reflected) = (symmetry % 6, symmetry >= 6);
+                let turned = positions
+                    .iter()
+                    .map(|position| position.turned(rotation, reflected));
+                let min_q = turned.clone().map(|position| position.q).min().unwrap_or(0);
+                let min_r = turned.map(|position| position.r).min().unwrap_or(0);
+                let orientation = Orientation {
+                    rotation,
+                    reflected,
+                    shift: (-min_q, -min_r),
+                };
+                (self.oriented_cells(&orientation, &key), orientation)
+            })
+            .min_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, orientation)| orientation)
+            .unwrap_or_default()
+    }
+
     pub fn all_taken_positions(&self) -> impl Iterator<Item = Position> {
         // TODO this does not uniq!
         self.positions.into_iter().flatten()
@@ -489,13 +575,16 @@ impl Board {
         if number_of_positions == 1 {
             return self.is_negative_space(position);
         }
-        !self
-            .top_layer_neighbors(position)
-            .any(|piece| color == Color::from(piece.color().opposite()))
+        self.is_negative_space(position)
+            && !self
+                .top_layer_neighbors(position)
+                .any(|piece| color == Color::from(piece.color().opposite()))
     }
 
     pub fn negative_space(&self) -> impl Iterator<Item = Position> + '_ {
-        Self::all_positions().filter(move |pos| self.is_negative_space(*pos))
+        self.neighbor_count
+            .positions()
+            .filter(move |pos| self.is_negative_space(*pos))
     }
 
     pub fn is_negative_space(&self, position: Position) -> bool {
@@ -503,31 +592,41 @@ impl Board {
     }
 
     pub fn insert(&mut self, position: Position, piece: Piece) {
-        self.last_moved = Some((piece, position));
+        self.place(position, piece);
+        self.update_pinned();
+    }
+
+    // Puts the piece on top of the stack without updating which pieces are pinned
+    pub(crate) fn place(&mut self, position: Position, piece: Piece) {
+        self.last_move = Some(LastMove::new_spawn(piece, position));
         self.board.get_mut(position).push_piece(piece);
         self.set_position_of_piece(piece, position);
         if self.board.get(position).size == 1 {
             self.neighbor_count_add(position)
         }
-        self.update_pinned();
-    }
-
-    pub fn all_positions() -> impl Iterator<Item = Position> {
-        (0..BOARD_SIZE)
-            .cartesian_product(0..BOARD_SIZE)
-            .map(|(q, r)| Position { q, r })
     }
 }
 
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut s = "".to_string();
-        for r in 0..BOARD_SIZE {
+        // the rows and columns around the hive, odd rows are shifted right
+        let taken = self.all_taken_positions().collect::<Vec<_>>();
+        let rows = taken.iter().map(|position| position.r);
+        let columns = taken
+            .iter()
+            .map(|position| position.q + position.r.div_euclid(2));
+        let (min_r, max_r) = (rows.clone().min().unwrap_or(0), rows.max().unwrap_or(0));
+        let (min_c, max_c) = (
+            columns.clone().min().unwrap_or(0),
+            columns.max().unwrap_or(0),
+        );
+        for r in min_r - 1..=max_r + 1 {
             if r.rem_euclid(2) == 1 {
                 write!(s, "  ")?;
             }
-            for q in 0..BOARD_SIZE {
-                let bug_stack = self.board.get(Position::new(q - r / 2, r + 15));
+            for c in min_c - 1..=max_c + 1 {
+                let bug_stack = self.board.get(Position::new(c - r.div_euclid(2), r));
                 if let Some(last) = bug_stack.top_piece() {
                     if last.to_string().len() < 3 {
                         write!(s, "{last}  ")?;
@@ -547,7 +646,7 @@ impl fmt::Display for Board {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::direction::Direction;
+    use crate::{direction::Direction, game_type::GameType};
     use std::collections::HashSet;
 
     #[test]
@@ -614,31 +713,7 @@ mod tests {
 
     #[test]
     fn tests_top_layer_neighbors() {
-        let mut board = Board::new();
-        board.insert(
-            Position::new(0, 0),
-            Piece::new_from(Bug::Queen, Color::Black, 0),
-        );
-        board.insert(
-            Position::new(1, 0),
-            Piece::new_from(Bug::Ant, Color::Black, 1),
-        );
-        board.insert(
-            Position::new(2, 0),
-            Piece::new_from(Bug::Ant, Color::Black, 2),
-        );
-        board.insert(
-            Position::new(3, 0),
-            Piece::new_from(Bug::Ant, Color::Black, 3),
-        );
-        board.insert(
-            Position::new(4, 0),
-            Piece::new_from(Bug::Grasshopper, Color::Black, 1),
-        );
-        board.insert(
-            Position::new(3, 1),
-            Piece::new_from(Bug::Grasshopper, Color::Black, 2),
-        );
+        let board = crate::board!["bQ@0,0", "bA1@1,0", "bA2@2,0", "bA3@3,0", "bG1@4,0", "bG2@3,1",];
         assert_eq!(
             board
                 .top_layer_neighbors(Position::new(0, 0))
@@ -686,6 +761,88 @@ mod tests {
         assert_eq!(board.negative_space().count(), 8);
     }
 
+    #[test]
+    fn tests_mid_move_board() {
+        let pieces = [(0, 0, "wQ"), (1, 0, "bQ"), (-1, 0, "wA1"), (2, 0, "bA1")];
+        let board = Board::from_pieces(
+            pieces.map(|(q, r, piece)| (piece.parse().unwrap(), Position::new(q, r))),
+        )
+        .unwrap();
+        let ant = Position::new(-1, 0);
+        let mid_move = MidMoveBoard::new(&board, "wA1".parse().unwrap(), ant);
+        let mut lifted = board.clone();
+        lifted.remove(ant);
+        for position in board.neighbor_count.positions() {
+            assert_eq!(
+                mid_move.neighbor_count(position),
+                *lifted.neighbor_count.get(position),
+                "{position}"
+            );
+            assert_eq!(
+                mid_move.is_negative_space(position),
+                lifted.is_negative_space(position),
+                "{position}"
+            );
+        }
+    }
+
+    #[test]
+    fn tests_canonical_orientation() {
+        let entries = [(0, 0, "wQ"), (1, 0, "bQ"), (1, -1, "wA1"), (0, 1, "bB1")];
+        let board = |rotation: u8, reflected: bool, shift: i32| {
+            Board::from_pieces(entries.map(|(q, r, piece)| {
+                let position = Position::new(q, r).turned(rotation, reflected);
+                (
+                    piece.parse().unwrap(),
+                    Position::new(position.q + shift, position.r - shift),
+                )
+            }))
+            .unwrap()
+        };
+        let key = |piece: Piece| piece.to_string();
+        let canonical =
+            |board: &Board| board.oriented_cells(&board.canonical_orientation(key), key);
+        let cells = canonical(&board(0, false, 0));
+        assert_eq!(cells.len(), entries.len());
+        assert_eq!(cells.iter().map(|(r, _, _)| *r).min(), Some(0));
+        assert_eq!(cells.iter().map(|(_, q, _)| *q).min(), Some(0));
+        for rotation in 0..6 {
+            for reflected in [false, true] {
+                assert_eq!(canonical(&board(rotation, reflected, 7)), cells);
+            }
+        }
+        assert!(canonical(&Board::new()).is_empty());
+    }
+
+    #[test]
+    fn tests_no_wrap_around() {
+        // every piece of a game in one line, far longer than half of any fixed size board
+        let rules = RuleSet::new(GameType::MLP, true);
+        let mut board = Board::new();
+        let pieces = [Color::White, Color::Black]
+            .into_iter()
+            .flat_map(|color| board.reserve(color, &rules).into_values().flatten())
+            .map(|piece| piece.parse::<Piece>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(pieces.len(), 28);
+        for (i, piece) in pieces.iter().enumerate() {
+            board.insert(Position::new(-(i as i32), 0), *piece);
+        }
+        assert!(board.check());
+        assert!(board.is_connected());
+        // a row of 29 hexes above and below and one at each end
+        assert_eq!(board.negative_space().count(), 2 * 29 + 2);
+        assert!(!board.occupied(Position::new(1, 0)));
+        assert!(!board.occupied(Position::new(-28, 0)));
+        assert!(!Position::new(0, 0).is_neighbor(Position::new(-31, 0)));
+        assert_eq!(
+            board.position_of_piece(pieces[27]),
+            Some(Position::new(-27, 0))
+        );
+        board.last_move = None;
+        assert_eq!(Board::from_diagram(&board.diagram()).unwrap(), board);
+    }
+
     #[test]
     fn tests_spawnable_positions() {
         let mut board = Board::new();
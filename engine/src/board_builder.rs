@@ -0,0 +1,263 @@
+// Boards for tests and fixtures, built from a list of entries or from a diagram.
+//
+// Entries are "piece@q,r", the parentheses in "wA1@(0,0)" are optional. Entries on a position
+// that is already taken go on top of the stack:
+//
+//   board!["wQ@0,0", "bA1@1,0", "bB1@(0,0)"]
+//
+// Diagrams have one line per row and one cell per column. A cell is "." or the stack listed
+// bottom to top and separated by "/". Like in `Board`'s Display every odd row is shifted right
+// by half a cell, the indentation only helps reading and is ignored. The optional first line
+// "@ q,r" is the position of the first cell of the first line, it defaults to 0,0:
+//
+//   @ 0,0
+//   wQ   bA1     .
+//      .   wG1/bB1  .
+//
+// Coordinates may be negative. Both are checked for duplicated pieces and a connected hive,
+// the board has no last move.
+use crate::{board::Board, game_error::GameError, piece::Piece, position::Position};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+#[macro_export]
+macro_rules! board {
+    ($($entry:expr),* $(,)?) => {
+        $crate::board::Board::from_entries(&[$($entry),*])
+            .expect("The entries should make up a valid board")
+    };
+}
+
+fn invalid(reason: String) -> GameError {
+    GameError::InvalidPosition { reason }
+}
+
+fn parse_coordinates(s: &str) -> Result<(i32, i32), GameError> {
+    let s = s.trim();
+    let s = s
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .unwrap_or(s);
+    let (q, r) = s
+        .split_once(',')
+        .ok_or_else(|| invalid(format!("{s} is not a position like q,r")))?;
+    let coordinate = |c: &str| {
+        c.trim()
+            .parse::<i32>()
+            .map_err(|_| invalid(format!("{s} is not a position like q,r")))
+    };
+    Ok((coordinate(q)?, coordinate(r)?))
+}
+
+// The column of a row in offset coordinates, odd rows are shifted right by half a cell
+fn column(q: i32, r: i32) -> i32 {
+    q + r.div_euclid(2)
+}
+
+impl Board {
+    // Builds the board by putting the pieces on in order
+    pub fn from_pieces(
+        pieces: impl IntoIterator<Item = (Piece, Position)>,
+    ) -> Result<Board, GameError> {
+        let mut board = Board::new();
+        for (piece, position) in pieces {
+            if board.piece_already_played(piece) {
+                return Err(invalid(format!("{piece} is on the board twice")));
+            }
+            if board.level(position) == 7 {
+                return Err(invalid(format!("the stack at {position} is full")));
+            }
+            board.insert(position, piece);
+        }
+        board.last_move = None;
+        if !board.is_connected() {
+            return Err(invalid("the hive is not connected".to_string()));
+        }
+        Ok(board)
+    }
+
+    pub fn from_entries(entries: &[&str]) -> Result<Board, GameError> {
+        let pieces = entries
+            .iter()
+            .map(|entry| {
+                let (piece, position) = entry
+                    .split_once('@')
+                    .ok_or_else(|| invalid(format!("{entry} is not an entry like wA1@0,0")))?;
+                let (q, r) = parse_coordinates(position)?;
+                Ok((piece.trim().parse::<Piece>()?, Position::new(q, r)))
+            })
+            .collect::<Result<Vec<_>, GameError>>()?;
+        Board::from_pieces(pieces)
+    }
+
+    pub fn from_diagram(diagram: &str) -> Result<Board, GameError> {
+        let mut lines = diagram
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .peekable();
+        let (q0, r0) = match lines.peek().and_then(|line| line.trim().strip_prefix('@')) {
+            Some(origin) => {
+                let origin = parse_coordinates(origin)?;
+                lines.next();
+                origin
+            }
+            None => (0, 0),
+        };
+        let mut pieces = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let r = r0 + i as i32;
+            for (j, cell) in line.split_whitespace().enumerate() {
+                if cell == "." {
+                    continue;
+                }
+                let q = column(q0, r0) + j as i32 - r.div_euclid(2);
+                for piece in cell.split('/') {
+                    pieces.push((piece.parse::<Piece>()?, Position::new(q, r)));
+                }
+            }
+        }
+        Board::from_pieces(pieces)
+    }
+
+    // The board as a diagram `from_diagram` reads, e.g. for the output of failing tests
+    pub fn diagram(&self) -> String {
+        let mut stacks = BTreeMap::new();
+        for position in self.positions.iter().flatten() {
+            let bug_stack = self.board.get(*position);
+            let cell = bug_stack.pieces[..bug_stack.len()]
+                .iter()
+                .map(Piece::to_string)
+                .collect::<Vec<_>>()
+                .join("/");
+            stacks.insert((position.r, position.q), cell);
+        }
+        let Some(((min_r, _), _)) = stacks.first_key_value() else {
+            return "@ 0,0\n".to_string();
+        };
+        let min_r = *min_r;
+        let max_r = stacks.keys().map(|(r, _)| *r).max().unwrap_or(min_r);
+        let columns = stacks.keys().map(|(r, q)| column(*q, *r));
+        let (min_column, max_column) = (columns.clone().min(), columns.max());
+        let (min_column, max_column) = (min_column.unwrap_or(0), max_column.unwrap_or(0));
+        let width = stacks.values().map(String::len).max().unwrap_or(1).max(3) + 1;
+        let mut diagram = format!("@ {},{min_r}\n", min_column - min_r.div_euclid(2));
+        for r in min_r..=max_r {
+            let mut line = " ".repeat(if r.rem_euclid(2) == 1 { width / 2 } else { 0 });
+            for c in min_column..=max_column {
+                let cell = stacks
+                    .get(&(r, c - r.div_euclid(2)))
+                    .map_or(".", String::as_str);
+                line += &format!("{cell:<width$}");
+            }
+            diagram += line.trim_end();
+            diagram.push('\n');
+        }
+        diagram
+    }
+
+    pub fn is_connected(&self) -> bool {
+        let occupied = self.positions.iter().flatten().collect::<HashSet<_>>();
+        let Some(start) = occupied.iter().next().copied() else {
+            return true;
+        };
+        let mut reached = HashSet::from([*start]);
+        let mut queue = VecDeque::from([*start]);
+        while let Some(position) = queue.pop_front() {
+            for next in self.positions_taken_around(position) {
+                if reached.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        reached.len() == occupied.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bug::Bug, color::Color, direction::Direction, history::History, state::State};
+
+    #[test]
+    fn tests_from_entries() {
+        let board = crate::board!["wQ@0,0", "bA1@(1,0)", "bB1@ (0, 0)", "wG1@-1,0"];
+        let queen = Piece::new_from(Bug::Queen, Color::White, 0);
+        let beetle = Piece::new_from(Bug::Beetle, Color::Black, 1);
+        assert_eq!(board.bottom_piece(Position::new(0, 0)), Some(queen));
+        assert_eq!(board.top_piece(Position::new(0, 0)), Some(beetle));
+        assert_eq!(board.level(Position::new(0, 0)), 2);
+        assert_eq!(board.last_move, None);
+        assert!(board.check());
+        // pins are up to date
+        let line = crate::board!["wG1@-1,0", "wQ@0,0", "bA1@1,0"];
+        assert!(line.is_pinned(queen));
+        assert!(!line.is_pinned(Piece::new_from(Bug::Ant, Color::Black, 1)));
+
+        for invalid in [
+            vec!["wQ@0,0", "bQ@5,5"],
+            vec!["wQ@0,0", "wQ@1,0"],
+            vec!["wQ 0,0"],
+            vec!["wQ@0"],
+            vec!["xQ@0,0"],
+        ] {
+            assert!(Board::from_entries(&invalid).is_err(), "{invalid:?}");
+        }
+        assert_eq!(Board::from_entries(&[]).unwrap(), Board::new());
+    }
+
+    #[test]
+    fn tests_from_diagram() {
+        let board = Board::from_diagram(
+            "
+            @ -1,0
+            wG1  wQ   bA1
+               .   bB1/wB1
+            ",
+        )
+        .unwrap();
+        let entries = crate::board!["wG1@-1,0", "wQ@0,0", "bA1@1,0", "bB1@0,1", "wB1@0,1"];
+        assert_eq!(board, entries);
+        // odd rows are shifted right: below an even row the first cell is south east of the
+        // first cell of the row above
+        let board = Board::from_diagram("wQ\nbQ").unwrap();
+        assert_eq!(
+            board.position_of_piece("bQ".parse().unwrap()),
+            Some(Position::new(0, 0).to(Direction::SE))
+        );
+        // and below an odd row it's south west of it
+        let board = Board::from_diagram("@ 0,1\nwQ\nbQ").unwrap();
+        assert_eq!(
+            board.position_of_piece("bQ".parse().unwrap()),
+            Some(Position::new(0, 1).to(Direction::SW))
+        );
+        assert!(Board::from_diagram("wQ . bQ").is_err());
+        assert!(Board::from_diagram("@ x,0\nwQ").is_err());
+        assert_eq!(Board::from_diagram("").unwrap(), Board::new());
+    }
+
+    #[test]
+    fn tests_diagram_round_trip() {
+        for file in [
+            "./test_pgns/valid/descend.pgn",
+            "./test_pgns/valid/p_game.pgn",
+            "./test_pgns/valid/plm_draw.pgn",
+        ] {
+            let history = History::from_filepath(file).unwrap();
+            let mut state = State::new(history.game_type, true);
+            for (piece, pos) in history.moves.iter() {
+                state.play_turn_from_notation(piece, pos).unwrap();
+                let diagram = state.board.diagram();
+                let board = Board::from_diagram(&diagram).unwrap();
+                assert_eq!(
+                    board.position_key(),
+                    state.board.position_key(),
+                    "{file}\n{diagram}"
+                );
+                assert_eq!(board.diagram(), diagram);
+            }
+        }
+        assert_eq!(
+            Board::from_diagram(&Board::new().diagram()).unwrap(),
+            Board::new()
+        );
+    }
+}
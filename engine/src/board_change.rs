@@ -0,0 +1,500 @@
+use crate::{
+    board::Board, game_error::GameError, last_move::LastMove, piece::Piece, position::Position,
+    state::State,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+// a position and a level
+type Place = (Position, usize);
+
+// What changed between two boards, levels count from 1 at the bottom of a stack like
+// `Board::level`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoardChange {
+    Placed {
+        piece: Piece,
+        position: Position,
+        level: usize,
+    },
+    Moved {
+        piece: Piece,
+        from: Position,
+        from_level: usize,
+        to: Position,
+        to_level: usize,
+    },
+    // a pillbug or mosquito moved the piece, thrown pieces are always on the ground
+    Thrown {
+        piece: Piece,
+        by: Piece,
+        from: Position,
+        to: Position,
+    },
+    // only between boards that aren't from the same game or when going back in a game
+    Removed {
+        piece: Piece,
+        position: Position,
+        level: usize,
+    },
+    // the piece stays where it is but `by` is on top of it now
+    Covered {
+        position: Position,
+        piece: Piece,
+        by: Piece,
+    },
+    // the piece is on top of its stack again
+    Uncovered {
+        position: Position,
+        piece: Piece,
+    },
+}
+
+impl BoardChange {
+    // The piece with where it was and where it is, None for changes to stacks
+    fn piece_change(&self) -> Option<(Piece, Option<Place>, Option<Place>)> {
+        match *self {
+            BoardChange::Placed {
+                piece,
+                position,
+                level,
+            } => Some((piece, None, Some((position, level)))),
+            BoardChange::Moved {
+                piece,
+                from,
+                from_level,
+                to,
+                to_level,
+            } => Some((piece, Some((from, from_level)), Some((to, to_level)))),
+            BoardChange::Thrown {
+                piece, from, to, ..
+            } => Some((piece, Some((from, 1)), Some((to, 1)))),
+            BoardChange::Removed {
+                piece,
+                position,
+                level,
+            } => Some((piece, Some((position, level)), None)),
+            BoardChange::Covered { .. } | BoardChange::Uncovered { .. } => None,
+        }
+    }
+}
+
+fn invalid(reason: String) -> GameError {
+    GameError::InvalidPosition { reason }
+}
+
+impl Board {
+    // Where every piece is, as position and level
+    fn stacked_pieces(&self) -> HashMap<Piece, Place> {
+        self.positions
+            .iter()
+            .flatten()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .flat_map(|position| {
+                let bug_stack = self.board.get(*position);
+                bug_stack.pieces[..bug_stack.len()]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, piece)| (*piece, (*position, i + 1)))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    // The changes that turn this board into `other`. Pieces that were placed, moved, thrown or
+    // removed come first, ordered by piece, followed by the stacks that got covered or
+    // uncovered, ordered by position.
+    pub fn diff(&self, other: &Board) -> Vec<BoardChange> {
+        let before = self.stacked_pieces();
+        let after = other.stacked_pieces();
+        let mut pieces = before
+            .keys()
+            .chain(after.keys())
+            .copied()
+            .collect::<Vec<_>>();
+        pieces.sort_by_key(|piece| self.piece_to_offset(*piece));
+        pieces.dedup();
+        let mut changes = Vec::new();
+        for piece in pieces {
+            let change = match (before.get(&piece), after.get(&piece)) {
+                (None, Some(&(position, level))) => BoardChange::Placed {
+                    piece,
+                    position,
+                    level,
+                },
+                (Some(&(position, level)), None) => BoardChange::Removed {
+                    piece,
+                    position,
+                    level,
+                },
+                (Some(&(from, from_level)), Some(&(to, to_level))) if from != to => {
+                    match other.last_move {
+                        Some(LastMove {
+                            piece: moved,
+                            to: last_to,
+                            thrown_by: Some(by),
+                            ..
+                        }) if moved == piece && last_to == to => BoardChange::Thrown {
+                            piece,
+                            by,
+                            from,
+                            to,
+                        },
+                        _ => BoardChange::Moved {
+                            piece,
+                            from,
+                            from_level,
+                            to,
+                            to_level,
+                        },
+                    }
+                }
+                (Some(&(from, from_level)), Some(&(to, to_level))) if from_level != to_level => {
+                    BoardChange::Moved {
+                        piece,
+                        from,
+                        from_level,
+                        to,
+                        to_level,
+                    }
+                }
+                _ => continue,
+            };
+            changes.push(change);
+        }
+        let positions = before
+            .values()
+            .chain(after.values())
+            .map(|(position, _)| *position)
+            .collect::<BTreeSet<_>>();
+        for position in positions {
+            let (old_top, new_top) = (self.top_piece(position), other.top_piece(position));
+            if old_top == new_top {
+                continue;
+            }
+            if let (Some(piece), Some(by)) = (old_top, new_top) {
+                if after.get(&piece).is_some_and(|(at, _)| *at == position) {
+                    changes.push(BoardChange::Covered {
+                        position,
+                        piece,
+                        by,
+                    });
+                }
+            }
+            if let Some(piece) = new_top {
+                if before.get(&piece).is_some_and(|(at, _)| *at == position) {
+                    changes.push(BoardChange::Uncovered { position, piece });
+                }
+            }
+        }
+        changes
+    }
+
+    // Applies changes as returned by `diff`, the board is left untouched when they don't start
+    // from this board. The piece that was placed, moved or thrown becomes the last move when
+    // there is exactly one.
+    pub fn apply_diff(&mut self, changes: &[BoardChange]) -> Result<(), GameError> {
+        let mut pieces = self.stacked_pieces();
+        let mut last_moves = Vec::new();
+        for change in changes {
+            let Some((piece, from, to)) = change.piece_change() else {
+                continue;
+            };
+            if pieces.get(&piece).copied() != from {
+                return Err(invalid(format!(
+                    "{piece} isn't where {change:?} expects it"
+                )));
+            }
+            match to {
+                Some(to) => pieces.insert(piece, to),
+                None => pieces.remove(&piece),
+            };
+            if let Some((to, _)) = to {
+                let last_move = match (change, from) {
+                    (BoardChange::Thrown { by, .. }, Some((from, _))) => LastMove {
+                        thrown_by: Some(*by),
+                        ..LastMove::new_move(piece, from, to)
+                    },
+                    (_, Some((from, _))) => LastMove::new_move(piece, from, to),
+                    (_, None) => LastMove::new_spawn(piece, to),
+                };
+                last_moves.push(last_move);
+            }
+        }
+        let mut stacks = pieces.into_iter().collect::<Vec<_>>();
+        stacks.sort_by_key(|(_, (position, level))| (*position, *level));
+        let mut board = Board::new();
+        for (piece, (position, level)) in stacks {
+            if board.level(position) + 1 != level {
+                return Err(invalid(format!(
+                    "{piece} would be at level {level} of a stack of {}",
+                    board.level(position)
+                )));
+            }
+            board.insert(position, piece);
+        }
+        board.last_move = match last_moves[..] {
+            [last_move] => Some(last_move),
+            _ => None,
+        };
+        if self.diff(&board) != changes {
+            return Err(invalid(
+                "the changes don't match the stacks they leave".to_string(),
+            ));
+        }
+        *self = board;
+        Ok(())
+    }
+
+    // Applies changes that were made on this board, like the changes of a turn played on it,
+    // without checking them like `apply_diff`, so pins are only updated once
+    pub fn replay(&mut self, changes: &[BoardChange]) {
+        self.last_move = None;
+        for change in changes {
+            match *change {
+                BoardChange::Placed {
+                    piece, position, ..
+                } => self.place(position, piece),
+                BoardChange::Moved {
+                    piece, from, to, ..
+                } => {
+                    self.remove(from);
+                    self.place(to, piece);
+                    self.last_move = Some(LastMove::new_move(piece, from, to));
+                }
+                BoardChange::Thrown {
+                    piece,
+                    by,
+                    from,
+                    to,
+                } => {
+                    self.remove(from);
+                    self.place(to, piece);
+                    self.last_move = Some(LastMove {
+                        thrown_by: Some(by),
+                        ..LastMove::new_move(piece, from, to)
+                    });
+                }
+                BoardChange::Removed {
+                    piece, position, ..
+                } => {
+                    self.remove(position);
+                    self.positions[self.piece_to_offset(piece)] = None;
+                    self.last_move = None;
+                }
+                BoardChange::Covered { .. } | BoardChange::Uncovered { .. } => {}
+            }
+        }
+        self.update_pinned();
+    }
+}
+
+impl State {
+    // Plays the turn the changes describe, e.g. a diff a server sent after a move. No changes
+    // is a pass. The turn has to be legal and leave the board exactly as the changes say.
+    pub fn apply_diff(&mut self, changes: &[BoardChange]) -> Result<(), GameError> {
+        let turns = changes
+            .iter()
+            .filter_map(BoardChange::piece_change)
+            .collect::<Vec<_>>();
+        let mut next = self.clone();
+        match turns[..] {
+            [] => next.play_turn_from_notation("pass", "")?,
+            [(piece, _, Some((to, _)))] => next.play_turn(piece, to)?,
+            _ => {
+                return Err(invalid(format!(
+                    "{} pieces changed, a turn changes one",
+                    turns.len()
+                )))
+            }
+        }
+        if self.board.diff(&next.board) != changes {
+            return Err(invalid(
+                "the turn doesn't change the board like the diff".to_string(),
+            ));
+        }
+        *self = next;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bug::Bug, color::Color, game_type::GameType, history::History};
+
+    fn piece(s: &str) -> Piece {
+        s.parse().unwrap()
+    }
+
+    fn states(file: &str) -> Vec<State> {
+        let history = History::from_filepath(file).unwrap();
+        let mut state = State::new(history.game_type, true);
+        let mut states = vec![state.clone()];
+        for (piece, pos) in history.moves.iter() {
+            state.play_turn_from_notation(piece, pos).unwrap();
+            states.push(state.clone());
+        }
+        states
+    }
+
+    #[test]
+    fn tests_diff_replays_games() {
+        for file in [
+            "./test_pgns/valid/descend.pgn",
+            "./test_pgns/valid/p_game.pgn",
+            "./test_pgns/valid/pass2.pgn",
+            "./test_pgns/valid/plm_draw.pgn",
+        ] {
+            let states = states(file);
+            let mut client = states[0].clone();
+            let mut board = states[0].board.clone();
+            let mut replayed = states[0].board.clone();
+            for pair in states.windows(2) {
+                let changes = pair[0].board.diff(&pair[1].board);
+                client.apply_diff(&changes).unwrap();
+                board.apply_diff(&changes).unwrap();
+                replayed.replay(&changes);
+                assert_eq!(replayed, pair[1].board);
+                assert_eq!(client.board.position_key(), pair[1].board.position_key());
+                assert_eq!(board.position_key(), pair[1].board.position_key());
+                assert_eq!(client.turn, pair[1].turn);
+                assert_eq!(client.history.moves, pair[1].history.moves);
+            }
+            // going back to the start removes every piece
+            let changes = board.diff(&Board::new());
+            assert!(changes
+                .iter()
+                .all(|change| matches!(change, BoardChange::Removed { .. })));
+            board.apply_diff(&changes).unwrap();
+            assert_eq!(board, Board::new());
+        }
+    }
+
+    #[test]
+    fn tests_diff_changes() {
+        let mut board = Board::new();
+        let queen = Piece::new_from(Bug::Queen, Color::White, 0);
+        let beetle = Piece::new_from(Bug::Beetle, Color::Black, 1);
+        board.insert(Position::new(0, 0), queen);
+        let before = board.clone();
+        board.insert(Position::new(1, 0), beetle);
+        assert_eq!(
+            before.diff(&board),
+            vec![BoardChange::Placed {
+                piece: beetle,
+                position: Position::new(1, 0),
+                level: 1
+            }]
+        );
+        let before = board.clone();
+        board
+            .move_piece(beetle, Position::new(1, 0), Position::new(0, 0), 2)
+            .unwrap();
+        let on_top = board.clone();
+        assert_eq!(
+            before.diff(&board),
+            vec![
+                BoardChange::Moved {
+                    piece: beetle,
+                    from: Position::new(1, 0),
+                    from_level: 1,
+                    to: Position::new(0, 0),
+                    to_level: 2
+                },
+                BoardChange::Covered {
+                    position: Position::new(0, 0),
+                    piece: queen,
+                    by: beetle
+                }
+            ]
+        );
+        board
+            .move_piece(beetle, Position::new(0, 0), Position::new(0, 1), 3)
+            .unwrap();
+        assert_eq!(
+            on_top.diff(&board),
+            vec![
+                BoardChange::Moved {
+                    piece: beetle,
+                    from: Position::new(0, 0),
+                    from_level: 2,
+                    to: Position::new(0, 1),
+                    to_level: 1
+                },
+                BoardChange::Uncovered {
+                    position: Position::new(0, 0),
+                    piece: queen
+                }
+            ]
+        );
+        assert!(board.diff(&board.clone()).is_empty());
+    }
+
+    #[test]
+    fn tests_diff_thrown() {
+        let states = states("./test_pgns/valid/descend.pgn");
+        let thrown = states
+            .windows(2)
+            .find(|pair| {
+                pair[1]
+                    .board
+                    .last_move
+                    .is_some_and(|last_move| last_move.was_thrown())
+            })
+            .expect("The game has a throw");
+        let changes = thrown[0].board.diff(&thrown[1].board);
+        assert!(matches!(changes[..], [BoardChange::Thrown { .. }]));
+        let mut board = thrown[0].board.clone();
+        board.apply_diff(&changes).unwrap();
+        assert_eq!(board.last_move, thrown[1].board.last_move);
+        let mut replayed = thrown[0].board.clone();
+        replayed.replay(&changes);
+        assert_eq!(replayed, thrown[1].board);
+    }
+
+    #[test]
+    fn tests_apply_diff_rejects() {
+        let mut state = State::new(GameType::Base, true);
+        state.play_turn_from_notation("wA1", ".").unwrap();
+        let before = state.clone();
+        // the queen can't be played on the first turn of a player
+        let queen = BoardChange::Placed {
+            piece: piece("bQ"),
+            position: Position::new(1, 0),
+            level: 1,
+        };
+        assert!(state.apply_diff(&[queen]).is_err());
+        // a piece that isn't on the board
+        let moved = BoardChange::Moved {
+            piece: piece("bA1"),
+            from: Position::new(1, 0),
+            from_level: 1,
+            to: Position::new(2, 0),
+            to_level: 1,
+        };
+        assert!(state.apply_diff(&[moved]).is_err());
+        assert!(state.board.clone().apply_diff(&[moved]).is_err());
+        // two pieces at once
+        let spawn = |piece, q| BoardChange::Placed {
+            piece,
+            position: Position::new(q, 0),
+            level: 1,
+        };
+        let two = [spawn(piece("bA1"), 1), spawn(piece("bG1"), 2)];
+        assert!(state.apply_diff(&two).is_err());
+        // a level that doesn't exist
+        let floating = BoardChange::Placed {
+            piece: piece("bA1"),
+            position: Position::new(1, 0),
+            level: 2,
+        };
+        assert!(state.board.clone().apply_diff(&[floating]).is_err());
+        // black can't pass
+        assert!(state.apply_diff(&[]).is_err());
+        assert_eq!(state, before);
+        state.apply_diff(&[spawn(piece("bA1"), 1)]).unwrap();
+        assert_eq!(state.turn, 2);
+    }
+}
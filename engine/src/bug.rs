@@ -1,11 +1,11 @@
 use crate::board::MidMoveBoard;
 use crate::{
-    board::Board, game_error::GameError, game_type::GameType, position::Position,
-    torus_array::TorusArray,
+    board::Board, game_error::GameError, game_type::GameType, hex_grid::HexGrid, move_list::Move,
+    position::Position, rule_set::RuleSet,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::{collections::HashSet, fmt, str::FromStr};
+use std::{fmt, str::FromStr};
 
 #[derive(Hash, Eq, PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
 #[repr(u8)]
@@ -74,6 +74,10 @@ impl Bug {
         &self.name()[0..=0]
     }
 
+    pub fn all() -> impl Iterator<Item = Bug> {
+        (0..8).map(Bug::from)
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             Bug::Ant => "Ant",
@@ -148,45 +152,95 @@ impl Bug {
         }
     }
 
-    pub fn available_moves(position: Position, board: &Board) -> HashMap<Position, Vec<Position>> {
+    pub fn available_moves(
+        position: Position,
+        board: &Board,
+        rules: &RuleSet,
+    ) -> HashMap<Position, Vec<Position>> {
         let mut moves = HashMap::default();
         if !board.is_pinned(
             board
                 .top_piece(position)
                 .expect("There must be something at this position"),
         ) {
-            let positions = match board.top_bug(position) {
-                Some(Bug::Ant) => Bug::ant_moves(position, board),
-                Some(Bug::Beetle) => Bug::beetle_moves(position, board),
-                Some(Bug::Grasshopper) => Bug::grasshopper_moves(position, board),
-                Some(Bug::Ladybug) => Bug::ladybug_moves(position, board),
-                Some(Bug::Mosquito) => Bug::mosquito_moves(position, board),
-                Some(Bug::Pillbug) => Bug::pillbug_moves(position, board).collect(),
-                Some(Bug::Queen) => Bug::queen_moves(position, board).collect(),
-                Some(Bug::Spider) => Bug::spider_moves(position, board),
-                None => Vec::new(),
-            };
+            let mut positions = Vec::new();
+            Bug::push_targets(position, board, &mut positions, &mut HexGrid::new(false));
             moves.insert(position, positions);
         }
-        moves.extend(Bug::available_abilities(position, board));
+        moves.extend(Bug::available_abilities(position, board, rules));
         moves
     }
 
+    // Appends the positions the bug on top of `position` can move to by itself, `found` is
+    // scratch space that is reused between calls
+    pub fn push_targets(
+        position: Position,
+        board: &Board,
+        targets: &mut Vec<Position>,
+        found: &mut HexGrid<bool>,
+    ) {
+        match board.top_bug(position) {
+            Some(Bug::Ant) => Bug::ant_moves(position, board, targets, found),
+            Some(Bug::Beetle) => Bug::beetle_moves(position, board, targets),
+            Some(Bug::Grasshopper) => Bug::grasshopper_moves(position, board, targets),
+            Some(Bug::Ladybug) => Bug::ladybug_moves(position, board, targets),
+            Some(Bug::Mosquito) => Bug::mosquito_moves(position, board, targets, found),
+            Some(Bug::Pillbug) => targets.extend(Bug::pillbug_moves(position, board)),
+            Some(Bug::Queen) => targets.extend(Bug::queen_moves(position, board)),
+            Some(Bug::Spider) => Bug::spider_moves(position, board, targets),
+            None => {}
+        }
+    }
+
     pub fn available_abilities(
         position: Position,
         board: &Board,
+        rules: &RuleSet,
     ) -> HashMap<Position, Vec<Position>> {
+        let mut throws = Vec::new();
+        Bug::push_abilities(position, board, rules, &mut throws);
+        let mut moves: HashMap<Position, Vec<Position>> = HashMap::default();
+        for throw in throws {
+            moves.entry(throw.from).or_default().push(throw.to);
+        }
+        moves
+    }
+
+    // Appends the moves of other pieces the bug on top of `position` can make with its ability
+    pub fn push_abilities(
+        position: Position,
+        board: &Board,
+        rules: &RuleSet,
+        moves: &mut Vec<Move>,
+    ) {
         match board.top_bug(position) {
-            Some(Bug::Pillbug) => Bug::pillbug_throw(position, board),
+            Some(Bug::Pillbug) if rules.pillbug_ability => {
+                Bug::pillbug_throw(position, board, moves)
+            }
             Some(Bug::Mosquito)
-                if board.level(position) == 1 && board.neighbor_is_a(position, Bug::Pillbug) =>
+                if rules.mosquito_ability
+                    && board.level(position) == 1
+                    && board.neighbor_is_a(position, Bug::Pillbug) =>
             {
-                Bug::pillbug_throw(position, board)
+                Bug::pillbug_throw(position, board, moves)
             }
-            _ => HashMap::default(),
+            _ => {}
         }
     }
 
+    // Removes the duplicates from targets[start..], keeping the first occurrence
+    fn dedup_from(targets: &mut Vec<Position>, start: usize) {
+        let mut end = start;
+        for i in start..targets.len() {
+            let pos = targets[i];
+            if !targets[start..end].contains(&pos) {
+                targets[end] = pos;
+                end += 1;
+            }
+        }
+        targets.truncate(end);
+    }
+
     fn crawl_negative_space<'a>(
         position: Position,
         board: &'a MidMoveBoard<'a>,
@@ -197,17 +251,37 @@ impl Bug {
             .filter(move |pos| !board.gated(1, position, *pos))
     }
 
+    pub fn crawl_distance(position: Position, target: Position, board: &Board) -> Option<usize> {
+        // breadth first search around the hive, with the piece at position lifted up, the queue
+        // keeps every found position so it is also the set of visited ones
+        let board = MidMoveBoard::new(board, board.top_piece(position)?, position);
+        let mut queue = vec![position];
+        let (mut next, mut steps, mut step_end) = (0, 0, 1);
+        while next < queue.len() {
+            if next == step_end {
+                steps += 1;
+                step_end = queue.len();
+            }
+            let pos = queue[next];
+            if pos == target {
+                return Some(steps);
+            }
+            for pos in Bug::crawl_negative_space(pos, &board) {
+                if !queue.contains(&pos) {
+                    queue.push(pos);
+                }
+            }
+            next += 1;
+        }
+        None
+    }
+
     fn crawl(position: Position, board: &Board) -> impl Iterator<Item = Position> + '_ {
         board.positions_taken_around(position).flat_map(move |pos| {
-            let mut positions = vec![];
             let (pos1, pos2) = position.common_adjacent_positions(pos);
-            if !board.gated(1, position, pos1) && !board.occupied(pos1) {
-                positions.push(pos1);
-            }
-            if !board.gated(1, position, pos2) && !board.occupied(pos2) {
-                positions.push(pos2);
-            }
-            positions
+            [pos1, pos2]
+                .into_iter()
+                .filter(move |p| !board.gated(1, position, *p) && !board.occupied(*p))
         })
     }
 
@@ -224,65 +298,46 @@ impl Bug {
         })
     }
 
-    fn ant_moves(position: Position, board: &Board) -> Vec<Position> {
-        //                               found  explored
-        let mut state = TorusArray::new((false, false));
-        state.set(position, (true, true));
-        let board = MidMoveBoard::new(board, board.top_piece(position).unwrap(), position);
-        let mut found_pos = Vec::with_capacity(24);
-        let mut unexplored = Vec::with_capacity(24);
-        unexplored.push(position);
-        Bug::ant_rec(&mut state, &mut found_pos, &mut unexplored, &board);
-        found_pos
-    }
-
-    fn ant_rec(
-        state: &mut TorusArray<(bool, bool)>,
-        found_pos: &mut Vec<Position>,
-        unexplored: &mut Vec<Position>,
-        board: &MidMoveBoard,
+    fn ant_moves(
+        position: Position,
+        board: &Board,
+        targets: &mut Vec<Position>,
+        found: &mut HexGrid<bool>,
     ) {
-        while let Some(position) = unexplored.pop() {
-            let (found, explored) = state.get(position);
-            if !found {
-                state.set(position, (true, *explored));
-                found_pos.push(position);
-            }
-            for pos in Bug::crawl_negative_space(position, board) {
-                let (found, explored) = state.get(pos);
-                if !explored && !found && board.is_negative_space(pos) {
-                    state.set(pos, (*found, true));
-                    unexplored.push(pos);
+        // breadth first search that uses the found positions as its queue
+        let board = MidMoveBoard::new(board, board.top_piece(position).unwrap(), position);
+        // the ant never leaves the grid of neighbor counts, so `found` only grows with the hive
+        found.reset_to_bounds_of(&board.board.neighbor_count);
+        found.set(position, true);
+        let start = targets.len();
+        targets.push(position);
+        let mut next = start;
+        while next < targets.len() {
+            for pos in Bug::crawl_negative_space(targets[next], &board) {
+                if !found.get(pos) {
+                    found.set(pos, true);
+                    targets.push(pos);
                 }
             }
+            next += 1;
         }
+        // the ant can't stay where it is
+        targets.remove(start);
     }
 
-    pub fn beetle_moves(position: Position, board: &Board) -> Vec<Position> {
-        let mut positions = Vec::new();
-        for pos in Bug::climb(position, board) {
-            positions.push(pos);
-        }
+    pub fn beetle_moves(position: Position, board: &Board, targets: &mut Vec<Position>) {
+        let start = targets.len();
+        targets.extend(Bug::climb(position, board));
         if board.level(position) == 1 {
-            for pos in Bug::crawl(position, board) {
-                if !positions.contains(&pos) {
-                    positions.push(pos);
-                }
-            }
+            targets.extend(Bug::crawl(position, board));
         } else {
-            for pos in Bug::descend(position, board) {
-                if !positions.contains(&pos) {
-                    positions.push(pos);
-                }
-            }
+            targets.extend(Bug::descend(position, board));
         }
-        positions
+        Bug::dedup_from(targets, start);
     }
 
-    pub fn grasshopper_moves(position: Position, board: &Board) -> Vec<Position> {
-        // get the directions of the grasshopper's neighbors
-        let mut positions = vec![];
-        // move in the given direction
+    pub fn grasshopper_moves(position: Position, board: &Board, targets: &mut Vec<Position>) {
+        // move in the direction of each of the grasshopper's neighbors
         for dir in board
             .positions_taken_around(position)
             .map(|pos| position.direction(pos))
@@ -293,107 +348,135 @@ impl Bug {
                 cur_pos = cur_pos.to(dir);
             }
             // then add the free position
-            positions.push(cur_pos.to(dir));
+            targets.push(cur_pos.to(dir));
         }
-        positions
     }
 
-    fn ladybug_moves(position: Position, board: &Board) -> Vec<Position> {
+    fn ladybug_moves(position: Position, board: &Board, targets: &mut Vec<Position>) {
+        let start = targets.len();
         // find all adjacent bugs to climb on
-        let first = Bug::climb(position, board);
-        // stay on top of the hive by performing another climb
-        let second: HashSet<Position> = first
-            .flat_map(|first_pos| {
-                Bug::climb(first_pos, board)
-                    .filter(|pos| *pos != position && *pos != first_pos)
-                    .collect::<HashSet<Position>>()
-            })
-            .collect::<HashSet<Position>>();
-        // then find available and ungated positions
-        let third: HashSet<Position> = second
-            .iter()
-            .flat_map(|pos| {
-                board
-                    .positions_available_around(*pos)
-                    .filter(|p| !board.gated(board.level(*pos) + 1, *pos, *p) && *p != position)
-                    .collect::<HashSet<Position>>()
-            })
-            .collect::<HashSet<Position>>();
-        return third.iter().cloned().collect();
-    }
-
-    fn mosquito_moves(position: Position, board: &Board) -> Vec<Position> {
-        return if board.level(position) == 1 {
-            board
-                .neighbors(position)
-                .flat_map(|pieces| {
-                    match pieces.top_piece().expect("Could not get last piece").bug() {
-                        Bug::Ant => Bug::ant_moves(position, board),
-                        Bug::Beetle => Bug::beetle_moves(position, board),
-                        Bug::Grasshopper => Bug::grasshopper_moves(position, board),
-                        Bug::Ladybug => Bug::ladybug_moves(position, board),
-                        Bug::Mosquito => vec![],
-                        Bug::Pillbug => Bug::pillbug_moves(position, board).collect(),
-                        Bug::Queen => Bug::queen_moves(position, board).collect(),
-                        Bug::Spider => Bug::spider_moves(position, board),
-                    }
-                })
-                .collect()
+        for first in Bug::climb(position, board) {
+            // stay on top of the hive by performing another climb
+            for second in Bug::climb(first, board).filter(|pos| *pos != position && *pos != first) {
+                // then find available and ungated positions
+                targets.extend(board.positions_available_around(second).filter(|p| {
+                    !board.gated(board.level(second) + 1, second, *p) && *p != position
+                }));
+            }
+        }
+        Bug::dedup_from(targets, start);
+    }
+
+    fn mosquito_moves(
+        position: Position,
+        board: &Board,
+        targets: &mut Vec<Position>,
+        found: &mut HexGrid<bool>,
+    ) {
+        if board.level(position) == 1 {
+            let start = targets.len();
+            for pos in board.positions_taken_around(position) {
+                match board.top_bug(pos).expect("Could not get last piece") {
+                    Bug::Ant => Bug::ant_moves(position, board, targets, found),
+                    Bug::Beetle => Bug::beetle_moves(position, board, targets),
+                    Bug::Grasshopper => Bug::grasshopper_moves(position, board, targets),
+                    Bug::Ladybug => Bug::ladybug_moves(position, board, targets),
+                    Bug::Mosquito => {}
+                    Bug::Pillbug => targets.extend(Bug::pillbug_moves(position, board)),
+                    Bug::Queen => targets.extend(Bug::queen_moves(position, board)),
+                    Bug::Spider => Bug::spider_moves(position, board, targets),
+                }
+            }
+            Bug::dedup_from(targets, start);
         } else {
-            Bug::beetle_moves(position, board)
-        };
+            Bug::beetle_moves(position, board, targets)
+        }
     }
 
     fn pillbug_moves(position: Position, board: &Board) -> impl Iterator<Item = Position> + '_ {
         Bug::crawl(position, board)
     }
 
-    fn pillbug_throw(position: Position, board: &Board) -> HashMap<Position, Vec<Position>> {
-        let mut moves = HashMap::default();
-        // get all the positions the pillbug can throw a bug to
-        let to = board
-            .positions_available_around(position)
-            .filter(|pos| !board.gated(2, position, *pos))
-            .collect::<Vec<Position>>();
-        // get bugs around the pillbug that aren't pinned
-        for pos in board.positions_taken_around(position).filter(|p| {
-            !board.is_pinned(board.top_piece(*p).unwrap())
-                && !board.gated(2, *p, position)
-                && board.level(*p) <= 1
-        }) {
-            moves.insert(pos, to.clone());
+    fn pillbug_throw(position: Position, board: &Board, moves: &mut Vec<Move>) {
+        // a pillbug or mosquito that was just moved can't use its ability
+        if board.is_stunned(board.top_piece(position).unwrap(), position) {
+            return;
+        }
+        // get bugs around the pillbug that aren't pinned and weren't just moved
+        for pos in board.positions_taken_around(position) {
+            let piece = board.top_piece(pos).unwrap();
+            if board.is_pinned(piece)
+                || board.is_stunned(piece, pos)
+                || board.gated(2, pos, position)
+                || board.level(pos) > 1
+            {
+                continue;
+            }
+            // and throw them to all the positions around the pillbug
+            for to in board
+                .positions_available_around(position)
+                .filter(|to| !board.gated(2, position, *to))
+            {
+                moves.push(Move::new(piece, pos, to));
+            }
         }
-        moves
     }
 
     fn queen_moves(position: Position, board: &Board) -> impl Iterator<Item = Position> + '_ {
         Bug::crawl(position, board)
     }
 
-    fn spider_moves(position: Position, board: &Board) -> Vec<Position> {
+    fn spider_moves(position: Position, board: &Board, targets: &mut Vec<Position>) {
+        let start = targets.len();
         let board = MidMoveBoard::new(board, board.top_piece(position).unwrap(), position);
-        let mut res = Vec::new();
         for pos1 in Bug::crawl_negative_space(position, &board) {
             for pos2 in Bug::crawl_negative_space(pos1, &board).filter(move |pos| *pos != position)
             {
                 for pos3 in Bug::crawl_negative_space(pos2, &board).filter(move |pos| *pos != pos1)
                 {
                     if pos3 != position {
-                        res.push(pos3);
+                        targets.push(pos3);
                     }
                 }
             }
         }
-        res.sort();
-        res.dedup();
-        res
+        targets[start..].sort();
+        Bug::dedup_from(targets, start);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{color::Color, piece::Piece};
+    use crate::{color::Color, last_move::LastMove, piece::Piece};
+
+    fn targets(
+        moves: fn(Position, &Board, &mut Vec<Position>),
+        position: Position,
+        board: &Board,
+    ) -> Vec<Position> {
+        let mut targets = Vec::new();
+        moves(position, board, &mut targets);
+        targets
+    }
+
+    fn ant_moves(position: Position, board: &Board, targets: &mut Vec<Position>) {
+        Bug::ant_moves(position, board, targets, &mut HexGrid::new(false))
+    }
+
+    fn mosquito_moves(position: Position, board: &Board, targets: &mut Vec<Position>) {
+        Bug::mosquito_moves(position, board, targets, &mut HexGrid::new(false))
+    }
+
+    fn pillbug_throw(position: Position, board: &Board) -> HashMap<Position, Vec<Position>> {
+        let mut throws = Vec::new();
+        Bug::pillbug_throw(position, board, &mut throws);
+        let mut moves: HashMap<Position, Vec<Position>> = HashMap::default();
+        for throw in throws {
+            moves.entry(throw.from).or_default().push(throw.to);
+        }
+        moves
+    }
 
     #[test]
     fn tests_available_moves() {
@@ -410,9 +493,9 @@ mod tests {
             Position::new(1, 0),
             Piece::new_from(Bug::Beetle, Color::Black, 1),
         );
-        let moves = Bug::available_moves(Position::new(0, 0), &board);
+        let moves = Bug::available_moves(Position::new(0, 0), &board, &RuleSet::default());
         assert_eq!(moves.get(&Position::new(0, 0)).unwrap().len(), 2);
-        let moves = Bug::available_moves(Position::new(1, 0), &board);
+        let moves = Bug::available_moves(Position::new(1, 0), &board, &RuleSet::default());
         assert_eq!(moves.get(&Position::new(1, 0)).unwrap().len(), 6);
     }
 
@@ -427,10 +510,22 @@ mod tests {
             Position::new(1, 0),
             Piece::new_from(Bug::Mosquito, Color::Black, 0),
         );
-        let positions = Bug::available_abilities(Position::new(0, 0), &board);
+        // neither piece was just placed
+        board.last_move = None;
+        let rules = RuleSet::default();
+        let positions = Bug::available_abilities(Position::new(0, 0), &board, &rules);
         assert_eq!(positions.get(&Position::new(1, 0)).unwrap().len(), 5);
-        let positions = Bug::available_abilities(Position::new(1, 0), &board);
+        let positions = Bug::available_abilities(Position::new(1, 0), &board, &rules);
         assert_eq!(positions.get(&Position::new(0, 0)).unwrap().len(), 5);
+
+        let mut rules = RuleSet {
+            mosquito_ability: false,
+            ..RuleSet::default()
+        };
+        assert!(Bug::available_abilities(Position::new(1, 0), &board, &rules).is_empty());
+        assert!(!Bug::available_abilities(Position::new(0, 0), &board, &rules).is_empty());
+        rules.pillbug_ability = false;
+        assert!(Bug::available_abilities(Position::new(0, 0), &board, &rules).is_empty());
     }
 
     #[test]
@@ -444,7 +539,8 @@ mod tests {
             Position::new(1, 0),
             Piece::new_from(Bug::Mosquito, Color::Black, 0),
         );
-        let positions = Bug::pillbug_throw(Position::new(0, 0), &board);
+        board.last_move = None;
+        let positions = pillbug_throw(Position::new(0, 0), &board);
         assert_eq!(positions.get(&Position::new(1, 0)).unwrap().len(), 5);
 
         let mut board = Board::new();
@@ -460,10 +556,86 @@ mod tests {
             Position::new(1, 0),
             Piece::new_from(Bug::Beetle, Color::Black, 1),
         );
-        let positions = Bug::pillbug_throw(Position::new(0, 0), &board);
+        let positions = pillbug_throw(Position::new(0, 0), &board);
         assert!(!positions.contains_key(&Position::new(1, 0)));
     }
 
+    fn pillbug_triangle() -> Board {
+        // wP, bM and wA1 all touch each other, so none of them is pinned
+        crate::board!["wP@0,0", "bM@1,0", "wA1@0,1"]
+    }
+
+    #[test]
+    fn tests_pillbug_throw_just_moved() {
+        // the pillbug can't move the piece the opponent just moved
+        let mut board = pillbug_triangle();
+        let mosquito = Piece::new_from(Bug::Mosquito, Color::Black, 0);
+        board.last_move = Some(LastMove::new_move(
+            mosquito,
+            Position::new(1, -1),
+            Position::new(1, 0),
+        ));
+        let positions = pillbug_throw(Position::new(0, 0), &board);
+        assert!(!positions.contains_key(&Position::new(1, 0)));
+        assert!(positions.contains_key(&Position::new(0, 1)));
+    }
+
+    #[test]
+    fn tests_pillbug_throw_just_thrown() {
+        // a piece that was just thrown can't use its own ability
+        let mut board = pillbug_triangle();
+        let mosquito = Piece::new_from(Bug::Mosquito, Color::Black, 0);
+        board.last_move = Some(LastMove {
+            piece: mosquito,
+            from: Some(Position::new(1, -1)),
+            to: Position::new(1, 0),
+            thrown_by: Some(Piece::new_from(Bug::Pillbug, Color::White, 0)),
+        });
+        let rules = RuleSet::default();
+        assert!(Bug::available_abilities(Position::new(1, 0), &board, &rules).is_empty());
+        assert!(!Bug::available_abilities(Position::new(0, 0), &board, &rules).is_empty());
+    }
+
+    #[test]
+    fn tests_pillbug_throw_moved_pillbug() {
+        // a pillbug that was just moved can't use its ability
+        let mut board = pillbug_triangle();
+        let pillbug = Piece::new_from(Bug::Pillbug, Color::White, 0);
+        board.last_move = Some(LastMove::new_move(
+            pillbug,
+            Position::new(-1, 1),
+            Position::new(0, 0),
+        ));
+        assert!(pillbug_throw(Position::new(0, 0), &board).is_empty());
+        // the mosquito can still copy it, but not throw the pillbug
+        let positions = pillbug_throw(Position::new(1, 0), &board);
+        assert!(!positions.contains_key(&Position::new(0, 0)));
+        assert!(positions.contains_key(&Position::new(0, 1)));
+    }
+
+    #[test]
+    fn tests_mosquito_throw_limits() {
+        // a mosquito copying a pillbug follows the same limits
+        let mut board = pillbug_triangle();
+        let ant = Piece::new_from(Bug::Ant, Color::White, 1);
+        let rules = RuleSet::default();
+        let positions = Bug::available_abilities(Position::new(1, 0), &board, &rules);
+        assert!(positions.contains_key(&Position::new(0, 1)));
+        assert!(positions.contains_key(&Position::new(0, 0)));
+        board.last_move = Some(LastMove::new_move(
+            ant,
+            Position::new(-1, 1),
+            Position::new(0, 1),
+        ));
+        let positions = Bug::available_abilities(Position::new(1, 0), &board, &rules);
+        assert!(!positions.contains_key(&Position::new(0, 1)));
+        assert!(positions.contains_key(&Position::new(0, 0)));
+        // on top of the hive the mosquito is a beetle and can't throw at all
+        let mosquito = board.remove(Position::new(1, 0));
+        board.insert(Position::new(0, 1), mosquito);
+        assert!(Bug::available_abilities(Position::new(0, 1), &board, &rules).is_empty());
+    }
+
     #[test]
     fn tests_pillbug_moves() {
         let mut board = Board::new();
@@ -490,7 +662,7 @@ mod tests {
             Position::new(1, 0),
             Piece::new_from(Bug::Mosquito, Color::Black, 0),
         );
-        let positions = Bug::mosquito_moves(Position::new(0, 0), &board);
+        let positions = targets(mosquito_moves, Position::new(0, 0), &board);
         assert_eq!(positions.len(), 0);
 
         let mut board = Board::new();
@@ -502,7 +674,7 @@ mod tests {
             Position::new(1, 0),
             Piece::new_from(Bug::Ant, Color::Black, 1),
         );
-        let positions = Bug::mosquito_moves(Position::new(0, 0), &board);
+        let positions = targets(mosquito_moves, Position::new(0, 0), &board);
         assert_eq!(positions.len(), 5);
 
         let mut board = Board::new();
@@ -514,7 +686,7 @@ mod tests {
             Position::new(1, 0),
             Piece::new_from(Bug::Pillbug, Color::Black, 0),
         );
-        let positions = Bug::mosquito_moves(Position::new(0, 0), &board);
+        let positions = targets(mosquito_moves, Position::new(0, 0), &board);
         assert_eq!(positions.len(), 2);
 
         let mut board = Board::new();
@@ -526,37 +698,22 @@ mod tests {
             Position::new(0, 0),
             Piece::new_from(Bug::Mosquito, Color::Black, 0),
         );
-        let positions = Bug::mosquito_moves(Position::new(0, 0), &board);
+        let positions = targets(mosquito_moves, Position::new(0, 0), &board);
         assert_eq!(positions.len(), 6);
     }
 
     #[test]
     fn tests_descend() {
-        let mut board = Board::new();
-        board.insert(
-            Position::new(0, 0),
-            Piece::new_from(Bug::Queen, Color::White, 0),
-        );
-        board.insert(
-            Position::new(0, 0),
-            Piece::new_from(Bug::Beetle, Color::Black, 1),
-        );
-        board.insert(
-            Position::new(0, 0).to(crate::direction::Direction::NE),
-            Piece::new_from(Bug::Ant, Color::White, 1),
-        );
-        board.insert(
-            Position::new(0, 0).to(crate::direction::Direction::NE),
-            Piece::new_from(Bug::Beetle, Color::Black, 2),
-        );
-        board.insert(
-            Position::new(0, 0).to(crate::direction::Direction::SE),
-            Piece::new_from(Bug::Ant, Color::White, 2),
-        );
-        board.insert(
-            Position::new(0, 0).to(crate::direction::Direction::SE),
-            Piece::new_from(Bug::Mosquito, Color::Black, 0),
-        );
+        // black beetles on top of the queen and the ant, the mosquito on top of the other ant
+        let board = Board::from_diagram(
+            "
+            @ 1,-1
+                wA1/bB2
+            wQ/bB1
+                wA2/bM
+            ",
+        )
+        .unwrap();
         let positions = Bug::descend(Position::new(0, 0), &board);
         assert_eq!(positions.count(), 3);
         let mut positions = Bug::descend(Position::new(0, 0), &board);
@@ -853,7 +1010,7 @@ mod tests {
             Position::new(1, 0),
             Piece::new_from(Bug::Ant, Color::Black, 1),
         );
-        let positions = Bug::spider_moves(Position::new(0, 0), &board);
+        let positions = targets(Bug::spider_moves, Position::new(0, 0), &board);
         assert_eq!(positions.len(), 1);
         assert!(positions.contains(&Position::new(2, 0)));
     }
@@ -873,7 +1030,10 @@ mod tests {
             Position::new(-2, 0),
             Piece::new_from(Bug::Mosquito, Color::Black, 0),
         );
-        assert_eq!(Bug::ladybug_moves(Position::new(0, 0), &board).len(), 5);
+        assert_eq!(
+            targets(Bug::ladybug_moves, Position::new(0, 0), &board).len(),
+            5
+        );
 
         let mut board = Board::new();
         board.insert(
@@ -887,7 +1047,10 @@ mod tests {
             );
         }
         board.remove(Position::new(1, 0));
-        assert_eq!(Bug::ladybug_moves(Position::new(0, 0), &board).len(), 12);
+        assert_eq!(
+            targets(Bug::ladybug_moves, Position::new(0, 0), &board).len(),
+            12
+        );
 
         let mut board = Board::new();
         board.insert(
@@ -905,7 +1068,10 @@ mod tests {
             Piece::new_from(Bug::Ant, Color::Black, 1),
         );
         board.remove(Position::new(1, 0));
-        assert_eq!(Bug::ladybug_moves(Position::new(0, 0), &board).len(), 14);
+        assert_eq!(
+            targets(Bug::ladybug_moves, Position::new(0, 0), &board).len(),
+            14
+        );
     }
 
     #[test]
@@ -923,7 +1089,10 @@ mod tests {
             Position::new(1, 0),
             Piece::new_from(Bug::Mosquito, Color::Black, 0),
         );
-        assert_eq!(Bug::beetle_moves(Position::new(0, 0), &board).len(), 4);
+        assert_eq!(
+            targets(Bug::beetle_moves, Position::new(0, 0), &board).len(),
+            4
+        );
 
         let mut board = Board::new();
         board.insert(
@@ -936,7 +1105,10 @@ mod tests {
                 Piece::new_from(Bug::Grasshopper, Color::from((i % 2) as u8), i / 2 + 1),
             );
         }
-        assert_eq!(Bug::beetle_moves(Position::new(0, 0), &board).len(), 6);
+        assert_eq!(
+            targets(Bug::beetle_moves, Position::new(0, 0), &board).len(),
+            6
+        );
 
         let mut board = Board::new();
         board.insert(
@@ -950,7 +1122,10 @@ mod tests {
             );
         }
         board.remove(Position::new(1, 0));
-        assert_eq!(Bug::beetle_moves(Position::new(0, 0), &board).len(), 5);
+        assert_eq!(
+            targets(Bug::beetle_moves, Position::new(0, 0), &board).len(),
+            5
+        );
     }
 
     #[test]
@@ -973,7 +1148,7 @@ mod tests {
                 .unwrap(),
             Piece::new_from(Bug::Beetle, Color::White, 1)
         );
-        assert_eq!(Bug::ant_moves(Position::new(0, 0), &board).len(), 5);
+        assert_eq!(targets(ant_moves, Position::new(0, 0), &board).len(), 5);
     }
 
     #[test]
@@ -989,7 +1164,10 @@ mod tests {
                 Piece::new_from(Bug::Ant, Color::from((i % 2) as u8), i / 2 + 1),
             );
         }
-        assert_eq!(Bug::grasshopper_moves(Position::new(0, 0), &board).len(), 6);
+        assert_eq!(
+            targets(Bug::grasshopper_moves, Position::new(0, 0), &board).len(),
+            6
+        );
 
         let mut board = Board::new();
         board.insert(
@@ -1000,9 +1178,12 @@ mod tests {
             Position::new(1, 0),
             Piece::new_from(Bug::Beetle, Color::Black, 1),
         );
-        assert_eq!(Bug::grasshopper_moves(Position::new(0, 0), &board).len(), 1);
         assert_eq!(
-            *Bug::grasshopper_moves(Position::new(0, 0), &board)
+            targets(Bug::grasshopper_moves, Position::new(0, 0), &board).len(),
+            1
+        );
+        assert_eq!(
+            *targets(Bug::grasshopper_moves, Position::new(0, 0), &board)
                 .last()
                 .unwrap(),
             Position::new(2, 0)
@@ -1013,6 +1194,9 @@ mod tests {
             Position::new(0, 0),
             Piece::new_from(Bug::Grasshopper, Color::White, 1),
         );
-        assert_eq!(Bug::grasshopper_moves(Position::new(0, 0), &board).len(), 0);
+        assert_eq!(
+            targets(Bug::grasshopper_moves, Position::new(0, 0), &board).len(),
+            0
+        );
     }
 }
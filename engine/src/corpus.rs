@@ -0,0 +1,61 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// Collects all .pgn files below the given paths, directories are searched recursively
+pub fn pgn_files<P: AsRef<Path>>(paths: &[P]) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        collect(path.as_ref(), &mut files)?;
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn collect(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            let entry = entry?.path();
+            if entry.is_dir() || is_pgn(&entry) {
+                collect(&entry, files)?;
+            }
+        }
+    } else if path.is_file() {
+        files.push(path.to_path_buf());
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} does not exist", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+fn is_pgn(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pgn"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_pgn_files() {
+        let files = pgn_files(&["./test_pgns"]).unwrap();
+        let valid = pgn_files(&["./test_pgns/valid"]).unwrap();
+        let invalid = pgn_files(&["./test_pgns/invalid"]).unwrap();
+        let print = pgn_files(&["./test_pgns/print"]).unwrap();
+        assert_eq!(files.len(), valid.len() + invalid.len() + print.len());
+        assert!(files.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(
+            pgn_files(&["./test_pgns/valid", "./test_pgns/valid/pass.pgn"])
+                .unwrap()
+                .len(),
+            valid.len()
+        );
+        assert!(pgn_files(&["./test_pgns/missing"]).is_err());
+    }
+}
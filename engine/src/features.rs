@@ -0,0 +1,125 @@
+use crate::{bug::Bug, color::Color, piece::Piece, state::State};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SideFeatures {
+    // free positions around the queen, None while the queen is in the reserve
+    pub queen_liberties: Option<usize>,
+    pub queen_friendly_neighbors: Option<usize>,
+    pub queen_enemy_neighbors: Option<usize>,
+    pub mobile_pieces: usize,
+    pub pinned_pieces: usize,
+    // pieces that have a beetle or mosquito on top of them
+    pub covered_pieces: usize,
+    pub spawn_points: usize,
+    pub reserve_size: usize,
+    // number of target positions per bug, throws by a friendly pillbug included
+    pub mobility: HashMap<Bug, usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Features {
+    pub turn: usize,
+    pub turn_color: Color,
+    pub white: SideFeatures,
+    pub black: SideFeatures,
+}
+
+impl Features {
+    pub fn new(state: &State) -> Self {
+        Self {
+            turn: state.turn,
+            turn_color: state.turn_color,
+            white: Self::side(state, Color::White),
+            black: Self::side(state, Color::Black),
+        }
+    }
+
+    pub fn side_features(&self, color: Color) -> &SideFeatures {
+        match color {
+            Color::White => &self.white,
+            Color::Black => &self.black,
+        }
+    }
+
+    fn side(state: &State, color: Color) -> SideFeatures {
+        let board = &state.board;
+        let mut features = SideFeatures::default();
+        if let Some(queen) = board.position_of_piece(Piece::new_from(Bug::Queen, color, 0)) {
+            let neighbors = board.top_layer_neighbors(queen);
+            let (friendly, enemy): (Vec<Piece>, Vec<Piece>) =
+                neighbors.partition(|piece| piece.is_color(color));
+            features.queen_liberties = Some(board.positions_available_around(queen).count());
+            features.queen_friendly_neighbors = Some(friendly.len());
+            features.queen_enemy_neighbors = Some(enemy.len());
+        }
+        for (offset, position) in board.positions.iter().enumerate() {
+            if let Some(position) = position {
+                let piece = board.offset_to_piece(offset);
+                if !piece.is_color(color) {
+                    continue;
+                }
+                if !board.is_top_piece(piece, *position) {
+                    features.covered_pieces += 1;
+                } else if board.is_pinned(piece) {
+                    features.pinned_pieces += 1;
+                }
+            }
+        }
+        for ((piece, _), targets) in board.moves(color, &state.rules) {
+            if piece.is_color(color) {
+                features.mobile_pieces += 1;
+                *features.mobility.entry(piece.bug()).or_default() += targets.len();
+            }
+        }
+        features.spawn_points = board.spawnable_positions(color).count();
+        features.reserve_size = board
+            .reserve(color, &state.rules)
+            .values()
+            .map(|pieces| pieces.len())
+            .sum();
+        features
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game_type::GameType, position::Position};
+
+    #[test]
+    fn tests_features() {
+        let mut state = State::new(GameType::Base, false);
+        for (bug, color, order, q) in [
+            (Bug::Queen, Color::White, 0, 0),
+            (Bug::Queen, Color::Black, 0, 1),
+            (Bug::Ant, Color::White, 1, -1),
+            (Bug::Beetle, Color::Black, 1, 1),
+        ] {
+            let piece = Piece::new_from(bug, color, order);
+            state.board.insert(Position::new(q, 0), piece);
+        }
+
+        let features = Features::new(&state);
+        let white = features.side_features(Color::White);
+        assert_eq!(white.queen_liberties, Some(4));
+        assert_eq!(white.queen_friendly_neighbors, Some(1));
+        assert_eq!(white.queen_enemy_neighbors, Some(1));
+        assert_eq!(white.reserve_size, 9);
+        assert_eq!(white.covered_pieces, 0);
+        assert_eq!(white.pinned_pieces, 1);
+        assert_eq!(white.mobile_pieces, 1);
+        assert_eq!(white.mobility.get(&Bug::Ant), Some(&7));
+        assert_eq!(white.mobility.get(&Bug::Queen), None);
+
+        let black = features.side_features(Color::Black);
+        assert_eq!(black.queen_liberties, Some(5));
+        assert_eq!(black.queen_friendly_neighbors, Some(0));
+        assert_eq!(black.queen_enemy_neighbors, Some(1));
+        assert_eq!(black.covered_pieces, 1);
+        assert_eq!(black.reserve_size, 9);
+        assert_eq!(black.spawn_points, 3);
+        assert!(serde_json::to_string(&features).is_ok());
+    }
+}
@@ -0,0 +1,408 @@
+use crate::{
+    bug::Bug, game_error::GameError, game_type::GameType, position::Position, state::State,
+};
+use serde::Serialize;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{c_char, c_int, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+// C API over State, see ffi/hive.h for the header. Panics never unwind into the host, they are
+// turned into errors like any other.
+
+// The name of State in C, where it's opaque
+pub type HiveState = State;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+#[derive(Serialize)]
+struct MovesResponse {
+    moves: Vec<PieceMoves>,
+    spawns: Vec<Position>,
+    reserve: HashMap<Bug, Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct PieceMoves {
+    piece: String,
+    from: Position,
+    to: Vec<Position>,
+}
+
+#[derive(Serialize)]
+struct BoardResponse {
+    turn: usize,
+    turn_color: String,
+    game_status: String,
+    game_type: String,
+    history: Vec<(String, String)>,
+    stacks: Vec<StackResponse>,
+}
+
+#[derive(Serialize)]
+struct StackResponse {
+    position: Position,
+    // bottom to top
+    pieces: Vec<String>,
+}
+
+fn set_last_error(err: impl ToString) {
+    let message = CString::new(err.to_string().replace('\0', ""))
+        .expect("Null bytes have been removed from the message");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+// Runs `f`, a panic sets the last error and returns `error` instead
+fn catch_panic<T>(error: T, f: impl FnOnce() -> T) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => value,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(format!("internal error: {message}"));
+            error
+        }
+    }
+}
+
+fn to_c_string(s: String) -> *mut c_char {
+    match CString::new(s) {
+        Ok(s) => s.into_raw(),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+unsafe fn from_c_str<'a>(s: *const c_char, typ: &str) -> Result<&'a str, GameError> {
+    if s.is_null() {
+        return Ok("");
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| GameError::ParsingError {
+            found: "invalid UTF-8".to_string(),
+            typ: typ.to_string(),
+        })
+}
+
+/// Creates a new game, `game_type` is e.g. "Base+MLP" and may be NULL for Base.
+/// Returns NULL on error, the state has to be released with `hive_state_free`.
+///
+/// # Safety
+/// `game_type` has to be NULL or a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn hive_state_new(
+    game_type: *const c_char,
+    tournament: bool,
+) -> *mut HiveState {
+    catch_panic(ptr::null_mut(), || {
+        let game_type = match from_c_str(game_type, "game type string") {
+            Ok("") => GameType::default(),
+            Ok(s) => match s.parse() {
+                Ok(game_type) => game_type,
+                Err(e) => {
+                    set_last_error(e);
+                    return ptr::null_mut();
+                }
+            },
+            Err(e) => {
+                set_last_error(e);
+                return ptr::null_mut();
+            }
+        };
+        Box::into_raw(Box::new(State::new(game_type, tournament)))
+    })
+}
+
+/// # Safety
+/// `state` has to be NULL or returned by `hive_state_new` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn hive_state_free(state: *mut HiveState) {
+    catch_panic((), || {
+        if !state.is_null() {
+            drop(Box::from_raw(state));
+        }
+    })
+}
+
+/// Plays a move in notation, e.g. piece "bA1" and position "wQ/", or piece "pass".
+/// Returns 0 on success and -1 on error, see `hive_last_error`, the state is unchanged then.
+///
+/// # Safety
+/// `state` has to be a live state, `piece` and `position` NULL or valid strings.
+#[no_mangle]
+pub unsafe extern "C" fn hive_state_play(
+    state: *mut HiveState,
+    piece: *const c_char,
+    position: *const c_char,
+) -> c_int {
+    catch_panic(-1, || {
+        let Some(state) = state.as_mut() else {
+            set_last_error("state is NULL");
+            return -1;
+        };
+        // played on a copy, so neither an error nor a panic leaves half a turn behind
+        let mut next = state.clone();
+        let result = from_c_str(piece, "piece").and_then(|piece| {
+            let position = from_c_str(position, "position")?;
+            next.play_turn_from_notation(piece, position)
+        });
+        match result {
+            Ok(()) => {
+                *state = next;
+                0
+            }
+            Err(e) => {
+                set_last_error(e);
+                -1
+            }
+        }
+    })
+}
+
+/// Returns the legal moves, spawns and reserve of the player to move as JSON.
+///
+/// # Safety
+/// `state` has to be a live state. The string has to be released with `hive_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn hive_state_moves_json(state: *const HiveState) -> *mut c_char {
+    catch_panic(ptr::null_mut(), || {
+        let Some(state) = state.as_ref() else {
+            set_last_error("state is NULL");
+            return ptr::null_mut();
+        };
+        let mut moves = state
+            .board
+            .moves(state.turn_color, &state.rules)
+            .into_iter()
+            .map(|((piece, from), to)| PieceMoves {
+                piece: piece.to_string(),
+                from,
+                to,
+            })
+            .collect::<Vec<_>>();
+        moves.sort_by(|a, b| a.piece.cmp(&b.piece));
+        let response = MovesResponse {
+            moves,
+            spawns: state.board.spawnable_positions(state.turn_color).collect(),
+            reserve: state.board.reserve(state.turn_color, &state.rules),
+        };
+        to_c_string(serde_json::to_string(&response).expect("Moves serialize to JSON"))
+    })
+}
+
+/// Returns the game status, e.g. "InProgress" or "Finished(Winner(w))".
+///
+/// # Safety
+/// `state` has to be a live state. The string has to be released with `hive_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn hive_state_status(state: *const HiveState) -> *mut c_char {
+    catch_panic(ptr::null_mut(), || {
+        let Some(state) = state.as_ref() else {
+            set_last_error("state is NULL");
+            return ptr::null_mut();
+        };
+        to_c_string(state.game_status.to_string())
+    })
+}
+
+/// Returns turn, status, history and all stacks on the board as JSON.
+///
+/// # Safety
+/// `state` has to be a live state. The string has to be released with `hive_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn hive_state_board_json(state: *const HiveState) -> *mut c_char {
+    catch_panic(ptr::null_mut(), || {
+        let Some(state) = state.as_ref() else {
+            set_last_error("state is NULL");
+            return ptr::null_mut();
+        };
+        let mut positions = state.board.all_taken_positions().collect::<Vec<_>>();
+        positions.sort();
+        positions.dedup();
+        let stacks = positions
+            .into_iter()
+            .map(|position| {
+                let bug_stack = state.board.board.get(position);
+                StackResponse {
+                    position,
+                    pieces: bug_stack.pieces[..bug_stack.len()]
+                        .iter()
+                        .map(|piece| piece.to_string())
+                        .collect(),
+                }
+            })
+            .collect();
+        let response = BoardResponse {
+            turn: state.turn,
+            turn_color: state.turn_color.to_string(),
+            game_status: state.game_status.to_string(),
+            game_type: state.game_type.to_string(),
+            history: state.history.moves.clone(),
+            stacks,
+        };
+        to_c_string(serde_json::to_string(&response).expect("Board serializes to JSON"))
+    })
+}
+
+/// Returns the message of the last error on this thread or NULL.
+/// The string is owned by the library and valid until the next call into it.
+#[no_mangle]
+pub extern "C" fn hive_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map(|message| message.as_ptr())
+            .unwrap_or(ptr::null())
+    })
+}
+
+/// # Safety
+/// `s` has to be NULL or a string returned by this library and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn hive_string_free(s: *mut c_char) {
+    catch_panic((), || {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    #[test]
+    fn tests_ffi_play() {
+        unsafe {
+            let game_type = c("Base+M");
+            let state = hive_state_new(game_type.as_ptr(), false);
+            assert!(!state.is_null());
+            assert_eq!(hive_state_play(state, c("wQ").as_ptr(), c(".").as_ptr()), 0);
+            assert_eq!(
+                hive_state_play(state, c("bQ").as_ptr(), c("wQ-").as_ptr()),
+                0
+            );
+            assert_eq!(
+                hive_state_play(state, c("bA1").as_ptr(), c("bQ-").as_ptr()),
+                -1
+            );
+            let error = CStr::from_ptr(hive_last_error()).to_str().unwrap();
+            assert!(error.contains("It is not this color's turn."));
+
+            let status = hive_state_status(state);
+            assert_eq!(CStr::from_ptr(status).to_str().unwrap(), "InProgress");
+            hive_string_free(status);
+
+            let moves = hive_state_moves_json(state);
+            let json: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(moves).to_str().unwrap()).unwrap();
+            assert_eq!(json["moves"][0]["piece"], "wQ");
+            assert_eq!(json["spawns"].as_array().unwrap().len(), 3);
+            hive_string_free(moves);
+
+            let board = hive_state_board_json(state);
+            let json: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(board).to_str().unwrap()).unwrap();
+            assert_eq!(json["stacks"].as_array().unwrap().len(), 2);
+            assert_eq!(json["turn_color"], "w");
+            hive_string_free(board);
+            hive_state_free(state);
+        }
+    }
+
+    #[test]
+    fn tests_ffi_errors() {
+        unsafe {
+            let game_type = c("Chess");
+            assert!(hive_state_new(game_type.as_ptr(), false).is_null());
+            assert!(!hive_last_error().is_null());
+            assert_eq!(
+                hive_state_play(ptr::null_mut(), c("wQ").as_ptr(), c(".").as_ptr()),
+                -1
+            );
+            assert!(hive_state_status(ptr::null()).is_null());
+            hive_state_free(ptr::null_mut());
+            hive_string_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn tests_ffi_panics() {
+        assert_eq!(catch_panic(-1, || panic!("engine bug")), -1);
+        let error = unsafe { CStr::from_ptr(hive_last_error()) };
+        assert_eq!(error.to_str().unwrap(), "internal error: engine bug");
+        let value = catch_panic(ptr::null_mut::<c_char>(), || {
+            panic!("{} pieces", 3);
+        });
+        assert!(value.is_null());
+        let error = unsafe { CStr::from_ptr(hive_last_error()) };
+        assert_eq!(error.to_str().unwrap(), "internal error: 3 pieces");
+    }
+
+    // The header is written by hand, every export has to be declared in it with the same
+    // signature
+    #[test]
+    fn tests_header_matches_exports() {
+        let source = include_str!("ffi.rs").split("#[cfg(test)]").next().unwrap();
+        let header = include_str!("../ffi/hive.h")
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("/*") && !line.starts_with(" *"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let header = header.split_whitespace().collect::<Vec<_>>().join(" ");
+        let c_type = |rust: &str| match rust.trim() {
+            "" => "void",
+            "bool" => "bool",
+            "c_int" => "int",
+            "*const c_char" => "const char *",
+            "*mut c_char" => "char *",
+            "*const HiveState" => "const HiveState *",
+            "*mut HiveState" => "HiveState *",
+            other => panic!("no C type for {other}"),
+        };
+        let mut exports = 0;
+        for export in source.split("extern \"C\" fn ").skip(1) {
+            let (name, rest) = export.split_once('(').unwrap();
+            let (parameters, rest) = rest.split_once(')').unwrap();
+            let returns = rest.split_once('{').unwrap().0.trim();
+            let parameters = parameters
+                .split(',')
+                .filter(|parameter| !parameter.trim().is_empty())
+                .map(|parameter| {
+                    let (name, rust) = parameter.split_once(':').unwrap();
+                    format!("{} {}", c_type(rust), name.trim()).replace("* ", "*")
+                })
+                .collect::<Vec<_>>();
+            let parameters = match parameters.is_empty() {
+                true => "void".to_string(),
+                false => parameters.join(", "),
+            };
+            let declaration = format!(
+                "{} {name}({parameters});",
+                c_type(returns.trim_start_matches("->"))
+            )
+            .replace("* ", "*");
+            assert!(
+                header.contains(&declaration),
+                "{declaration} isn't in hive.h"
+            );
+            exports += 1;
+        }
+        assert_eq!(header.matches(");").count(), exports);
+    }
+}
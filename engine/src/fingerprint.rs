@@ -0,0 +1,284 @@
+// Fingerprints identify a game by the positions it went through instead of the way its turns
+// were written down. Every position is taken in its canonical orientation, see
+// `Board::canonical_orientation`, with pieces only told apart by color and bug. Records of the
+// same game in another orientation or notation get the same fingerprint, and the positions of a
+// record that stops early are a prefix of the positions of the full game.
+use crate::{
+    game_error::GameError, game_type::GameType, history::History, piece::Piece, state::State,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+// FNV-1a, unlike DefaultHasher its values don't change between platforms and Rust versions
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+// Pieces of the same color and bug are the same in a fingerprint
+fn piece_key(piece: Piece) -> (u8, u8) {
+    (piece.color() as u8, piece.bug() as u8)
+}
+
+// The side to move and the canonical cells
+fn position_key(state: &State) -> u64 {
+    let mut hasher = Fnv::new();
+    hasher.write(&[state.turn_color as u8]);
+    let board = &state.board;
+    for (r, q, stack) in board.oriented_cells(&board.canonical_orientation(piece_key), piece_key) {
+        hasher.write(&r.to_le_bytes());
+        hasher.write(&q.to_le_bytes());
+        hasher.write(&[stack.len() as u8]);
+        for (color, bug) in stack {
+            hasher.write(&[color, bug]);
+        }
+    }
+    hasher.0
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub game_type: GameType,
+    // the key of the position after every turn
+    pub positions: Vec<u64>,
+    // digests[n] is the fingerprint of the first n turns
+    #[serde(skip)]
+    digests: Vec<u64>,
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:016x}", self.digest())
+    }
+}
+
+impl Fingerprint {
+    pub fn new(history: &History) -> Result<Self, GameError> {
+        // replaying the whole game first detects the opening rule
+        let end = State::new_from_history(history)?;
+        let mut state = State::new(history.game_type, end.rules.tournament_opening);
+        let mut positions = Vec::new();
+        for (piece, position) in history.played_moves() {
+            state.play_turn_from_notation(piece, position)?;
+            positions.push(position_key(&state));
+        }
+        Ok(Self::from_positions(history.game_type, positions))
+    }
+
+    pub fn from_positions(game_type: GameType, positions: Vec<u64>) -> Self {
+        let mut hasher = Fnv::new();
+        hasher.write(game_type.to_string().as_bytes());
+        let mut digests = vec![hasher.0];
+        for key in positions.iter() {
+            hasher.write(&key.to_le_bytes());
+            digests.push(hasher.0);
+        }
+        Self {
+            game_type,
+            positions,
+            digests,
+        }
+    }
+
+    pub fn turns(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn digest(&self) -> u64 {
+        self.digests[self.turns()]
+    }
+
+    // The digest of the game cut off after `turns` turns
+    pub fn digest_after(&self, turns: usize) -> Option<u64> {
+        self.digests.get(turns).copied()
+    }
+
+    // True if `other` is a longer game that starts with this one
+    pub fn is_prefix_of(&self, other: &Fingerprint) -> bool {
+        self.game_type == other.game_type
+            && self.turns() < other.turns()
+            && other.positions.starts_with(&self.positions)
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    pub fingerprint: String,
+    pub game_type: GameType,
+    pub turns: usize,
+    // files that record exactly this game
+    pub games: Vec<PathBuf>,
+    // files of longer games that start with this one
+    pub prefix_of: Vec<PathBuf>,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DedupeReport {
+    pub games: usize,
+    // games that could not be replayed
+    pub skipped: usize,
+    // only games that are recorded more than once or that another game continues
+    pub groups: Vec<DuplicateGroup>,
+}
+
+fn fingerprint(path: &Path) -> Option<Fingerprint> {
+    let history = History::from_filepath(&path.display().to_string()).ok()?;
+    Fingerprint::new(&history).ok()
+}
+
+impl DedupeReport {
+    // Fingerprints all games in parallel
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let fingerprints = paths
+            .par_iter()
+            .map(|path| fingerprint(path))
+            .collect::<Vec<_>>();
+        let mut report = DedupeReport::default();
+        let mut games: BTreeMap<u64, (Fingerprint, Vec<PathBuf>)> = BTreeMap::new();
+        for (path, fingerprint) in paths.into_iter().zip(fingerprints) {
+            let Some(fingerprint) = fingerprint else {
+                report.skipped += 1;
+                continue;
+            };
+            report.games += 1;
+            games
+                .entry(fingerprint.digest())
+                .or_insert_with(|| (fingerprint, Vec::new()))
+                .1
+                .push(path);
+        }
+        // the games every prefix of a game is continued by, a game without turns continues nothing
+        let mut prefix_of: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+        for (fingerprint, paths) in games.values() {
+            for turns in 1..fingerprint.turns() {
+                let digest = fingerprint.digests[turns];
+                if games.contains_key(&digest) {
+                    prefix_of
+                        .entry(digest)
+                        .or_default()
+                        .extend(paths.iter().cloned());
+                }
+            }
+        }
+        report.groups = games
+            .into_iter()
+            .map(|(digest, (fingerprint, mut paths))| {
+                paths.sort();
+                let mut longer = prefix_of.remove(&digest).unwrap_or_default();
+                longer.sort();
+                longer.dedup();
+                DuplicateGroup {
+                    fingerprint: fingerprint.to_string(),
+                    game_type: fingerprint.game_type,
+                    turns: fingerprint.turns(),
+                    games: paths,
+                    prefix_of: longer,
+                }
+            })
+            .filter(|group| group.games.len() > 1 || !group.prefix_of.is_empty())
+            .collect();
+        report.groups.sort_by(|a, b| a.games.cmp(&b.games));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // The game mirrored left to right, written with the opposite directions
+    fn mirrored(history: &History) -> History {
+        let mut mirrored = history.clone();
+        for (_, position) in mirrored.moves.iter_mut() {
+            let (prefix, rest) = match position.chars().next() {
+                Some(c @ ('-' | '/' | '\\')) => (Some(c), &position[1..]),
+                _ => (None, &position[..]),
+            };
+            let (piece, suffix) = match rest.chars().last() {
+                Some(c @ ('-' | '/' | '\\')) => (&rest[..rest.len() - 1], Some(c)),
+                _ => (rest, None),
+            };
+            let flip = |c| match c {
+                '/' => '\\',
+                '\\' => '/',
+                c => c,
+            };
+            *position = match (prefix, suffix) {
+                (Some(c), None) => format!("{piece}{}", flip(c)),
+                (None, Some(c)) => format!("{}{piece}", flip(c)),
+                _ => position.clone(),
+            };
+        }
+        mirrored
+    }
+
+    #[test]
+    fn tests_fingerprint() {
+        let history = History::from_filepath("./test_pgns/valid/descend.pgn").unwrap();
+        let fingerprint = Fingerprint::new(&history).unwrap();
+        assert_eq!(fingerprint.turns(), history.played_moves().count());
+        assert_eq!(fingerprint.to_string().len(), 16);
+        assert_eq!(Fingerprint::new(&mirrored(&history)).unwrap(), fingerprint);
+        let mut start = history.clone();
+        start.moves.truncate(10);
+        let start = Fingerprint::new(&start).unwrap();
+        assert!(start.is_prefix_of(&fingerprint));
+        assert!(!fingerprint.is_prefix_of(&start));
+        assert_eq!(fingerprint.digest_after(10), Some(start.digest()));
+        let other = History::from_filepath("./test_pgns/valid/p_game.pgn").unwrap();
+        let other = Fingerprint::new(&other).unwrap();
+        assert_ne!(other.digest(), fingerprint.digest());
+        assert!(!start.is_prefix_of(&other));
+    }
+
+    #[test]
+    fn tests_dedupe_report() {
+        let history = History::from_filepath("./test_pgns/valid/descend.pgn").unwrap();
+        let dir = std::env::temp_dir().join(format!("dedupe_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut start = mirrored(&history);
+        start.moves.truncate(6);
+        let copies = [
+            (dir.join("mirrored.pgn"), mirrored(&history)),
+            (dir.join("start.pgn"), start),
+        ];
+        for (path, history) in copies.iter() {
+            fs::write(path, history.to_pgn(&[])).unwrap();
+        }
+        let descend = PathBuf::from("./test_pgns/valid/descend.pgn");
+        let report = DedupeReport::new(vec![
+            descend.clone(),
+            copies[0].0.clone(),
+            copies[1].0.clone(),
+            PathBuf::from("./test_pgns/valid/p_game.pgn"),
+            PathBuf::from("./test_pgns/invalid/missing.pgn"),
+        ]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!((report.games, report.skipped), (4, 1));
+        assert_eq!(report.groups.len(), 2);
+        let mut same = vec![descend, copies[0].0.clone()];
+        same.sort();
+        let full = report.groups.iter().find(|group| group.turns > 6).unwrap();
+        assert_eq!(full.games, same);
+        assert!(full.prefix_of.is_empty());
+        let prefix = report.groups.iter().find(|group| group.turns == 6).unwrap();
+        assert_eq!(prefix.games, vec![copies[1].0.clone()]);
+        assert_eq!(prefix.prefix_of, same);
+    }
+}
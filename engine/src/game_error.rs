@@ -1,4 +1,6 @@
 use crate::game_result::GameResult;
+use crate::game_status::GameStatus;
+use crate::illegal_move_reason::IllegalMoveReason;
 
 #[derive(thiserror::Error, Debug, Clone, PartialEq)]
 pub enum GameError {
@@ -8,7 +10,7 @@ pub enum GameError {
         from: String,
         to: String,
         turn: usize,
-        reason: String,
+        reason: IllegalMoveReason,
     },
     #[error("Found {found:?} which is not a valid {typ}")]
     ParsingError { found: String, typ: String },
@@ -19,15 +21,20 @@ pub enum GameError {
     },
     #[error("No .pgn file supplied")]
     NoPgnFile,
+    #[error("Couldn't read {path}: {reason}")]
+    FileError { path: String, reason: String },
     #[error("Invalid direction {direction:?}")]
     InvalidDirection { direction: String },
+    #[error("Invalid rule set: {reason}")]
+    InvalidRuleSet { reason: String },
+    #[error("Invalid position: {reason}")]
+    InvalidPosition { reason: String },
+    #[error("Game status can't change from {from} to {to}")]
+    InvalidStatusTransition { from: GameStatus, to: GameStatus },
 }
 
 impl GameError {
-    pub fn update_reason<S>(&mut self, reason_new: S)
-    where
-        S: Into<String>,
-    {
+    pub fn update_reason(&mut self, reason_new: IllegalMoveReason) {
         if let GameError::InvalidMove {
             piece: _,
             from: _,
@@ -36,7 +43,7 @@ impl GameError {
             ref mut reason,
         } = self
         {
-            *reason = reason_new.into();
+            *reason = reason_new;
         }
     }
 
@@ -24,6 +24,34 @@ impl fmt::Display for GameStatus {
     }
 }
 
+impl GameStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Finished(_))
+    }
+
+    // Games only move forward: NotStarted -> InProgress -> Finished, a game can be finished
+    // before it started, e.g. by resignation, but a finished game stays finished
+    pub fn can_transition_to(&self, next: &GameStatus) -> bool {
+        matches!(
+            (self, next),
+            (Self::NotStarted, Self::InProgress)
+                | (Self::NotStarted, Self::Finished(_))
+                | (Self::InProgress, Self::Finished(_))
+        )
+    }
+
+    pub fn transition(&mut self, next: GameStatus) -> Result<(), GameError> {
+        if !self.can_transition_to(&next) {
+            return Err(GameError::InvalidStatusTransition {
+                from: self.clone(),
+                to: next,
+            });
+        }
+        *self = next;
+        Ok(())
+    }
+}
+
 impl FromStr for GameStatus {
     type Err = GameError;
 
@@ -62,4 +90,23 @@ mod tests {
             assert_eq!(Ok(gc.clone()), GameStatus::from_str(&format!("{gc}")));
         }
     }
+
+    #[test]
+    fn tests_game_status_transitions() {
+        let mut status = GameStatus::NotStarted;
+        assert!(status.transition(GameStatus::NotStarted).is_err());
+        assert!(status.transition(GameStatus::InProgress).is_ok());
+        assert!(status.transition(GameStatus::NotStarted).is_err());
+        assert!(status.transition(GameStatus::InProgress).is_err());
+        assert!(status
+            .transition(GameStatus::Finished(GameResult::Unknown))
+            .is_ok());
+        assert!(status.is_finished());
+        assert!(status
+            .transition(GameStatus::Finished(GameResult::Draw))
+            .is_err());
+        assert_eq!(status, GameStatus::Finished(GameResult::Unknown));
+        assert!(GameStatus::NotStarted
+            .can_transition_to(&GameStatus::Finished(GameResult::Winner(Color::White))));
+    }
 }
@@ -0,0 +1,239 @@
+use crate::position::Position;
+
+// Values on the unbounded hex plane. The grid stores the rectangle of axial coordinates that
+// has been written to so far and grows when a position outside of it is written, reading
+// outside of it returns the default. Distinct positions never share a value.
+#[derive(Clone, Debug)]
+pub struct HexGrid<T>
+where
+    T: Clone,
+{
+    data: Vec<T>,
+    // the smallest q and r of the rectangle
+    origin: Position,
+    width: i32,
+    height: i32,
+    default: T,
+}
+
+// Extra rows and columns when the grid grows, so a growing hive doesn't copy it on every turn
+const MARGIN: i32 = 4;
+
+impl<T> HexGrid<T>
+where
+    T: Clone,
+{
+    pub fn new(default: T) -> Self {
+        Self {
+            data: Vec::new(),
+            origin: Position::initial_spawn_position(),
+            width: 0,
+            height: 0,
+            default,
+        }
+    }
+
+    // A grid over the rectangle of another grid, writes inside of it never grow it
+    pub fn with_bounds_of<U>(grid: &HexGrid<U>, default: T) -> Self
+    where
+        U: Clone,
+    {
+        let mut bounded = Self::new(default);
+        bounded.reset_to_bounds_of(grid);
+        bounded
+    }
+
+    // Sets every value back to the default over the rectangle of another grid, the storage is
+    // only reallocated when that rectangle is larger than any before
+    pub fn reset_to_bounds_of<U>(&mut self, grid: &HexGrid<U>)
+    where
+        U: Clone,
+    {
+        self.data.clear();
+        self.data
+            .resize((grid.width * grid.height) as usize, self.default.clone());
+        self.origin = grid.origin;
+        self.width = grid.width;
+        self.height = grid.height;
+    }
+
+    fn index(&self, position: Position) -> Option<usize> {
+        let q = position.q - self.origin.q;
+        let r = position.r - self.origin.r;
+        if (0..self.width).contains(&q) && (0..self.height).contains(&r) {
+            Some((r * self.width + q) as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, position: Position) -> &T {
+        match self.index(position) {
+            Some(index) => &self.data[index],
+            None => &self.default,
+        }
+    }
+
+    pub fn get_mut(&mut self, position: Position) -> &mut T {
+        let index = match self.index(position) {
+            Some(index) => index,
+            None => {
+                self.grow(position);
+                self.index(position)
+                    .expect("The grid has grown to include the position")
+            }
+        };
+        &mut self.data[index]
+    }
+
+    pub fn set(&mut self, position: Position, element: T) {
+        *self.get_mut(position) = element;
+    }
+
+    fn grow(&mut self, position: Position) {
+        let (min_q, min_r, max_q, max_r) = if self.data.is_empty() {
+            (
+                position.q - MARGIN,
+                position.r - MARGIN,
+                position.q + MARGIN,
+                position.r + MARGIN,
+            )
+        } else {
+            let (max_q, max_r) = (
+                self.origin.q + self.width - 1,
+                self.origin.r + self.height - 1,
+            );
+            (
+                if position.q < self.origin.q {
+                    position.q - MARGIN
+                } else {
+                    self.origin.q
+                },
+                if position.r < self.origin.r {
+                    position.r - MARGIN
+                } else {
+                    self.origin.r
+                },
+                if position.q > max_q {
+                    position.q + MARGIN
+                } else {
+                    max_q
+                },
+                if position.r > max_r {
+                    position.r + MARGIN
+                } else {
+                    max_r
+                },
+            )
+        };
+        let mut grown = Self {
+            data: vec![self.default.clone(); ((max_q - min_q + 1) * (max_r - min_r + 1)) as usize],
+            origin: Position::new(min_q, min_r),
+            width: max_q - min_q + 1,
+            height: max_r - min_r + 1,
+            default: self.default.clone(),
+        };
+        let positions = self.positions().collect::<Vec<_>>();
+        for (position, value) in positions.into_iter().zip(std::mem::take(&mut self.data)) {
+            let index = grown.index(position).expect("The grown grid is larger");
+            grown.data[index] = value;
+        }
+        *self = grown;
+    }
+
+    // Every position the grid stores a value for, row by row
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let (origin, width) = (self.origin, self.width);
+        (0..self.height)
+            .flat_map(move |r| (0..width).map(move |q| Position::new(origin.q + q, origin.r + r)))
+    }
+}
+
+impl<T> Default for HexGrid<T>
+where
+    T: Clone + Default,
+{
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+// Grids are equal when they hold the same values, no matter how far they have grown
+impl<T> PartialEq for HexGrid<T>
+where
+    T: Clone + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.default == other.default
+            && self
+                .positions()
+                .chain(other.positions())
+                .all(|position| self.get(position) == other.get(position))
+    }
+}
+
+impl<T> Eq for HexGrid<T> where T: Clone + Eq {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_new_insert_get() {
+        let mut grid = HexGrid::new(0_i32);
+        let position = Position::new(0, 1);
+        grid.set(position, 1);
+        assert_eq!(*grid.get(position), 1);
+        assert_eq!(*grid.get(Position::new(1000, -1000)), 0);
+    }
+
+    #[test]
+    fn tests_with_bounds_of() {
+        let mut grid = HexGrid::new(0_u8);
+        grid.set(Position::new(-3, 2), 1);
+        grid.set(Position::new(5, -7), 2);
+        let mut other = HexGrid::with_bounds_of(&grid, false);
+        let positions = grid.positions().collect::<Vec<_>>();
+        assert!(other.positions().eq(positions.iter().copied()));
+        for position in positions.iter() {
+            other.set(*position, true);
+        }
+        assert!(other.positions().eq(positions.iter().copied()));
+        assert!(!other.get(Position::new(100, 0)));
+        let data = other.data.as_ptr();
+        other.reset_to_bounds_of(&HexGrid::with_bounds_of(&grid, 0_i8));
+        assert!(positions.iter().all(|position| !other.get(*position)));
+        assert_eq!(other.data.as_ptr(), data);
+    }
+
+    #[test]
+    fn tests_grow_without_aliasing() {
+        let mut grid = HexGrid::new(0_i32);
+        let positions = [
+            (0, 0),
+            (-1, 0),
+            (31, 0),
+            (-32, 5),
+            (0, 32),
+            (7, -100),
+            (100, 100),
+        ]
+        .map(|(q, r)| Position::new(q, r));
+        for (i, position) in positions.iter().enumerate() {
+            *grid.get_mut(*position) += i as i32 + 1;
+        }
+        for (i, position) in positions.iter().enumerate() {
+            assert_eq!(*grid.get(*position), i as i32 + 1, "{position}");
+        }
+        assert_eq!(*grid.get(Position::new(32, 0)), 0);
+        // a grid that has grown further still equals one with the same values
+        let mut other = HexGrid::new(0_i32);
+        for (i, position) in positions.iter().enumerate().rev() {
+            other.set(*position, i as i32 + 1);
+        }
+        other.set(Position::new(-500, 500), 0);
+        assert_eq!(grid, other);
+        other.set(Position::new(-500, 500), 1);
+        assert_ne!(grid, other);
+    }
+}
@@ -2,11 +2,13 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     fmt,
     fs::{File, OpenOptions},
     io::{self, prelude::*, BufRead},
 };
 
+use crate::annotation::{Annotation, Nag};
 use crate::color::Color;
 use crate::game_error::GameError;
 use crate::game_result::GameResult;
@@ -17,6 +19,9 @@ pub struct History {
     pub moves: Vec<(String, String)>,
     pub result: GameResult,
     pub game_type: GameType,
+    // keyed by the index of the annotated move
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<usize, Annotation>,
 }
 
 impl fmt::Display for History {
@@ -35,6 +40,7 @@ impl History {
             moves: Vec::new(),
             result: GameResult::Unknown,
             game_type: GameType::default(),
+            annotations: BTreeMap::new(),
         }
     }
 
@@ -49,6 +55,10 @@ impl History {
                 found: "NA".to_string(),
                 typ: "Piece".to_string(),
             })?;
+            if *piece == "pass" {
+                history.moves.push(("pass".to_string(), "".to_string()));
+                continue;
+            }
             let pos = split.get(1).ok_or(GameError::ParsingError {
                 found: "NA".to_string(),
                 typ: "Position".to_string(),
@@ -66,6 +76,49 @@ impl History {
         self.moves.push((piece.into(), pos.into()));
     }
 
+    // Adds to the annotation the move already has
+    pub fn annotate(&mut self, index: usize, annotation: Annotation) {
+        self.annotations.entry(index).or_default().merge(annotation);
+    }
+
+    pub fn annotation(&self, index: usize) -> Option<&Annotation> {
+        self.annotations.get(&index)
+    }
+
+    // Moves that were played, without the entries State records when a game ends
+    pub fn played_moves(&self) -> impl Iterator<Item = &(String, String)> {
+        self.moves
+            .iter()
+            .filter(|(piece, pos)| pos != "won" && piece != "It's a draw")
+    }
+
+    // Headers come after GameType and before Result, e.g. [("White", "Dumbot")]
+    pub fn to_pgn(&self, headers: &[(&str, &str)]) -> String {
+        let mut pgn = format!("[GameType \"{}\"]\n", self.game_type);
+        for (name, value) in headers {
+            pgn += &format!("[{name} \"{}\"]\n", value.replace('"', "'"));
+        }
+        let result = match self.result {
+            GameResult::Winner(Color::White) => "1-0",
+            GameResult::Winner(Color::Black) => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        };
+        pgn += &format!("[Result \"{result}\"]\n\n");
+        for (i, (piece, pos)) in self.played_moves().enumerate() {
+            if i == 0 || piece == "pass" {
+                pgn += &format!("{}. {piece}", i + 1);
+            } else {
+                pgn += &format!("{}. {piece} {pos}", i + 1);
+            }
+            if let Some(annotation) = self.annotation(i) {
+                pgn += &annotation.to_pgn();
+            }
+            pgn += "\n";
+        }
+        pgn
+    }
+
     fn parse_game_result(&mut self, str: &str) {
         match str {
             "\"1-0\"]" => self.result = GameResult::Winner(Color::White),
@@ -124,42 +177,89 @@ impl History {
         Ok(())
     }
 
-    pub fn from_filepath(file_path: &str) -> Result<Self, GameError> {
-        let mut history = History::new();
+    // A move line with its annotations, e.g. `3. wQ wG1/!? {a comment [%clk 0:04:55]}`.
+    // Annotations without a move belong to the move before them.
+    fn parse_move_line(&mut self, line: &str) -> Result<(), GameError> {
         lazy_static! {
-            static ref HEADER: Regex = Regex::new(r"\[.*").expect("This regex should compile");
+            static ref COMMENT: Regex =
+                Regex::new(r"\{([^}]*)\}").expect("This regex should compile");
+        }
+        let mut annotation = Annotation::default();
+        for caps in COMMENT.captures_iter(line) {
+            annotation.merge(Annotation::from_comment(&caps[1]));
+        }
+        let rest = COMMENT.replace_all(line, " ");
+        let mut tokens = Vec::new();
+        for token in rest.split_whitespace() {
+            match token.parse::<Nag>() {
+                Ok(nag) => annotation.nags.push(nag),
+                Err(_) => tokens.push(token),
+            }
         }
+        // symbols can be attached to the move, e.g. `wQ wG1/!?`
+        if tokens.len() > 1 {
+            if let Some(last) = tokens.last_mut() {
+                let trimmed = last.trim_end_matches(['!', '?']);
+                if let Ok(nag) = last[trimmed.len()..].parse::<Nag>() {
+                    annotation.nags.insert(0, nag);
+                    *last = trimmed;
+                }
+            }
+        }
+        self.parse_turn(&tokens)?;
+        if !annotation.is_empty() {
+            if let Some(index) = self.moves.len().checked_sub(1) {
+                self.annotate(index, annotation);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn from_filepath(file_path: &str) -> Result<Self, GameError> {
+        let mut history = History::new();
         lazy_static! {
-            static ref RESULT: Regex = Regex::new(r"\[Result").expect("This regex should compile");
+            static ref RESULT: Regex = Regex::new(r"^\[Result").expect("This regex should compile");
         }
         lazy_static! {
             static ref GAME_TYPE_LINE: Regex =
-                Regex::new(r"\[GameType.*").expect("This regex should compile");
+                Regex::new(r"^\[GameType.*").expect("This regex should compile");
         }
-        match File::open(file_path) {
-            Ok(file) => {
-                for line in io::BufReader::new(file).lines().flatten() {
-                    if line.is_empty() {
-                        continue;
-                    }
-                    let tokens = line.split_whitespace().collect::<Vec<&str>>();
-                    if RESULT.is_match(&line) {
-                        if let Some(game_result) = tokens.get(1) {
-                            history.parse_game_result(game_result);
-                        }
-                    }
-                    if GAME_TYPE_LINE.is_match(&line) {
-                        history.parse_game_type(&line)?;
-                    }
-                    if HEADER.is_match(&line) {
-                        continue;
+        let file_error = |e: io::Error| GameError::FileError {
+            path: file_path.to_string(),
+            reason: e.to_string(),
+        };
+        let file = File::open(file_path).map_err(file_error)?;
+        // comments can span several lines
+        let mut pending = String::new();
+        for line in io::BufReader::new(file).lines() {
+            let line = line.map_err(file_error)?;
+            if line.is_empty() {
+                continue;
+            }
+            if pending.is_empty() && line.starts_with('[') {
+                let tokens = line.split_whitespace().collect::<Vec<&str>>();
+                if RESULT.is_match(&line) {
+                    if let Some(game_result) = tokens.get(1) {
+                        history.parse_game_result(game_result);
                     }
-                    history.parse_turn(&tokens)?;
                 }
+                if GAME_TYPE_LINE.is_match(&line) {
+                    history.parse_game_type(&line)?;
+                }
+                continue;
             }
-            Err(e) => {
-                println!("Couldn't open file because: {e}");
+            pending += &line;
+            pending.push(' ');
+            if pending.matches('{').count() > pending.matches('}').count() {
+                continue;
             }
+            history.parse_move_line(&std::mem::take(&mut pending))?;
+        }
+        if !pending.is_empty() {
+            return Err(GameError::ParsingError {
+                found: pending,
+                typ: "comment, it is never closed".to_string(),
+            });
         }
         Ok(history)
     }
@@ -176,3 +276,93 @@ impl History {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, time::Duration};
+
+    #[test]
+    fn tests_to_pgn_round_trip() {
+        for entry in fs::read_dir("./test_pgns/valid/").expect("Should be valid directory") {
+            let path = entry.expect("PGN").path();
+            let history = History::from_filepath(&path.display().to_string()).unwrap();
+            let pgn = history.to_pgn(&[("White", "w \"bot\""), ("Black", "b")]);
+            let copy = std::env::temp_dir().join(format!(
+                "to_pgn_{}_{}",
+                std::process::id(),
+                path.file_name().unwrap().to_string_lossy()
+            ));
+            fs::write(&copy, &pgn).unwrap();
+            let parsed = History::from_filepath(&copy.display().to_string()).unwrap();
+            fs::remove_file(&copy).unwrap();
+            assert_eq!(parsed, history, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn tests_annotations() {
+        let path = std::env::temp_dir().join(format!("annotated_{}.pgn", std::process::id()));
+        fs::write(
+            &path,
+            "[GameType \"Base\"]\n[Result \"*\"]\n\n\
+             1. wA1 {Ant opening [%clk 0:05:00]}\n\
+             2. bA1 wA1-?! $14 {\n  risky,\n  [%eval -0.4] [%clk 0:04:58.5]\n}\n\
+             3. wG1 -wA1 !!\n{a second comment}\n\
+             4. bG1 bA1-\n",
+        )
+        .unwrap();
+        let history = History::from_filepath(&path.display().to_string()).unwrap();
+        assert_eq!(history.moves.len(), 4);
+        assert_eq!(history.moves[1], ("bA1".to_string(), "wA1-".to_string()));
+        let first = history.annotation(0).unwrap();
+        assert_eq!(first.comment.as_deref(), Some("Ant opening"));
+        assert_eq!(first.clock, Some(Duration::from_secs(300)));
+        let second = history.annotation(1).unwrap();
+        assert_eq!(second.nags, vec![Nag::Dubious, Nag::Other(14)]);
+        assert_eq!(second.comment.as_deref(), Some("risky,"));
+        assert_eq!(second.evaluation, Some(-40));
+        assert_eq!(second.clock, Some(Duration::from_millis(298_500)));
+        let third = history.annotation(2).unwrap();
+        assert_eq!(third.nags, vec![Nag::Brilliant]);
+        assert_eq!(third.comment.as_deref(), Some("a second comment"));
+        assert_eq!(history.annotation(3), None);
+        // the annotations survive writing and reading the game and serde
+        fs::write(&path, history.to_pgn(&[])).unwrap();
+        assert_eq!(
+            History::from_filepath(&path.display().to_string()).unwrap(),
+            history
+        );
+        let json = serde_json::to_string(&history).unwrap();
+        assert_eq!(serde_json::from_str::<History>(&json).unwrap(), history);
+        // games without annotations serialize like before
+        let plain = History::new_from_str("wA1 .".to_string()).unwrap();
+        assert!(!serde_json::to_string(&plain)
+            .unwrap()
+            .contains("annotations"));
+        fs::write(&path, "1. wA1 {never closed\n2. bA1 wA1-\n").unwrap();
+        assert!(History::from_filepath(&path.display().to_string()).is_err());
+        fs::remove_file(&path).unwrap();
+        // files that can't be read aren't empty games
+        for path in ["./test_pgns/valid/missing.pgn", "./test_pgns"] {
+            assert!(matches!(
+                History::from_filepath(path),
+                Err(GameError::FileError { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn tests_to_pgn() {
+        let mut history =
+            History::new_from_str("wA1 .;bA1 wA1-;pass;wG1 -wA1;bG1 bA1-".to_string()).unwrap();
+        history.record_move("w", "won");
+        history.result = GameResult::Winner(Color::White);
+        assert_eq!(history.played_moves().count(), 5);
+        assert_eq!(
+            history.to_pgn(&[("Event", "test")]),
+            "[GameType \"Base\"]\n[Event \"test\"]\n[Result \"1-0\"]\n\n\
+             1. wA1\n2. bA1 wA1-\n3. pass\n4. wG1 -wA1\n5. bG1 bA1-\n"
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IllegalMoveReason {
+    Pinned,
+    JustMoved,
+    QueenNotPlayed,
+    QueenRequired,
+    TournamentQueenOpening,
+    WrongColor,
+    NotOnBoard,
+    NotInGame,
+    CoveredPiece,
+    Gated,
+    DestinationNotReachable,
+    SpawnTouchesOpponent,
+    NotASpawnPosition,
+    MovesAvailable,
+    GameOver,
+}
+
+impl fmt::Display for IllegalMoveReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            Self::Pinned => "Piece is pinned.",
+            Self::JustMoved => "Piece was moved on the last turn.",
+            Self::QueenNotPlayed => "Pieces can only move once the queen is played.",
+            Self::QueenRequired => "Can't spawn another piece. Queen is required.",
+            Self::TournamentQueenOpening => "Can't spawn Queen. Game uses tournament rules.",
+            Self::WrongColor => "It is not this color's turn.",
+            Self::NotOnBoard => "This piece is not on the board.",
+            Self::NotInGame => "This piece is not part of this game.",
+            Self::CoveredPiece => "Trying to move a covered piece.",
+            Self::Gated => "Piece can't slide through a gate.",
+            Self::DestinationNotReachable => "Piece can't reach this position.",
+            Self::SpawnTouchesOpponent => "Piece can't be spawned next to an opponent's piece.",
+            Self::NotASpawnPosition => "Piece can't be spawned on this position.",
+            Self::MovesAvailable => "Trying to pass when there are available moves.",
+            Self::GameOver => "The game is over.",
+        };
+        write!(f, "{reason}")
+    }
+}
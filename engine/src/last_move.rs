@@ -0,0 +1,61 @@
+use crate::{piece::Piece, position::Position};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LastMove {
+    pub piece: Piece,
+    // None if the piece was spawned
+    pub from: Option<Position>,
+    pub to: Position,
+    // the pillbug or mosquito that moved the piece with its ability
+    pub thrown_by: Option<Piece>,
+}
+
+impl LastMove {
+    pub fn new_spawn(piece: Piece, to: Position) -> Self {
+        Self {
+            piece,
+            from: None,
+            to,
+            thrown_by: None,
+        }
+    }
+
+    pub fn new_move(piece: Piece, from: Position, to: Position) -> Self {
+        Self {
+            piece,
+            from: Some(from),
+            to,
+            thrown_by: None,
+        }
+    }
+
+    pub fn was_thrown(&self) -> bool {
+        self.thrown_by.is_some()
+    }
+
+    // The piece that just moved can't move, be thrown or use its ability on the next turn
+    pub fn stuns(&self, piece: Piece, position: Position) -> bool {
+        self.piece == piece && self.to == position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bug::Bug, color::Color};
+
+    #[test]
+    fn tests_stuns() {
+        let ant = Piece::new_from(Bug::Ant, Color::White, 1);
+        let pillbug = Piece::new_from(Bug::Pillbug, Color::Black, 0);
+        let last_move = LastMove::new_move(ant, Position::new(0, 0), Position::new(1, 0));
+        assert!(last_move.stuns(ant, Position::new(1, 0)));
+        assert!(!last_move.stuns(ant, Position::new(0, 0)));
+        assert!(!last_move.stuns(pillbug, Position::new(1, 0)));
+        assert!(!last_move.was_thrown());
+        let spawn = LastMove::new_spawn(ant, Position::new(2, 0));
+        assert_eq!(spawn.from, None);
+        assert!(spawn.stuns(ant, Position::new(2, 0)));
+    }
+}
@@ -4,8 +4,10 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
 pub enum LastTurn {
     Pass,
+    // the player had no legal move or spawn and passed automatically
     Shutout,
     Move(Position, Position),
+    Spawn(Position),
     #[default]
     None,
 }
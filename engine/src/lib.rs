@@ -1,17 +1,41 @@
+pub mod annotation;
 pub mod board;
+pub mod board_builder;
+pub mod board_change;
 pub mod bug;
 pub mod bug_stack;
 pub mod color;
+pub mod corpus;
 pub mod direction;
+pub mod features;
+pub mod ffi;
+pub mod fingerprint;
 pub mod game_control;
 pub mod game_error;
 pub mod game_result;
 pub mod game_status;
 pub mod game_type;
+pub mod hex_grid;
 pub mod history;
+pub mod illegal_move_reason;
+pub mod last_move;
 pub mod last_turn;
+pub mod move_description;
+pub mod move_explanation;
+pub mod move_list;
+pub mod perft;
 pub mod piece;
 pub mod player;
 pub mod position;
+pub mod position_notation;
+pub mod repl;
+pub mod rule_set;
+pub mod search;
+pub mod self_play;
+pub mod shared_state;
 pub mod state;
-pub mod torus_array;
+pub mod stats;
+pub mod tactics;
+pub mod training;
+pub mod turn;
+pub mod validation;
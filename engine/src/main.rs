@@ -1,71 +1,552 @@
-use hive_lib::game_error::GameError;
-use hive_lib::game_result::GameResult;
-use hive_lib::game_status::GameStatus;
+use clap::{Parser, Subcommand, ValueEnum};
+use hive_lib::color::Color;
+use hive_lib::corpus::pgn_files;
+use hive_lib::fingerprint::DedupeReport;
 use hive_lib::game_type::GameType;
-use hive_lib::history::History;
-use hive_lib::state::State;
-use std::env;
-
-fn play_game_from_file(file_path: &str) -> Result<(), GameError> {
-    let history = History::from_filepath(file_path)?;
-    let mut state: State = State::new(GameType::default(), false);
-    for _ in 0..1 {
-        state = State::new_from_history(&history)?;
-    }
-    if let GameStatus::Finished(GameResult::Winner(winner)) = state.game_status {
-        println!("State says {winner} won!");
-    }
-    if let GameStatus::Finished(GameResult::Draw) = state.game_status {
-        println!("State says it's a draw");
-    }
-    if let GameResult::Winner(winner) = history.result {
-        println!("History says {winner} won!");
-    }
-    if let GameResult::Winner(hw) = history.result {
-        if let GameStatus::Finished(GameResult::Winner(sw)) = state.game_status {
-            if sw != hw {
-                return Err(GameError::ResultMismatch {
-                    reported_result: history.result,
-                    actual_result: GameResult::Winner(sw),
-                });
-            }
-        }
-        if let GameStatus::Finished(GameResult::Draw) = state.game_status {
-            return Err(GameError::ResultMismatch {
-                reported_result: history.result,
-                actual_result: GameResult::Draw,
-            });
+use hive_lib::repl::Repl;
+use hive_lib::self_play::{
+    default_openings, parse_openings, play_match, Engine, MatchConfig, MatchReport,
+};
+use hive_lib::stats::{CorpusStats, Outcomes};
+use hive_lib::training::TrainingData;
+use hive_lib::validation::{Counts, ValidationReport};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(name = "hive_bin", about = "Tools for hive games stored as PGN")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Replays every game and reports the ones that are broken
+    Validate {
+        /// PGN files or directories, directories are searched recursively
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+        /// Number of worker threads, defaults to the number of CPUs
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    /// Aggregates openings, results, game lengths and passes over all valid games
+    Stats {
+        /// PGN files or directories, directories are searched recursively
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Number of moves that make up an opening
+        #[arg(long, default_value_t = 4)]
+        depth: usize,
+        /// Number of openings shown in the table
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        /// Print the statistics as JSON
+        #[arg(long)]
+        json: bool,
+        /// Number of worker threads, defaults to the number of CPUs
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    /// Groups games that are the same, or the start of another game, in any notation or orientation
+    Dedupe {
+        /// PGN files or directories, directories are searched recursively
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Print the groups as JSON
+        #[arg(long)]
+        json: bool,
+        /// Number of worker threads, defaults to the number of CPUs
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    /// Writes every position of the valid games as a training sample
+    Export {
+        /// PGN files or directories, directories are searched recursively
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Directory for npy, file for jsonl
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Npy)]
+        format: ExportFormat,
+        /// Width and height of the grid the board is encoded on, larger hives are skipped
+        #[arg(long, default_value_t = 24)]
+        window: usize,
+        /// Number of worker threads, defaults to the number of CPUs
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    /// Plays two engines against each other and estimates their Elo difference
+    Match {
+        /// First engine, e.g. `search:depth=3,queen=100,free=10` or `random:seed=1`
+        first: Engine,
+        /// Second engine
+        second: Engine,
+        /// Number of games, every opening is played once with each engine as white
+        #[arg(long, default_value_t = 100)]
+        games: usize,
+        /// Time limit per move in milliseconds, an engine that exceeds it loses
+        #[arg(long, default_value_t = 1000)]
+        time_ms: u64,
+        /// Games still running after this many turns are adjudicated a draw
+        #[arg(long, default_value_t = 200)]
+        max_turns: usize,
+        #[arg(long, default_value_t = GameType::Base)]
+        game_type: GameType,
+        /// File with one opening per line, moves separated by ';', e.g. `wA1 .;bG1 wA1-`
+        #[arg(long)]
+        openings: Option<PathBuf>,
+        /// Directory every game is saved to as PGN
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+        /// Number of worker threads, defaults to the number of CPUs
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    /// Plays and inspects a game one command per line, `help` lists the commands
+    Repl {
+        /// PGN file to start from
+        file: Option<PathBuf>,
+        #[arg(long, default_value_t = GameType::Base)]
+        game_type: GameType,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// One NumPy array per field
+    Npy,
+    /// One JSON object per line
+    Jsonl,
+}
+
+struct MatchOptions {
+    games: usize,
+    time_ms: u64,
+    max_turns: usize,
+    game_type: GameType,
+    openings: Option<PathBuf>,
+    out: Option<PathBuf>,
+    json: bool,
+    threads: Option<usize>,
+}
+
+fn thread_pool(threads: Option<usize>) -> Result<rayon::ThreadPool, String> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads.unwrap_or(0))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn validate(paths: Vec<PathBuf>, json: bool, threads: Option<usize>) -> Result<bool, String> {
+    let files = pgn_files(&paths).map_err(|e| e.to_string())?;
+    let report = thread_pool(threads)?.install(|| ValidationReport::new(files));
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?
+        );
+    } else {
+        print_report(&report);
+    }
+    Ok(report.summary.total.invalid == 0)
+}
+
+fn print_report(report: &ValidationReport) {
+    for game in report.invalid() {
+        match (&game.illegal_move, &game.error) {
+            (Some(illegal_move), _) => println!(
+                "{}: turn {}: {} {} -> {}: {}",
+                game.path.display(),
+                illegal_move.turn,
+                illegal_move.piece,
+                illegal_move.from,
+                illegal_move.to,
+                illegal_move.reason
+            ),
+            (None, Some(error)) => println!("{}: {error}", game.path.display()),
+            (None, None) => {}
         }
     }
-    if let GameResult::Draw = history.result {
-        println!("History says game ended in a draw");
-        if let GameStatus::Finished(GameResult::Winner(sw)) = state.game_status {
-            return Err(GameError::ResultMismatch {
-                reported_result: history.result,
-                actual_result: GameResult::Winner(sw),
-            });
+    let summary = &report.summary;
+    println!();
+    print_counts("", [("total".to_string(), summary.total.clone())]);
+    print_counts("game type", summary.by_game_type.clone());
+    print_counts("result", summary.by_result.clone());
+    for (reason, count) in summary.by_reason.iter() {
+        println!("{reason:<24} {count:>8}");
+    }
+}
+
+fn print_counts(title: &str, counts: impl IntoIterator<Item = (String, Counts)>) {
+    println!("{title:<12} {:>8} {:>8} {:>8}", "games", "valid", "invalid");
+    for (name, counts) in counts {
+        println!(
+            "{name:<12} {:>8} {:>8} {:>8}",
+            counts.games, counts.valid, counts.invalid
+        );
+    }
+    println!();
+}
+
+fn stats(
+    paths: Vec<PathBuf>,
+    depth: usize,
+    top: usize,
+    json: bool,
+    threads: Option<usize>,
+) -> Result<bool, String> {
+    let files = pgn_files(&paths).map_err(|e| e.to_string())?;
+    let stats = thread_pool(threads)?.install(|| CorpusStats::new(files, depth));
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&stats).map_err(|e| e.to_string())?
+        );
+    } else {
+        print_stats(&stats, top);
+    }
+    Ok(true)
+}
+
+fn print_stats(stats: &CorpusStats, top: usize) {
+    println!("games            {:>8}", stats.games);
+    println!("skipped          {:>8}", stats.skipped);
+    println!("average length   {:>8.1}", stats.average_length);
+    println!(
+        "passes           {:>8} in {} games, {:.2}% of turns",
+        stats.passes,
+        stats.games_with_passes,
+        stats.pass_rate * 100.0
+    );
+    println!();
+    print_outcomes(&stats.outcomes, &stats.outcomes_by_game_type);
+    print_frequencies("first move", stats.first_moves.iter(), stats.games);
+    let mut final_surround = stats.final_surround.iter().collect::<Vec<_>>();
+    final_surround.sort_by(|a, b| b.1.cmp(a.1));
+    let decided = stats.outcomes.white_wins + stats.outcomes.black_wins;
+    print_frequencies("final surround", final_surround.into_iter(), decided);
+    println!("openings ({} moves)", stats.opening_depth);
+    for (opening, count) in stats.top_openings(top) {
+        println!("{count:>8}  {opening}");
+    }
+}
+
+fn dedupe(paths: Vec<PathBuf>, json: bool, threads: Option<usize>) -> Result<bool, String> {
+    let files = pgn_files(&paths).map_err(|e| e.to_string())?;
+    let report = thread_pool(threads)?.install(|| DedupeReport::new(files));
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?
+        );
+    } else {
+        print_dedupe(&report);
+    }
+    Ok(true)
+}
+
+fn print_dedupe(report: &DedupeReport) {
+    for group in report.groups.iter() {
+        println!(
+            "{}  {} turns  {}",
+            group.fingerprint, group.turns, group.game_type
+        );
+        for game in group.games.iter() {
+            println!("    {}", game.display());
+        }
+        for game in group.prefix_of.iter() {
+            println!("    prefix of {}", game.display());
         }
     }
+    if !report.groups.is_empty() {
+        println!();
+    }
+    println!("games            {:>8}", report.games);
+    println!("skipped          {:>8}", report.skipped);
+    println!("groups           {:>8}", report.groups.len());
+}
+
+fn print_outcomes(total: &Outcomes, by_game_type: &BTreeMap<String, Outcomes>) {
+    println!(
+        "{:<12} {:>8} {:>8} {:>8} {:>8} {:>10}",
+        "game type", "games", "white", "black", "draw", "unfinished"
+    );
+    for (name, outcomes) in std::iter::once(("total", total)).chain(
+        by_game_type
+            .iter()
+            .map(|(name, outcomes)| (name.as_str(), outcomes)),
+    ) {
+        println!(
+            "{name:<12} {:>8} {:>7.1}% {:>7.1}% {:>7.1}% {:>10}",
+            outcomes.games,
+            outcomes.white_win_rate * 100.0,
+            outcomes.black_win_rate * 100.0,
+            outcomes.draw_rate * 100.0,
+            outcomes.unfinished
+        );
+    }
+    println!();
+}
+
+fn print_frequencies<'a>(
+    title: &str,
+    counts: impl Iterator<Item = (&'a String, &'a usize)>,
+    total: usize,
+) {
+    println!("{title:<16} {:>8} {:>8}", "games", "share");
+    for (name, count) in counts {
+        let share = if total > 0 {
+            *count as f64 / total as f64 * 100.0
+        } else {
+            0.0
+        };
+        println!("{name:<16} {count:>8} {share:>7.1}%");
+    }
+    println!();
+}
+
+fn export(
+    paths: Vec<PathBuf>,
+    out: PathBuf,
+    format: ExportFormat,
+    window: usize,
+    threads: Option<usize>,
+) -> Result<bool, String> {
+    if window == 0 {
+        return Err("The window has to be at least 1 wide".to_string());
+    }
+    let files = pgn_files(&paths).map_err(|e| e.to_string())?;
+    let data = thread_pool(threads)?.install(|| TrainingData::new(files, window));
+    match format {
+        ExportFormat::Npy => data.write_npy(&out),
+        ExportFormat::Jsonl => fs::File::create(&out).and_then(|file| data.write_jsonl(file)),
+    }
+    .map_err(|e| format!("{}: {e}", out.display()))?;
+    println!(
+        "{} samples from {} games, skipped {} games and {} positions",
+        data.samples.len(),
+        data.games,
+        data.skipped_games,
+        data.skipped_positions
+    );
+    Ok(true)
+}
+
+fn run_match(first: Engine, second: Engine, options: MatchOptions) -> Result<bool, String> {
+    let openings = match &options.openings {
+        Some(path) => parse_openings(
+            &fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?,
+            options.game_type,
+        )
+        .map_err(|e| e.to_string())?,
+        None => default_openings(options.game_type),
+    };
+    let config = MatchConfig {
+        first,
+        second,
+        games: options.games,
+        time_per_move: Duration::from_millis(options.time_ms),
+        max_turns: options.max_turns,
+        game_type: options.game_type,
+        openings,
+    };
+    let report = thread_pool(options.threads)?
+        .install(|| play_match(&config))
+        .map_err(|e| e.to_string())?;
+    if let Some(out) = &options.out {
+        save_games(&report, out)?;
+    }
+    if options.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?
+        );
+    } else {
+        print_match(&report);
+    }
+    Ok(true)
+}
+
+fn save_games(report: &MatchReport, out: &PathBuf) -> Result<(), String> {
+    fs::create_dir_all(out).map_err(|e| format!("{}: {e}", out.display()))?;
+    for game in report.games.iter() {
+        let (white, black) = match game.first_color {
+            Color::White => (&report.first, &report.second),
+            Color::Black => (&report.second, &report.first),
+        };
+        let round = (game.round + 1).to_string();
+        let termination = game.termination.to_string();
+        let pgn = game.history.to_pgn(&[
+            ("Event", "hive_bin match"),
+            ("Round", &round),
+            ("White", white),
+            ("Black", black),
+            ("Termination", &termination),
+        ]);
+        let path = out.join(format!("game_{round:0>4}.pgn"));
+        fs::write(&path, pgn).map_err(|e| format!("{}: {e}", path.display()))?;
+    }
     Ok(())
 }
 
-fn main() {
-    let game: Vec<String> = env::args().collect();
-    if let Some(game) = game.get(1) {
-        println!("{game}");
-        match play_game_from_file(game) {
-            Ok(_) => {}
-            Err(e) => eprintln!("{e}"),
+fn print_match(report: &MatchReport) {
+    for game in report.games.iter() {
+        println!(
+            "{:>5}  {} as {}  {:<10} {:>4} turns  {:<13} {}",
+            game.round + 1,
+            report.first,
+            game.first_color.name(),
+            game.result.to_string(),
+            game.turns,
+            game.termination.to_string(),
+            game.opening
+        );
+    }
+    println!();
+    println!("{} vs {}", report.first, report.second);
+    println!(
+        "wins {}  draws {}  losses {}  score {:.1}%",
+        report.wins,
+        report.draws,
+        report.losses,
+        report.score * 100.0
+    );
+    match report.elo {
+        Some(elo) => match elo.error {
+            Some(error) => println!("elo {:+.1} +/- {error:.1}", elo.difference),
+            None => println!("elo {:+.1} +/- inf", elo.difference),
+        },
+        None => println!("elo unbounded, one engine scored every point"),
+    }
+}
+
+fn repl(file: Option<PathBuf>, game_type: GameType) -> Result<bool, String> {
+    let mut repl = Repl::new(game_type);
+    if let Some(file) = file {
+        let loaded = repl.load(&file).map_err(|e| e.to_string())?;
+        println!("{loaded}");
+    }
+    let stdin = io::stdin();
+    let prompt = stdin.is_terminal();
+    run_repl(&mut repl, stdin.lock(), io::stdout(), prompt).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+fn run_repl(
+    repl: &mut Repl,
+    input: impl BufRead,
+    mut output: impl Write,
+    prompt: bool,
+) -> io::Result<()> {
+    let mut lines = input.lines();
+    loop {
+        if prompt {
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        let line = line?;
+        if matches!(line.trim(), "quit" | "exit") {
+            return Ok(());
+        }
+        match repl.execute(&line) {
+            Ok(reply) if reply.is_empty() => {}
+            Ok(reply) => writeln!(output, "{reply}")?,
+            Err(e) => writeln!(output, "error: {e}")?,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Validate {
+            paths,
+            json,
+            threads,
+        } => validate(paths, json, threads),
+        Command::Stats {
+            paths,
+            depth,
+            top,
+            json,
+            threads,
+        } => stats(paths, depth, top, json, threads),
+        Command::Dedupe {
+            paths,
+            json,
+            threads,
+        } => dedupe(paths, json, threads),
+        Command::Export {
+            paths,
+            out,
+            format,
+            window,
+            threads,
+        } => export(paths, out, format, window, threads),
+        Command::Match {
+            first,
+            second,
+            games,
+            time_ms,
+            max_turns,
+            game_type,
+            openings,
+            out,
+            json,
+            threads,
+        } => run_match(
+            first,
+            second,
+            MatchOptions {
+                games,
+                time_ms,
+                max_turns,
+                game_type,
+                openings,
+                out,
+                json,
+                threads,
+            },
+        ),
+        Command::Repl { file, game_type } => repl(file, game_type),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::from(2)
         }
-    } else {
-        eprint!("{}", GameError::NoPgnFile);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
+    use hive_lib::game_error::GameError;
+    use hive_lib::history::History;
+    use hive_lib::illegal_move_reason::IllegalMoveReason;
+    use hive_lib::validation::replay;
+
+    fn play_game_from_file(file_path: &str) -> Result<(), GameError> {
+        replay(&History::from_filepath(file_path)?).map(|_| ())
+    }
 
     #[test]
     fn test_play_games_from_valid_files() {
@@ -83,4 +564,136 @@ mod tests {
             assert!(play_game_from_file(&entry).is_err());
         }
     }
+
+    #[test]
+    fn test_illegal_move_reasons_from_invalid_files() {
+        for (file, expected) in [
+            ("duplicated_move.pgn", IllegalMoveReason::JustMoved),
+            (
+                "spawn_unallowed.pgn",
+                IllegalMoveReason::SpawnTouchesOpponent,
+            ),
+            ("white_double_spawn.pgn", IllegalMoveReason::WrongColor),
+            ("move_before_q.pgn", IllegalMoveReason::QueenNotPlayed),
+            ("move_a_just_moved.pgn", IllegalMoveReason::JustMoved),
+            ("ant_climb.pgn", IllegalMoveReason::DestinationNotReachable),
+        ] {
+            match play_game_from_file(&format!("./test_pgns/invalid/{file}")) {
+                Err(GameError::InvalidMove { reason, .. }) => assert_eq!(reason, expected),
+                other => panic!("{file} should be an invalid move, got: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_command() {
+        assert_eq!(
+            validate(vec![PathBuf::from("./test_pgns/valid")], true, Some(2)),
+            Ok(true)
+        );
+        assert_eq!(
+            validate(vec![PathBuf::from("./test_pgns/invalid")], false, None),
+            Ok(false)
+        );
+        assert!(validate(vec![PathBuf::from("./test_pgns/missing")], false, None).is_err());
+    }
+
+    #[test]
+    fn test_stats_command() {
+        let paths = vec![PathBuf::from("./test_pgns")];
+        assert_eq!(stats(paths.clone(), 2, 5, true, Some(2)), Ok(true));
+        assert_eq!(stats(paths, 0, 0, false, None), Ok(true));
+        assert!(stats(
+            vec![PathBuf::from("./test_pgns/missing")],
+            2,
+            5,
+            false,
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_dedupe_command() {
+        let paths = vec![
+            PathBuf::from("./test_pgns/valid"),
+            PathBuf::from("./test_pgns/valid/pass.pgn"),
+        ];
+        assert_eq!(dedupe(paths, true, Some(2)), Ok(true));
+        assert_eq!(
+            dedupe(vec![PathBuf::from("./test_pgns")], false, None),
+            Ok(true)
+        );
+        assert!(dedupe(vec![PathBuf::from("./test_pgns/missing")], false, None).is_err());
+    }
+
+    #[test]
+    fn test_match_command() {
+        let out = std::env::temp_dir().join(format!("hive_match_{}", std::process::id()));
+        let options = |openings, out, json| MatchOptions {
+            games: 2,
+            time_ms: 60_000,
+            max_turns: 40,
+            game_type: GameType::Base,
+            openings,
+            out,
+            json,
+            threads: Some(2),
+        };
+        let first: Engine = "search:depth=1".parse().unwrap();
+        let second: Engine = "random:seed=3".parse().unwrap();
+        assert_eq!(
+            run_match(first, second, options(None, Some(out.clone()), false)),
+            Ok(true)
+        );
+        let saved = pgn_files(&[&out]).unwrap();
+        assert_eq!(saved.len(), 2);
+        for path in saved {
+            assert!(play_game_from_file(&path.display().to_string()).is_ok());
+        }
+        fs::remove_dir_all(&out).unwrap();
+        let openings = out.with_extension("txt");
+        fs::write(&openings, "wA1 .;bA1 wA1-\n").unwrap();
+        assert_eq!(
+            run_match(first, second, options(Some(openings.clone()), None, true)),
+            Ok(true)
+        );
+        fs::write(&openings, "wA1 .;wA2 wA1-\n").unwrap();
+        assert!(run_match(first, second, options(Some(openings.clone()), None, true)).is_err());
+        fs::remove_file(&openings).unwrap();
+    }
+
+    #[test]
+    fn test_repl_command() {
+        let mut repl = Repl::new(GameType::Base);
+        let input = "play wA1\n\nplay bQ wA1-\nplay bA1 wA1-\nquit\nboard\n";
+        let mut output = Vec::new();
+        run_repl(&mut repl, input.as_bytes(), &mut output, false).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "White Ant 1 is placed as the first piece");
+        assert!(lines[2].starts_with("error: "), "{output}");
+        assert_eq!(lines.last(), Some(&"turn 2, white to move"));
+        assert_eq!(repl.ply(), 2);
+    }
+
+    #[test]
+    fn test_export_command() {
+        let paths = vec![PathBuf::from("./test_pgns/valid")];
+        let out = std::env::temp_dir().join(format!("hive_export_{}", std::process::id()));
+        assert_eq!(
+            export(paths.clone(), out.clone(), ExportFormat::Npy, 16, Some(2)),
+            Ok(true)
+        );
+        assert!(out.join("planes.npy").exists());
+        fs::remove_dir_all(&out).unwrap();
+        let jsonl = out.with_extension("jsonl");
+        assert_eq!(
+            export(paths.clone(), jsonl.clone(), ExportFormat::Jsonl, 16, None),
+            Ok(true)
+        );
+        assert!(fs::read_to_string(&jsonl).unwrap().lines().count() > 0);
+        fs::remove_file(&jsonl).unwrap();
+        assert!(export(paths, out, ExportFormat::Jsonl, 0, None).is_err());
+    }
 }
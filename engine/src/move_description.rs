@@ -0,0 +1,218 @@
+// Turns in plain language, e.g. for screen readers, commentary and notifications:
+//
+//   White Ant 2 moves to the northeast of Black Queen
+//   Black Pillbug throws White Beetle 1 to the west of White Spider 1
+//
+// The piece a destination is described by is the one the history notation uses.
+use crate::{
+    board::Board, color::Color, game_error::GameError, last_move::LastMove, last_turn::LastTurn,
+    move_list::Move, piece::Piece, position::Position, state::State, turn::Turn,
+};
+
+impl Color {
+    // "White" or "Black"
+    pub fn title(&self) -> &'static str {
+        match self {
+            Color::White => "White",
+            Color::Black => "Black",
+        }
+    }
+}
+
+impl Piece {
+    // "White Ant 2" or "Black Queen"
+    pub fn long_name(&self) -> String {
+        if self.bug().has_order() {
+            format!(
+                "{} {} {}",
+                self.color().title(),
+                self.bug().name(),
+                self.order()
+            )
+        } else {
+            format!("{} {}", self.color().title(), self.bug().name())
+        }
+    }
+}
+
+impl Move {
+    // The move as it would be played in the given state
+    pub fn describe(&self, state: &State) -> Result<String, GameError> {
+        state.describe_turn(Turn::Move(*self))
+    }
+}
+
+impl State {
+    // Describes a turn before it is played, fails if the turn is illegal
+    pub fn describe_turn(&self, turn: Turn) -> Result<String, GameError> {
+        let mut state = self.clone();
+        // an automatic pass would forget the last move
+        state.rules.automatic_pass = false;
+        state.apply_turn(turn)?;
+        match state.board.last_move {
+            Some(last_move) if turn != Turn::Pass => Ok(describe(&state.board, &last_move)),
+            _ => Ok(format!("{} passes", self.turn_color.title())),
+        }
+    }
+
+    // Describes the turn that was played last, None before the first turn
+    pub fn describe_last_turn(&self) -> Option<String> {
+        let passer = Color::from(self.turn_color.opposite()).title();
+        match self.last_turn {
+            LastTurn::Pass => Some(format!("{passer} passes")),
+            LastTurn::Shutout => Some(format!("{passer} has no legal turn and passes")),
+            LastTurn::Move(..) | LastTurn::Spawn(_) => self
+                .board
+                .last_move
+                .map(|last_move| describe(&self.board, &last_move)),
+            LastTurn::None => None,
+        }
+    }
+}
+
+// Where a piece is now, relative to a neighbor, e.g. "to the northeast of Black Queen"
+fn location(board: &Board, position: Position) -> Option<String> {
+    board
+        .get_neighbor(position)
+        .map(|(neighbor, neighbor_position)| {
+            let direction = neighbor_position.direction(position).to_string();
+            format!(
+                "to the {} of {}",
+                direction.to_lowercase(),
+                neighbor.long_name()
+            )
+        })
+}
+
+// Describes the last move of a board the move has been played on
+fn describe(board: &Board, last_move: &LastMove) -> String {
+    let piece = last_move.piece.long_name();
+    let to = last_move.to;
+    let level = board.level(to);
+    if level > 1 {
+        let stack = board.board.get(to);
+        let below = stack.pieces[level - 2].long_name();
+        return match last_move.from {
+            Some(_) => format!("{piece} climbs on top of {below}"),
+            None => format!("{piece} is placed on top of {below}"),
+        };
+    }
+    let Some(location) = location(board, to) else {
+        return format!("{piece} is placed as the first piece");
+    };
+    match (last_move.from, last_move.thrown_by) {
+        (None, _) => format!("{piece} is placed {location}"),
+        (Some(_), Some(thrower)) => {
+            format!("{} throws {piece} {location}", thrower.long_name())
+        }
+        (Some(from), None) if board.occupied(from) => {
+            format!("{piece} climbs down {location}")
+        }
+        (Some(_), None) => format!("{piece} moves {location}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bug::Bug, game_type::GameType, history::History};
+
+    fn play(moves: &[(&str, &str)]) -> State {
+        let mut state = State::new(GameType::MLP, true);
+        for (piece, position) in moves {
+            state.play_turn_from_notation(piece, position).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn tests_long_name() {
+        assert_eq!(
+            Piece::new_from(Bug::Ant, Color::White, 2).long_name(),
+            "White Ant 2"
+        );
+        assert_eq!(
+            Piece::new_from(Bug::Queen, Color::Black, 0).long_name(),
+            "Black Queen"
+        );
+    }
+
+    #[test]
+    fn tests_describe_turn() {
+        let state = State::new(GameType::MLP, true);
+        let ant = Piece::new_from(Bug::Ant, Color::White, 1);
+        let spawn = Turn::Spawn(ant, Position::initial_spawn_position());
+        assert_eq!(
+            state.describe_turn(spawn).unwrap(),
+            "White Ant 1 is placed as the first piece"
+        );
+        // describing doesn't play the turn
+        assert_eq!(state.board.positions.iter().flatten().count(), 0);
+
+        let state = play(&[
+            ("wA1", "."),
+            ("bA1", "wA1-"),
+            ("wQ", "-wA1"),
+            ("bQ", "bA1-"),
+        ]);
+        assert_eq!(
+            state.describe_last_turn().unwrap(),
+            "Black Queen is placed to the east of Black Ant 1"
+        );
+        let queen = Piece::new_from(Bug::Queen, Color::White, 0);
+        let from = state.board.position_of_piece(queen).unwrap();
+        let to = from.to(crate::direction::Direction::SE);
+        let description = Move {
+            piece: queen,
+            from,
+            to,
+        }
+        .describe(&state)
+        .unwrap();
+        assert_eq!(
+            description,
+            "White Queen moves to the southwest of White Ant 1"
+        );
+        let illegal = Move {
+            piece: queen,
+            from,
+            to: Position::new(5, 5),
+        };
+        assert!(illegal.describe(&state).is_err());
+        assert_eq!(State::new(GameType::MLP, true).describe_last_turn(), None);
+    }
+
+    #[test]
+    fn tests_describe_throw_and_climb() {
+        let history = History::from_filepath("./test_pgns/valid/descend.pgn").unwrap();
+        let mut state = State::new(history.game_type, true);
+        let mut throws = 0;
+        let mut climbs = 0;
+        for (piece, position) in history.played_moves() {
+            let expected = state.clone();
+            state.play_turn_from_notation(piece, position).unwrap();
+            let description = state.describe_last_turn().unwrap();
+            if let Some(last_move) = state.board.last_move {
+                if let Some(thrower) = last_move.thrown_by {
+                    assert!(description.starts_with(&format!("{} throws ", thrower.long_name())));
+                    throws += 1;
+                }
+                if last_move.from.is_some() && state.board.level(last_move.to) > 1 {
+                    assert!(description.contains(" climbs on top of "), "{description}");
+                    climbs += 1;
+                }
+                let turn = match last_move.from {
+                    Some(from) => Turn::Move(Move {
+                        piece: last_move.piece,
+                        from,
+                        to: last_move.to,
+                    }),
+                    None => Turn::Spawn(last_move.piece, last_move.to),
+                };
+                assert_eq!(expected.describe_turn(turn).unwrap(), description);
+            }
+        }
+        assert!(throws > 0);
+        assert!(climbs > 0);
+    }
+}
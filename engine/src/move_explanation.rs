@@ -0,0 +1,359 @@
+use crate::{
+    bug::Bug, color::Color, hex_grid::HexGrid, illegal_move_reason::IllegalMoveReason,
+    piece::Piece, position::Position, state::State,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Check {
+    GameInProgress,
+    PieceInGame,
+    TurnColor {
+        turn_color: Color,
+    },
+    TournamentOpening,
+    QueenDeadline,
+    SpawnPosition {
+        // top pieces of the opponent next to the target position
+        opponent_neighbors: Vec<Piece>,
+    },
+    TopOfStack {
+        covered_by: Option<Piece>,
+    },
+    QueenPlayed,
+    LastMoved {
+        last_moved: Option<Piece>,
+    },
+    OneHive {
+        // the parts the hive would fall apart into if the piece was lifted
+        separated: Vec<Vec<Position>>,
+    },
+    Gate {
+        between: Option<(Position, Position)>,
+    },
+    SpiderSteps {
+        // shortest crawl to the target, a spider needs exactly three steps
+        steps: Option<usize>,
+    },
+    Reachable,
+}
+
+impl Check {
+    pub fn reason(&self) -> IllegalMoveReason {
+        match self {
+            Check::GameInProgress => IllegalMoveReason::GameOver,
+            Check::PieceInGame => IllegalMoveReason::NotInGame,
+            Check::TurnColor { .. } => IllegalMoveReason::WrongColor,
+            Check::TournamentOpening => IllegalMoveReason::TournamentQueenOpening,
+            Check::QueenDeadline => IllegalMoveReason::QueenRequired,
+            Check::SpawnPosition { opponent_neighbors } if !opponent_neighbors.is_empty() => {
+                IllegalMoveReason::SpawnTouchesOpponent
+            }
+            Check::SpawnPosition { .. } => IllegalMoveReason::NotASpawnPosition,
+            Check::TopOfStack { .. } => IllegalMoveReason::CoveredPiece,
+            Check::QueenPlayed => IllegalMoveReason::QueenNotPlayed,
+            Check::LastMoved { .. } => IllegalMoveReason::JustMoved,
+            Check::OneHive { .. } => IllegalMoveReason::Pinned,
+            Check::Gate { .. } => IllegalMoveReason::Gated,
+            Check::SpiderSteps { .. } => IllegalMoveReason::DestinationNotReachable,
+            Check::Reachable => IllegalMoveReason::DestinationNotReachable,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub check: Check,
+    pub passed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MoveExplanation {
+    pub piece: Piece,
+    // None if the piece is still in the reserve
+    pub from: Option<Position>,
+    pub to: Option<Position>,
+    pub steps: Vec<Step>,
+    pub destinations: Vec<Position>,
+}
+
+impl MoveExplanation {
+    pub fn new(state: &State, piece: Piece, to: Option<Position>) -> Self {
+        let mut explanation = Self {
+            piece,
+            from: state.board.position_of_piece(piece),
+            to,
+            steps: Vec::new(),
+            destinations: Vec::new(),
+        };
+        explanation.check(Check::GameInProgress, !state.game_status.is_finished());
+        match explanation.from {
+            None => explanation.explain_spawn(state),
+            Some(from) => explanation.explain_move(state, from),
+        }
+        explanation
+    }
+
+    pub fn legal(&self) -> bool {
+        self.steps.iter().all(|step| step.passed)
+    }
+
+    pub fn reason(&self) -> Option<IllegalMoveReason> {
+        self.steps
+            .iter()
+            .find(|step| !step.passed)
+            .map(|step| step.check.reason())
+    }
+
+    fn check(&mut self, check: Check, passed: bool) {
+        self.steps.push(Step { check, passed });
+    }
+
+    fn explain_spawn(&mut self, state: &State) {
+        let piece = self.piece;
+        let turn_color = state.turn_color;
+        self.check(Check::TurnColor { turn_color }, piece.is_color(turn_color));
+        self.check(
+            Check::PieceInGame,
+            state.rules.piece_allowed(piece.bug(), piece.order()),
+        );
+        if piece.bug() == Bug::Queen {
+            self.check(Check::TournamentOpening, state.queen_allowed());
+        } else {
+            self.check(
+                Check::QueenDeadline,
+                !state
+                    .board
+                    .queen_required(state.turn, piece.color(), &state.rules),
+            );
+        }
+        if self.legal() {
+            self.destinations = state.board.spawnable_positions(turn_color).collect();
+        }
+        if let Some(to) = self.to {
+            let spawnable = state.board.spawnable(piece.color(), to);
+            let mut opponent_neighbors = Vec::new();
+            // only from the third piece on the colors have to be kept apart
+            if !spawnable && state.turn > 1 && state.board.is_negative_space(to) {
+                opponent_neighbors = state
+                    .board
+                    .top_layer_neighbors(to)
+                    .filter(|neighbor| !neighbor.is_color(piece.color()))
+                    .collect();
+            }
+            self.check(Check::SpawnPosition { opponent_neighbors }, spawnable);
+        }
+    }
+
+    fn explain_move(&mut self, state: &State, from: Position) {
+        let piece = self.piece;
+        let board = &state.board;
+        let top_piece = board.top_piece(from);
+        self.check(
+            Check::TopOfStack {
+                covered_by: top_piece.filter(|top| *top != piece),
+            },
+            top_piece == Some(piece),
+        );
+        self.check(Check::QueenPlayed, board.queen_played(state.turn_color));
+        let last_moved = board.last_move.map(|last_move| last_move.piece);
+        self.check(
+            Check::LastMoved { last_moved },
+            !board.is_stunned(piece, from),
+        );
+        if board.level(from) == 1 {
+            let separated = if board.is_pinned(piece) {
+                Self::separated_hive(state, from)
+            } else {
+                Vec::new()
+            };
+            let passed = separated.is_empty();
+            self.check(Check::OneHive { separated }, passed);
+        }
+        self.destinations = board
+            .moves(state.turn_color, &state.rules)
+            .remove(&(piece, from))
+            .unwrap_or_default();
+        if !piece.is_color(state.turn_color) && self.destinations.is_empty() {
+            // the opponent's pieces can only be moved by a pillbug
+            let turn_color = state.turn_color;
+            self.check(Check::TurnColor { turn_color }, false);
+        }
+        if let Some(to) = self.to {
+            self.explain_destination(state, from, to);
+        }
+    }
+
+    fn explain_destination(&mut self, state: &State, from: Position, to: Position) {
+        let board = &state.board;
+        if self.destinations.contains(&to) {
+            self.check(Check::Reachable, true);
+            return;
+        }
+        // grasshoppers and ladybugs don't slide, so gates don't stop them
+        let slides = !matches!(self.piece.bug(), Bug::Grasshopper | Bug::Ladybug);
+        if slides
+            && from.is_neighbor(to)
+            && !board.occupied(to)
+            && board.gated(board.level(from), from, to)
+        {
+            let between = Some(from.common_adjacent_positions(to));
+            self.check(Check::Gate { between }, false);
+            return;
+        }
+        if self.piece.bug() == Bug::Spider && board.level(from) == 1 {
+            let steps = Bug::crawl_distance(from, to, board);
+            self.check(Check::SpiderSteps { steps }, false);
+            return;
+        }
+        self.check(Check::Reachable, false);
+    }
+
+    fn separated_hive(state: &State, lifted: Position) -> Vec<Vec<Position>> {
+        let board = &state.board;
+        let mut visited = HexGrid::new(false);
+        visited.set(lifted, true);
+        let mut separated = Vec::new();
+        for start in board.positions_taken_around(lifted) {
+            if *visited.get(start) {
+                continue;
+            }
+            visited.set(start, true);
+            let mut part = vec![start];
+            let mut unexplored = vec![start];
+            while let Some(pos) = unexplored.pop() {
+                for next in board.positions_taken_around(pos) {
+                    if !*visited.get(next) {
+                        visited.set(next, true);
+                        part.push(next);
+                        unexplored.push(next);
+                    }
+                }
+            }
+            part.sort();
+            separated.push(part);
+        }
+        if separated.len() < 2 {
+            return Vec::new();
+        }
+        separated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game_type::GameType, last_move::LastMove};
+
+    fn state_with(pieces: &[(Bug, Color, usize, Position)]) -> State {
+        let mut state = State::new(GameType::Base, false);
+        for (bug, color, order, position) in pieces {
+            state
+                .board
+                .insert(*position, Piece::new_from(*bug, *color, *order));
+        }
+        state.turn = 10;
+        state.board.last_move = None;
+        state
+    }
+
+    #[test]
+    fn tests_explain_pinned() {
+        let state = state_with(&[
+            (Bug::Queen, Color::White, 0, Position::new(0, 0)),
+            (Bug::Ant, Color::White, 1, Position::new(1, 0)),
+            (Bug::Queen, Color::Black, 0, Position::new(2, 0)),
+        ]);
+        let ant = Piece::new_from(Bug::Ant, Color::White, 1);
+        let explanation = state.explain(ant, Some(Position::new(1, -1)));
+        assert!(!explanation.legal());
+        assert_eq!(explanation.reason(), Some(IllegalMoveReason::Pinned));
+        assert!(explanation.steps.contains(&Step {
+            check: Check::OneHive {
+                separated: vec![vec![Position::new(0, 0)], vec![Position::new(2, 0)]],
+            },
+            passed: false,
+        }));
+        assert!(explanation.destinations.is_empty());
+    }
+
+    #[test]
+    fn tests_explain_gated() {
+        let state = state_with(&[
+            (Bug::Queen, Color::White, 0, Position::new(0, 0)),
+            (Bug::Ant, Color::Black, 1, Position::new(1, -1)),
+            (Bug::Grasshopper, Color::Black, 1, Position::new(2, -1)),
+            (Bug::Grasshopper, Color::Black, 2, Position::new(2, 0)),
+            (Bug::Grasshopper, Color::Black, 3, Position::new(1, 1)),
+            (Bug::Ant, Color::Black, 2, Position::new(0, 1)),
+        ]);
+        let queen = Piece::new_from(Bug::Queen, Color::White, 0);
+        let explanation = state.explain(queen, Some(Position::new(1, 0)));
+        assert_eq!(explanation.reason(), Some(IllegalMoveReason::Gated));
+        assert_eq!(
+            explanation.steps.last(),
+            Some(&Step {
+                check: Check::Gate {
+                    between: Some((Position::new(1, -1), Position::new(0, 1))),
+                },
+                passed: false,
+            })
+        );
+    }
+
+    #[test]
+    fn tests_explain_spider_steps() {
+        let state = state_with(&[
+            (Bug::Spider, Color::White, 1, Position::new(-1, 0)),
+            (Bug::Queen, Color::White, 0, Position::new(0, 0)),
+            (Bug::Queen, Color::Black, 0, Position::new(1, 0)),
+        ]);
+        let spider = Piece::new_from(Bug::Spider, Color::White, 1);
+        let explanation = state.explain(spider, Some(Position::new(0, -1)));
+        assert_eq!(
+            explanation.steps.last(),
+            Some(&Step {
+                check: Check::SpiderSteps { steps: Some(1) },
+                passed: false,
+            })
+        );
+        let target = explanation.destinations[0];
+        assert!(state.explain(spider, Some(target)).legal());
+    }
+
+    #[test]
+    fn tests_explain_last_moved() {
+        let mut state = state_with(&[
+            (Bug::Queen, Color::White, 0, Position::new(0, 0)),
+            (Bug::Queen, Color::Black, 0, Position::new(1, 0)),
+        ]);
+        let queen = Piece::new_from(Bug::Queen, Color::White, 0);
+        state.board.last_move = Some(LastMove::new_spawn(queen, Position::new(0, 0)));
+        let explanation = state.explain(queen, None);
+        assert_eq!(explanation.reason(), Some(IllegalMoveReason::JustMoved));
+        assert!(explanation.destinations.is_empty());
+    }
+
+    #[test]
+    fn tests_explain_spawn() {
+        let mut state = state_with(&[
+            (Bug::Queen, Color::White, 0, Position::new(0, 0)),
+            (Bug::Queen, Color::Black, 0, Position::new(1, 0)),
+        ]);
+        state.turn_color = Color::Black;
+        let ant = Piece::new_from(Bug::Ant, Color::Black, 1);
+        let explanation = state.explain(ant, Some(Position::new(-1, 0)));
+        assert_eq!(
+            explanation.reason(),
+            Some(IllegalMoveReason::SpawnTouchesOpponent)
+        );
+        assert!(explanation.steps.contains(&Step {
+            check: Check::SpawnPosition {
+                opponent_neighbors: vec![Piece::new_from(Bug::Queen, Color::White, 0)],
+            },
+            passed: false,
+        }));
+        assert_eq!(explanation.destinations.len(), 3);
+        assert!(state.explain(ant, Some(Position::new(2, 0))).legal());
+    }
+}
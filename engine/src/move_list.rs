@@ -0,0 +1,230 @@
+use crate::{
+    board::Board, bug::Bug, color::Color, hex_grid::HexGrid, piece::Piece, position::Position,
+    rule_set::RuleSet,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub piece: Piece,
+    pub from: Position,
+    pub to: Position,
+}
+
+impl Move {
+    pub fn new(piece: Piece, from: Position, to: Position) -> Self {
+        Self { piece, from, to }
+    }
+}
+
+// A reusable buffer for the move generator, once it has grown to the size needed by a
+// position, generating moves does not allocate anymore
+#[derive(Clone, Debug, Default)]
+pub struct MoveList {
+    moves: Vec<Move>,
+    // scratch space for the positions of the piece that is currently generated
+    targets: Vec<Position>,
+    // scratch space for the moves made with the pillbug ability
+    throws: Vec<Move>,
+    // scratch space for the positions an ant has found, it only grows with the hive
+    found: HexGrid<bool>,
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replaces the content of the list with all moves of `color`
+    pub fn generate(&mut self, board: &Board, color: Color, rules: &RuleSet) {
+        self.moves.clear();
+        self.throws.clear();
+        if !board.queen_played(color) {
+            return;
+        }
+        for (offset, position) in board.positions.iter().enumerate() {
+            let Some(position) = *position else {
+                continue;
+            };
+            let Some(piece) = board.top_piece(position) else {
+                continue;
+            };
+            // visit stacks once, for their top piece, and skip pieces that were just moved
+            if board.piece_to_offset(piece) != offset
+                || !piece.is_color(color)
+                || board.is_stunned(piece, position)
+            {
+                continue;
+            }
+            if !board.is_pinned(piece) {
+                self.targets.clear();
+                Bug::push_targets(position, board, &mut self.targets, &mut self.found);
+                self.moves.extend(
+                    self.targets
+                        .iter()
+                        .map(|target| Move::new(piece, position, *target)),
+                );
+            }
+            Bug::push_abilities(position, board, rules, &mut self.throws);
+        }
+        // a throw can end where the piece could have moved by itself, or be possible for both
+        // the pillbug and the mosquito
+        for throw in self.throws.iter() {
+            if !self.moves.contains(throw) {
+                self.moves.push(*throw);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.moves.clear();
+    }
+
+    pub fn contains(&self, mv: &Move) -> bool {
+        self.moves.contains(mv)
+    }
+
+    pub fn as_slice(&self) -> &[Move] {
+        &self.moves
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Move> {
+        self.moves.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.moves.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, state::State};
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+    };
+
+    // Counts the allocations of each thread, so tests running in parallel don't disturb them
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn count_allocation() {
+        let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count_allocation();
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count_allocation();
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn tests_generate_moves() {
+        let history = History::from_filepath("./test_pgns/valid/plm_draw.pgn").unwrap();
+        let mut move_list = MoveList::new();
+        for turn in 0..history.moves.len() {
+            let mut prefix = history.clone();
+            prefix.moves.truncate(turn);
+            let state = State::new_from_history(&prefix).unwrap();
+            let board = &state.board;
+            board.generate_moves(state.turn_color, &state.rules, &mut move_list);
+            let moves = move_list.as_slice();
+            for (i, mv) in moves.iter().enumerate() {
+                assert!(!moves[..i].contains(mv), "{mv:?} is generated twice");
+                assert!(board.is_valid_move(
+                    state.turn_color,
+                    mv.piece,
+                    mv.from,
+                    mv.to,
+                    &state.rules
+                ));
+                let mut next = state.clone();
+                assert!(next.play_turn(mv.piece, mv.to).is_ok());
+            }
+            let grouped = board.moves(state.turn_color, &state.rules);
+            assert_eq!(
+                grouped.values().map(Vec::len).sum::<usize>(),
+                move_list.len()
+            );
+        }
+    }
+
+    #[test]
+    fn tests_move_list_reuse() {
+        let state = State::new_from_history(
+            &History::from_filepath("./test_pgns/valid/p_game.pgn").unwrap(),
+        )
+        .unwrap();
+        let mut move_list = MoveList::new();
+        state
+            .board
+            .generate_moves(state.turn_color, &state.rules, &mut move_list);
+        let moves = move_list.as_slice().to_vec();
+        let capacity = move_list.moves.capacity();
+        state
+            .board
+            .generate_moves(state.turn_color, &state.rules, &mut move_list);
+        // the second run neither grows the buffer nor keeps the old moves
+        assert_eq!(move_list.moves.capacity(), capacity);
+        assert_eq!(move_list.as_slice(), moves.as_slice());
+        assert_eq!((&move_list).into_iter().count(), moves.len());
+        move_list.clear();
+        assert!(move_list.is_empty());
+    }
+
+    #[test]
+    fn tests_generate_does_not_allocate() {
+        let history = History::from_filepath("./test_pgns/valid/plm_draw.pgn").unwrap();
+        let mut move_list = MoveList::new();
+        let mut states = Vec::new();
+        for turn in 0..history.moves.len() {
+            let mut prefix = history.clone();
+            prefix.moves.truncate(turn);
+            states.push(State::new_from_history(&prefix).unwrap());
+        }
+        // the first run grows the buffers to the largest position of the game
+        for state in states.iter() {
+            state
+                .board
+                .generate_moves(state.turn_color, &state.rules, &mut move_list);
+        }
+        let before = ALLOCATIONS.with(Cell::get);
+        for state in states.iter() {
+            state
+                .board
+                .generate_moves(state.turn_color, &state.rules, &mut move_list);
+        }
+        assert_eq!(ALLOCATIONS.with(Cell::get), before);
+    }
+}
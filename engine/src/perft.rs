@@ -0,0 +1,99 @@
+use crate::{move_list::MoveList, state::State, turn::Turn};
+use rayon::prelude::*;
+
+// Counts the positions `depth` turns after `state`, finished games have no children
+pub fn perft(state: &State, depth: usize) -> u64 {
+    let mut buffers = vec![(MoveList::new(), Vec::new()); depth];
+    perft_rec(state, depth, &mut buffers)
+}
+
+// Splits the tree at the root and counts the subtrees on the current rayon thread pool, the
+// counts are in the order of `State::legal_turns`
+pub fn perft_divide(state: &State, depth: usize) -> Vec<(Turn, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    state
+        .legal_turns()
+        .into_par_iter()
+        .map(|turn| {
+            let mut child = state.clone();
+            child.apply_turn(turn).expect("Generated turns are legal");
+            (turn, perft(&child, depth - 1))
+        })
+        .collect()
+}
+
+pub fn perft_parallel(state: &State, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    perft_divide(state, depth)
+        .into_iter()
+        .map(|(_, nodes)| nodes)
+        .sum()
+}
+
+fn perft_rec(state: &State, depth: usize, buffers: &mut [(MoveList, Vec<Turn>)]) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let ((moves, turns), rest) = buffers
+        .split_first_mut()
+        .expect("There is a buffer for every level");
+    state.generate_turns(moves, turns);
+    if depth == 1 {
+        return turns.len() as u64;
+    }
+    turns
+        .iter()
+        .map(|turn| {
+            let mut child = state.clone();
+            child.apply_turn(*turn).expect("Generated turns are legal");
+            perft_rec(&child, depth - 1, rest)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game_type::GameType, history::History};
+
+    fn thread_pool(threads: usize) -> rayon::ThreadPool {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn tests_perft_opening() {
+        // the queen can't open, afterwards only spawns are possible until the queens are out
+        let state = State::new(GameType::Base, true);
+        assert_eq!(perft(&state, 0), 1);
+        assert_eq!(perft(&state, 1), 4);
+        assert_eq!(perft(&state, 2), 96);
+        assert_eq!(perft(&state, 3), 1440);
+        assert_eq!(thread_pool(4).install(|| perft_parallel(&state, 4)), 21600);
+        let state = State::new(GameType::Base, false);
+        assert_eq!(perft(&state, 2), 150);
+    }
+
+    #[test]
+    fn tests_perft_parallel() {
+        let mut history = History::from_filepath("./test_pgns/valid/p_game.pgn").unwrap();
+        history.moves.truncate(14);
+        let state = State::new_from_history(&history).unwrap();
+        let expected = perft(&state, 2);
+        for threads in [1, 4] {
+            let pool = thread_pool(threads);
+            assert_eq!(pool.install(|| perft_parallel(&state, 2)), expected);
+            let divide = pool.install(|| perft_divide(&state, 2));
+            assert_eq!(
+                divide.iter().map(|(turn, _)| *turn).collect::<Vec<_>>(),
+                state.legal_turns()
+            );
+        }
+    }
+}
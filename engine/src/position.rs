@@ -3,12 +3,7 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-use crate::{
-    board::{Board, BOARD_SIZE},
-    direction::Direction,
-    game_error::GameError,
-    piece::Piece,
-};
+use crate::{board::Board, direction::Direction, game_error::GameError, piece::Piece};
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct Position {
@@ -22,10 +17,23 @@ impl fmt::Display for Position {
     }
 }
 
+// One of the 12 symmetries of the hex grid, see `Position::turned`, followed by a shift
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Orientation {
+    pub rotation: u8,
+    pub reflected: bool,
+    pub shift: (i32, i32),
+}
+
+impl Orientation {
+    pub fn apply(&self, position: Position) -> Position {
+        let turned = position.turned(self.rotation, self.reflected);
+        Position::new(turned.q + self.shift.0, turned.r + self.shift.1)
+    }
+}
+
 impl Position {
     pub fn new(q: i32, r: i32) -> Self {
-        let q = q.rem_euclid(BOARD_SIZE);
-        let r = r.rem_euclid(BOARD_SIZE);
         Self { q, r }
     }
 
@@ -33,21 +41,8 @@ impl Position {
         Self { q: 0, r: 0 }
     }
 
-    fn wrap_around(num: i32) -> i32 {
-        if num == (BOARD_SIZE - 1) {
-            return -1;
-        }
-        if num == -(BOARD_SIZE - 1) {
-            return 1;
-        }
-        num
-    }
-
     pub fn is_neighbor(&self, to: Position) -> bool {
-        let diff = (
-            Self::wrap_around(to.q - self.q),
-            Self::wrap_around(to.r - self.r),
-        );
+        let diff = (to.q - self.q, to.r - self.r);
         matches!(
             diff,
             (0, -1) | (0, 1) | (1, -1) | (-1, 1) | (-1, 0) | (1, 0)
@@ -56,10 +51,7 @@ impl Position {
 
     // this implements "odd-r horizontal" which offsets odd rows to the right
     pub fn direction(&self, to: Position) -> Direction {
-        let diff = (
-            Self::wrap_around(to.q - self.q),
-            Self::wrap_around(to.r - self.r),
-        );
+        let diff = (to.q - self.q, to.r - self.r);
         match diff {
             (0, -1) => Direction::NW,
             (0, 1) => Direction::SE,
@@ -105,6 +97,19 @@ impl Position {
         }
     }
 
+    // One of the 12 symmetries of the hex grid around (0, 0): q and r are swapped if
+    // `reflected`, then the position is turned `rotation` times by 60 degrees
+    pub fn turned(&self, rotation: u8, reflected: bool) -> Position {
+        let (mut q, mut r) = (self.q, self.r);
+        if reflected {
+            (q, r) = (r, q);
+        }
+        for _ in 0..rotation {
+            (q, r) = (-r, q + r);
+        }
+        Position::new(q, r)
+    }
+
     pub fn from_string(s: &str, board: &Board) -> Result<Position, GameError> {
         if s.starts_with('.') {
             return Ok(Position::initial_spawn_position());
@@ -0,0 +1,415 @@
+// A position without its history, the fields are separated by ";":
+//
+//   Base+P;9;b;0,0:wP 1,0:bQ -1,0:wQ 1,-1:bA1/wB1;wA1 wA2 ...;bA2 bA3 ...;wB1 from 2,0
+//
+// 1. game type
+// 2. turn, counting from 0 like `State::turn`
+// 3. side to move, w or b
+// 4. stacks as "q,r:pieces", pieces are listed bottom to top and separated by "/"
+// 5. white reserve, or "-" when it's empty
+// 6. black reserve, or "-" when it's empty
+// 7. the piece that moved last, optionally followed by "from q,r" and "by <piece>" when it was
+//    thrown, or "-" when there is none
+// 8. "tournament" if the queen can't be placed on the first turn, or "-" when it can. The field
+//    is optional and defaults to "tournament".
+//
+// Coordinates are the axial ones of `Position` and may be negative.
+use crate::{
+    bug::Bug, color::Color, game_error::GameError, game_result::GameResult,
+    game_status::GameStatus, game_type::GameType, last_move::LastMove, last_turn::LastTurn,
+    piece::Piece, position::Position, rule_set::RuleSet, state::State,
+};
+use std::collections::HashSet;
+
+impl State {
+    pub fn from_position_string(s: &str) -> Result<State, GameError> {
+        let mut fields = s.trim().split(';').map(str::trim).collect::<Vec<_>>();
+        if fields.len() == 7 {
+            fields.push("tournament");
+        }
+        let [game_type, turn, color, stacks, white_reserve, black_reserve, last_move, opening] =
+            fields[..]
+        else {
+            return Err(invalid(format!(
+                "expected 7 or 8 fields separated by ';', found {}",
+                fields.len()
+            )));
+        };
+        let game_type: GameType = game_type.parse()?;
+        let turn: usize = turn.parse().map_err(|_| GameError::ParsingError {
+            found: turn.to_string(),
+            typ: "turn".to_string(),
+        })?;
+        let turn_color: Color = color.parse()?;
+        if turn_color != Color::from((turn % 2) as u8) {
+            return Err(invalid(format!(
+                "{turn_color} can't be to move on turn {turn}"
+            )));
+        }
+        let tournament = match opening {
+            "tournament" => true,
+            "-" => false,
+            any => {
+                return Err(GameError::ParsingError {
+                    found: any.to_string(),
+                    typ: "opening rule".to_string(),
+                })
+            }
+        };
+        let mut state = State::new_from_rules(game_type, RuleSet::new(game_type, tournament));
+        state.turn = turn;
+        state.turn_color = turn_color;
+        let mut seen = HashSet::new();
+        for stack in stacks.split_whitespace() {
+            let (position, pieces) = stack
+                .split_once(':')
+                .ok_or_else(|| invalid(format!("stack {stack:?} has no ':'")))?;
+            let position = parse_position(position)?;
+            if state.board.occupied(position) {
+                return Err(invalid(format!("the stack at {position} is listed twice")));
+            }
+            for (level, piece) in pieces.split('/').enumerate() {
+                let piece: Piece = piece.parse()?;
+                if !state.rules.piece_allowed(piece.bug(), piece.order()) {
+                    return Err(invalid(format!("{piece} is not part of {game_type}")));
+                }
+                if !seen.insert(piece) {
+                    return Err(invalid(format!("{piece} is on the board twice")));
+                }
+                if level > 0 && !matches!(piece.bug(), Bug::Beetle | Bug::Mosquito) {
+                    return Err(invalid(format!("{piece} can't be on top of the hive")));
+                }
+                state.board.insert(position, piece);
+            }
+        }
+        state.board.last_move = None;
+        check_hive(&state)?;
+        check_piece_counts(&state)?;
+        for (color, reserve) in [(Color::White, white_reserve), (Color::Black, black_reserve)] {
+            let expected = reserve_string(&state, color);
+            if reserve != expected {
+                return Err(invalid(format!(
+                    "the {} reserve is {reserve:?} but the board leaves {expected:?}",
+                    color.name()
+                )));
+            }
+        }
+        if last_move != "-" {
+            let last_move = parse_last_move(&state, last_move)?;
+            state.last_turn = match last_move.from {
+                Some(from) => LastTurn::Move(from, last_move.to),
+                None => LastTurn::Spawn(last_move.to),
+            };
+            state.board.last_move = Some(last_move);
+        }
+        state.game_status = match state.board.game_result() {
+            // the engine starts the game once black played its first turn
+            GameResult::Unknown if turn <= 1 => GameStatus::NotStarted,
+            GameResult::Unknown => GameStatus::InProgress,
+            result => GameStatus::Finished(result),
+        };
+        Ok(state)
+    }
+
+    pub fn to_position_string(&self) -> String {
+        let mut positions = self
+            .board
+            .positions
+            .iter()
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        positions.sort_by_key(|position| (position.r, position.q));
+        positions.dedup();
+        let stacks = positions
+            .iter()
+            .map(|position| {
+                let stack = self.board.board.get(*position);
+                let pieces = stack.pieces[..stack.len()]
+                    .iter()
+                    .map(Piece::to_string)
+                    .collect::<Vec<_>>()
+                    .join("/");
+                format!("{}:{pieces}", position_string(*position))
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let last_move = match self.board.last_move {
+            None => "-".to_string(),
+            Some(last_move) => {
+                let mut s = last_move.piece.to_string();
+                if let Some(from) = last_move.from {
+                    s.push_str(&format!(" from {}", position_string(from)));
+                }
+                if let Some(thrower) = last_move.thrown_by {
+                    s.push_str(&format!(" by {thrower}"));
+                }
+                s
+            }
+        };
+        [
+            self.game_type.to_string(),
+            self.turn.to_string(),
+            self.turn_color.to_string(),
+            stacks,
+            reserve_string(self, Color::White),
+            reserve_string(self, Color::Black),
+            last_move,
+            match self.rules.tournament_opening {
+                true => "tournament".to_string(),
+                false => "-".to_string(),
+            },
+        ]
+        .join(";")
+    }
+}
+
+fn invalid(reason: String) -> GameError {
+    GameError::InvalidPosition { reason }
+}
+
+fn position_string(position: Position) -> String {
+    format!("{},{}", position.q, position.r)
+}
+
+fn parse_position(s: &str) -> Result<Position, GameError> {
+    let err = || GameError::ParsingError {
+        found: s.to_string(),
+        typ: "position".to_string(),
+    };
+    let (q, r) = s.split_once(',').ok_or_else(err)?;
+    let q = q.trim().parse::<i32>().map_err(|_| err())?;
+    let r = r.trim().parse::<i32>().map_err(|_| err())?;
+    Ok(Position::new(q, r))
+}
+
+fn parse_last_move(state: &State, s: &str) -> Result<LastMove, GameError> {
+    let mut words = s.split_whitespace();
+    let piece: Piece = words.next().unwrap_or_default().parse()?;
+    let to = state
+        .board
+        .position_of_piece(piece)
+        .ok_or_else(|| invalid(format!("the last moved piece {piece} is not on the board")))?;
+    let mut last_move = LastMove::new_spawn(piece, to);
+    while let Some(word) = words.next() {
+        let value = words
+            .next()
+            .ok_or_else(|| invalid(format!("{word:?} in the last move needs a value")))?;
+        match word {
+            "from" => last_move.from = Some(parse_position(value)?),
+            "by" => last_move.thrown_by = Some(value.parse()?),
+            any => {
+                return Err(GameError::ParsingError {
+                    found: any.to_string(),
+                    typ: "last move".to_string(),
+                })
+            }
+        }
+    }
+    Ok(last_move)
+}
+
+fn reserve_string(state: &State, color: Color) -> String {
+    let reserve = Bug::all()
+        .flat_map(|bug| {
+            let orders = if bug.has_order() {
+                1..=state.rules.bug_count(bug)
+            } else {
+                0..=0
+            };
+            orders.map(move |order| Piece::new_from(bug, color, order))
+        })
+        .filter(|piece| {
+            state.rules.piece_allowed(piece.bug(), piece.order())
+                && !state.board.piece_already_played(*piece)
+        })
+        .map(|piece| piece.to_string())
+        .collect::<Vec<_>>();
+    if reserve.is_empty() {
+        "-".to_string()
+    } else {
+        reserve.join(" ")
+    }
+}
+
+fn check_hive(state: &State) -> Result<(), GameError> {
+    if !state.board.is_connected() {
+        return Err(invalid("the hive is not connected".to_string()));
+    }
+    Ok(())
+}
+
+fn check_piece_counts(state: &State) -> Result<(), GameError> {
+    for color in [Color::White, Color::Black] {
+        let placed = state
+            .board
+            .positions
+            .iter()
+            .enumerate()
+            .filter(|(offset, position)| {
+                position.is_some() && state.board.offset_to_piece(*offset).is_color(color)
+            })
+            .count();
+        // white plays on even turns, black on odd ones
+        let turns_played = (state.turn + 1 - color as usize) / 2;
+        if placed > turns_played {
+            return Err(invalid(format!(
+                "{} placed {placed} pieces in {turns_played} turns",
+                color.name()
+            )));
+        }
+        if !state.board.queen_played(color) && placed >= state.rules.queen_deadline {
+            return Err(invalid(format!(
+                "{} has {placed} pieces on the board but no queen",
+                color.name()
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{corpus::pgn_files, history::History};
+
+    #[test]
+    fn tests_position_string_round_trip() {
+        for path in pgn_files(&["./test_pgns/valid"]).unwrap() {
+            let history = History::from_filepath(&path.display().to_string()).unwrap();
+            let state = State::new_from_history(&history).unwrap();
+            let s = state.to_position_string();
+            let parsed = State::from_position_string(&s).unwrap();
+            assert_eq!(parsed.to_position_string(), s);
+            assert_eq!(parsed.board.position_key(), state.board.position_key());
+            assert_eq!(parsed.board.last_move, state.board.last_move);
+            assert_eq!(parsed.turn_color, state.turn_color);
+            assert_eq!(parsed.game_status, state.game_status);
+            assert_eq!(
+                parsed.rules.tournament_opening,
+                state.rules.tournament_opening
+            );
+            if !state.game_status.is_finished() {
+                assert_eq!(parsed.legal_turns(), state.legal_turns());
+            }
+        }
+    }
+
+    #[test]
+    fn tests_from_position_string() {
+        let state = State::from_position_string(
+            "Base+P;4;w;0,0:wP 1,0:bQ -1,0:wQ 2,0:bA1;\
+             wA1 wA2 wA3 wB1 wB2 wG1 wG2 wG3 wS1 wS2;\
+             bA2 bA3 bB1 bB2 bG1 bG2 bG3 bP bS1 bS2;bA1 from 2,-1",
+        )
+        .unwrap();
+        assert_eq!(state.turn, 4);
+        assert_eq!(state.game_status, GameStatus::InProgress);
+        let ant = Piece::new_from(Bug::Ant, Color::Black, 1);
+        assert_eq!(
+            state.board.last_move,
+            Some(LastMove::new_move(
+                ant,
+                Position::new(2, -1),
+                Position::new(2, 0)
+            ))
+        );
+        // the black ant was just moved, so the pillbug can't throw it
+        assert!(!state
+            .board
+            .moves(Color::White, &state.rules)
+            .contains_key(&(ant, Position::new(2, 0))));
+        let stacked = State::from_position_string(
+            "Base;6;w;0,0:wQ 1,0:bQ/wB1 -1,0:wA1 2,0:bA1;\
+             wA2 wA3 wB2 wG1 wG2 wG3 wS1 wS2;bA2 bA3 bB1 bB2 bG1 bG2 bG3 bS1 bS2;-",
+        )
+        .unwrap();
+        assert_eq!(stacked.board.level(Position::new(1, 0)), 2);
+        assert!(stacked.to_position_string().contains("1,0:bQ/wB1"));
+    }
+
+    #[test]
+    fn tests_first_turns_and_opening_rule() {
+        for (tournament, first) in [(true, "wA1"), (false, "wQ")] {
+            let mut state = State::new(GameType::Base, tournament);
+            state.play_turn_from_notation(first, ".").unwrap();
+            let mut parsed = State::from_position_string(&state.to_position_string()).unwrap();
+            assert_eq!(parsed.game_status, GameStatus::NotStarted);
+            assert_eq!(parsed.rules.tournament_opening, tournament);
+            assert_eq!(parsed.legal_turns(), state.legal_turns());
+            for state in [&mut state, &mut parsed] {
+                state
+                    .play_turn_from_notation("bA1", &format!("{first}-"))
+                    .unwrap();
+                assert_eq!(state.game_status, GameStatus::InProgress);
+            }
+            assert_eq!(parsed.to_position_string(), state.to_position_string());
+        }
+        // without the last field the tournament opening applies
+        let state = State::from_position_string(
+            "Base;0;w;;wA1 wA2 wA3 wB1 wB2 wG1 wG2 wG3 wQ wS1 wS2;\
+             bA1 bA2 bA3 bB1 bB2 bG1 bG2 bG3 bQ bS1 bS2;-",
+        )
+        .unwrap();
+        assert!(state.rules.tournament_opening);
+        assert!(State::from_position_string(&format!(
+            "{};free",
+            state.to_position_string().rsplit_once(';').unwrap().0
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn tests_invalid_position_strings() {
+        let reserves = "wA2 wA3 wB1 wB2 wG1 wG2 wG3 wS1 wS2;bA2 bA3 bB1 bB2 bG1 bG2 bG3 bS1 bS2";
+        for (s, fragment) in [
+            ("Base;4;w;0,0:wQ", "7 or 8 fields"),
+            (
+                &format!("Base;4;b;0,0:wQ 1,0:bQ -1,0:wA1 2,0:bA1;{reserves};-"),
+                "can't be to move",
+            ),
+            (
+                &format!("Base;4;w;0,0:wQ 1,0:bQ -1,0:wA1 3,0:bA1;{reserves};-"),
+                "not connected",
+            ),
+            (
+                &format!("Base;4;w;0,0:wQ 1,0:bQ -1,0:wA1 2,0:bA1/bQ;{reserves};-"),
+                "twice",
+            ),
+            (
+                &format!("Base;4;w;0,0:wQ 1,0:bQ -1,0:wA1 2,0:bA1/bG1;{reserves};-"),
+                "on top of the hive",
+            ),
+            (
+                &format!("Base;4;w;0,0:wQ 1,0:bQ -1,0:wA1 2,0:bA1;wA2;{reserves};-;-"),
+                "fields",
+            ),
+            ("Base;4;w;0,0:wQ 1,0:bQ -1,0:wA1 2,0:bA1;-;-;-", "reserve"),
+            (
+                &format!("Base;2;w;0,0:wQ 1,0:bQ -1,0:wA1 2,0:bA1;{reserves};-"),
+                "turns",
+            ),
+            (
+                &format!("Base+P;4;w;0,0:wQ 1,0:bQ -1,0:wA1 2,0:bA1;{reserves};-"),
+                "reserve",
+            ),
+            (
+                &format!("Base;4;w;0,0:wQ 1,0:bQ -1,0:wA1 2,0:bA1;{reserves};wS1"),
+                "not on the board",
+            ),
+        ] {
+            match State::from_position_string(s) {
+                Err(GameError::InvalidPosition { reason }) => {
+                    assert!(reason.contains(fragment), "{s}: {reason}")
+                }
+                other => panic!("{s} should be invalid, got {other:?}"),
+            }
+        }
+        let missing_queen = "Base;8;w;0,0:wA1 1,0:bQ -1,0:wA2 2,0:bA1 -2,0:wA3 -3,0:wG1;\
+             wB1 wB2 wG2 wG3 wQ wS1 wS2;bA2 bA3 bB1 bB2 bG1 bG2 bG3 bS1 bS2;-";
+        assert!(matches!(
+            State::from_position_string(missing_queen),
+            Err(GameError::InvalidPosition { .. })
+        ));
+    }
+}
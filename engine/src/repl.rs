@@ -0,0 +1,334 @@
+// An interactive session on one game, every line is a command:
+//
+//   play wA1 -bQ    plays a turn in history notation, `play pass` passes
+//   undo            goes back one turn, the undone turns stay available to `goto`
+//   goto 17         replays the first 17 turns of the game
+//   moves wA1       legal destinations of a piece for the side to move
+//   spawns          positions the side to move can place pieces on
+//   reserve w       pieces a color hasn't placed yet
+//   board           the board as a diagram
+//   pinned          pieces that can't move without splitting the hive
+//   load file.pgn   replaces the game with the one in the file
+//   eval 3          searches the position, the depth defaults to 2
+//   check           runs the consistency checks of the board and the state
+use crate::{
+    bug::Bug, color::Color, game_error::GameError, game_status::GameStatus, game_type::GameType,
+    history::History, piece::Piece, search, search::TranspositionTable, state::State, turn::Turn,
+};
+use std::path::Path;
+
+pub const HELP: &str = "commands: play <piece> <position> | play pass | undo | goto <turn> | \
+moves <piece> | spawns | reserve <w|b> | board | pinned | load <file> | eval [depth] | check | \
+help | quit";
+
+pub struct Repl {
+    pub state: State,
+    game_type: GameType,
+    // the turns played or loaded, the state is at any point of them
+    turns: Vec<(String, String)>,
+    table: TranspositionTable,
+}
+
+fn usage(command: &str, arguments: &[&str]) -> GameError {
+    GameError::ParsingError {
+        found: format!("{command} {}", arguments.join(" "))
+            .trim()
+            .to_string(),
+        typ: "command, try help".to_string(),
+    }
+}
+
+impl Repl {
+    pub fn new(game_type: GameType) -> Self {
+        let mut history = History::new();
+        history.game_type = game_type;
+        Repl {
+            state: Self::replay(&history).expect("An empty game replays"),
+            game_type,
+            turns: Vec::new(),
+            table: TranspositionTable::new(),
+        }
+    }
+
+    fn replay(history: &History) -> Result<State, GameError> {
+        let mut state = State::new_from_history(history)?;
+        state.history.game_type = history.game_type;
+        Ok(state)
+    }
+
+    // The number of turns the state is at
+    pub fn ply(&self) -> usize {
+        self.state.history.played_moves().count()
+    }
+
+    pub fn execute(&mut self, line: &str) -> Result<String, GameError> {
+        // the rest of the line is the path, which may contain spaces
+        if let Some(("load", file)) = line.trim().split_once(char::is_whitespace) {
+            return self.load(Path::new(file.trim()));
+        }
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(String::new());
+        };
+        let arguments = words.collect::<Vec<_>>();
+        match (command, &arguments[..]) {
+            ("play", ["pass"]) => self.play("pass", ""),
+            ("play", [piece, position]) => self.play(piece, position),
+            ("play", [piece]) => self.play(piece, "."),
+            ("undo", []) => match self.ply() {
+                0 => Ok("nothing to undo".to_string()),
+                ply => self.goto(ply - 1),
+            },
+            ("goto", [turn]) => {
+                let turn = turn.parse().map_err(|_| usage(command, &arguments))?;
+                self.goto(turn)
+            }
+            ("moves", [piece]) => self.moves(piece.parse()?),
+            ("spawns", []) => Ok(self.spawns()),
+            ("reserve", [color]) => Ok(self.reserve(color.parse()?)),
+            ("board", []) => Ok(self.state.board.diagram().trim_end().to_string()),
+            ("pinned", []) => Ok(self.pinned()),
+            ("eval", []) => Ok(self.eval(2)),
+            ("eval", [depth]) => {
+                let depth = depth.parse().map_err(|_| usage(command, &arguments))?;
+                Ok(self.eval(depth))
+            }
+            ("check", []) => Ok(self.check()),
+            ("help", _) => Ok(HELP.to_string()),
+            _ => Err(usage(command, &arguments)),
+        }
+    }
+
+    fn status(&self) -> String {
+        match &self.state.game_status {
+            GameStatus::Finished(result) => format!("turn {}, game over: {result}", self.ply()),
+            _ => format!(
+                "turn {}, {} to move",
+                self.ply(),
+                self.state.turn_color.name()
+            ),
+        }
+    }
+
+    fn play(&mut self, piece: &str, position: &str) -> Result<String, GameError> {
+        let mut state = self.state.clone();
+        state.play_turn_from_notation(piece, position)?;
+        self.state = state;
+        self.turns = self.state.history.played_moves().cloned().collect();
+        let description = self.state.describe_last_turn().unwrap_or_default();
+        Ok(format!("{description}\n{}", self.status()))
+    }
+
+    fn goto(&mut self, turn: usize) -> Result<String, GameError> {
+        if turn > self.turns.len() {
+            return Err(GameError::ParsingError {
+                found: turn.to_string(),
+                typ: format!("turn, the game has {} turns", self.turns.len()),
+            });
+        }
+        let mut history = History::new();
+        history.game_type = self.game_type;
+        history.moves = self.turns[..turn].to_vec();
+        self.state = Self::replay(&history)?;
+        let last = self
+            .state
+            .describe_last_turn()
+            .map(|description| format!("{description}\n"))
+            .unwrap_or_default();
+        Ok(format!("{last}{}", self.status()))
+    }
+
+    // Replaces the game with the one in the file
+    pub fn load(&mut self, file: &Path) -> Result<String, GameError> {
+        let history = History::from_filepath(&file.display().to_string())?;
+        self.game_type = history.game_type;
+        self.turns = history.played_moves().cloned().collect();
+        self.goto(self.turns.len())
+    }
+
+    // Every legal turn of the piece, in notation and in words
+    fn moves(&self, piece: Piece) -> Result<String, GameError> {
+        let lines = self
+            .state
+            .legal_turns()
+            .into_iter()
+            .filter(|turn| turn.piece() == Some(piece))
+            .map(|turn| self.turn_line(turn))
+            .collect::<Result<Vec<_>, GameError>>()?;
+        match lines.is_empty() {
+            true => Ok(format!("{piece} has no legal move")),
+            false => Ok(lines.join("\n")),
+        }
+    }
+
+    fn turn_line(&self, turn: Turn) -> Result<String, GameError> {
+        let mut state = self.state.clone();
+        let index = state.history.moves.len();
+        state.apply_turn(turn)?;
+        let (piece, position) = &state.history.moves[index];
+        let description = self.state.describe_turn(turn)?;
+        Ok(format!("{piece} {position}  {description}"))
+    }
+
+    fn spawns(&self) -> String {
+        let board = &self.state.board;
+        let positions = board
+            .spawnable_positions(self.state.turn_color)
+            .map(|position| match board.get_neighbor(position) {
+                Some((neighbor, neighbor_position)) => neighbor_position
+                    .direction(position)
+                    .to_history_string(neighbor.to_string()),
+                None => ".".to_string(),
+            })
+            .collect::<Vec<_>>();
+        match positions.is_empty() {
+            true => "none".to_string(),
+            false => positions.join(" "),
+        }
+    }
+
+    fn reserve(&self, color: Color) -> String {
+        let reserve = self.state.board.reserve(color, &self.state.rules);
+        let pieces = Bug::all()
+            .filter_map(|bug| reserve.get(&bug))
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        match pieces.is_empty() {
+            true => "empty".to_string(),
+            false => pieces.join(" "),
+        }
+    }
+
+    fn pinned(&self) -> String {
+        let board = &self.state.board;
+        let mut pinned = board
+            .positions
+            .iter()
+            .flatten()
+            .filter_map(|position| board.top_piece(*position))
+            .filter(|piece| board.is_pinned(*piece))
+            .map(|piece| piece.to_string())
+            .collect::<Vec<_>>();
+        pinned.sort();
+        pinned.dedup();
+        match pinned.is_empty() {
+            true => "none".to_string(),
+            false => pinned.join(" "),
+        }
+    }
+
+    fn eval(&self, depth: usize) -> String {
+        let result = search::search(&self.state, depth, &self.table);
+        let best = match result.best_turn {
+            Some(turn) => self
+                .turn_line(turn)
+                .unwrap_or_else(|_| format!("best turn {turn}")),
+            None => "no turn".to_string(),
+        };
+        format!(
+            "score {} for {} at depth {} ({} nodes)\n{best}",
+            result.score,
+            search::side_to_move(&self.state).name(),
+            result.depth,
+            result.nodes
+        )
+    }
+
+    fn check(&self) -> String {
+        let verdict = |ok: bool| if ok { "ok" } else { "inconsistent" };
+        format!(
+            "board {}, state {}",
+            verdict(self.state.board.check()),
+            verdict(self.state.check_board())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_play_undo_goto() {
+        let mut repl = Repl::new(GameType::MLP);
+        assert_eq!(
+            repl.execute("play wA1").unwrap(),
+            "White Ant 1 is placed as the first piece\nturn 1, black to move"
+        );
+        repl.execute("play bG1 wA1-").unwrap();
+        repl.execute("play wQ -wA1").unwrap();
+        assert_eq!(repl.ply(), 3);
+        assert!(repl.execute("play bQ .").is_err());
+        assert_eq!(repl.ply(), 3);
+        assert_eq!(
+            repl.execute("undo").unwrap().lines().last(),
+            Some("turn 2, white to move")
+        );
+        repl.execute("goto 0").unwrap();
+        assert_eq!(
+            repl.execute("goto 3").unwrap().lines().last(),
+            Some("turn 3, black to move")
+        );
+        assert!(repl.execute("goto 4").is_err());
+        // playing after going back drops the later turns
+        repl.execute("goto 2").unwrap();
+        repl.execute("play wL -wA1").unwrap();
+        assert!(repl.execute("goto 4").is_err());
+        assert_eq!(repl.state.history.game_type, GameType::MLP);
+        assert_eq!(repl.execute("undo").unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn tests_queries() {
+        let mut repl = Repl::new(GameType::MLP);
+        assert_eq!(repl.execute("spawns").unwrap(), ".");
+        repl.execute("play wA1").unwrap();
+        repl.execute("play bA1 wA1-").unwrap();
+        assert_eq!(repl.execute("spawns").unwrap().split(' ').count(), 3);
+        let moves = repl.execute("moves wA1").unwrap();
+        assert_eq!(moves, "wA1 has no legal move");
+        repl.execute("play wQ -wA1").unwrap();
+        repl.execute("play bQ bA1-").unwrap();
+        let moves = repl.execute("moves wQ").unwrap();
+        assert_eq!(moves.lines().count(), 2);
+        assert!(moves.contains("wQ \\wA1  White Queen moves to the northwest of White Ant 1"));
+        assert!(repl
+            .execute("reserve w")
+            .unwrap()
+            .starts_with("wA2 wA3 wB1"));
+        assert!(!repl.execute("reserve b").unwrap().contains("bQ"));
+        assert_eq!(repl.execute("pinned").unwrap(), "bA1 wA1");
+        assert_eq!(repl.execute("check").unwrap(), "board ok, state ok");
+        assert!(repl.execute("board").unwrap().contains("wQ"));
+        assert!(repl.execute("eval 1").unwrap().starts_with("score "));
+        assert!(repl.execute("fly wQ").is_err());
+        assert!(repl.execute("reserve x").is_err());
+        assert_eq!(repl.execute("  ").unwrap(), "");
+    }
+
+    #[test]
+    fn tests_load() {
+        let mut repl = Repl::new(GameType::Base);
+        let loaded = repl.execute("load ./test_pgns/valid/descend.pgn").unwrap();
+        let history = History::from_filepath("./test_pgns/valid/descend.pgn").unwrap();
+        let turns = history.played_moves().count();
+        assert!(loaded.contains(&format!("turn {turns}")), "{loaded}");
+        assert_eq!(repl.state.history.game_type, history.game_type);
+        repl.execute("goto 5").unwrap();
+        assert_eq!(repl.ply(), 5);
+        assert!(repl.execute("load ./test_pgns/valid/missing.pgn").is_err());
+        assert_eq!(repl.ply(), 5);
+        // the path is the rest of the line, spaces included
+        let dir = std::env::temp_dir().join(format!("repl load {}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("a game.pgn");
+        std::fs::copy("./test_pgns/valid/descend.pgn", &file).unwrap();
+        repl.execute("goto 5").unwrap();
+        let loaded = repl.execute(&format!("load  {} ", file.display()));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(loaded.unwrap().contains(&format!("turn {turns}")));
+        assert_eq!(repl.ply(), turns);
+        assert!(repl.execute("load").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use crate::bug::Bug;
+use crate::game_error::GameError;
+use crate::game_type::GameType;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RuleSet {
+    // the queen has to be on the board after this many turns of a player
+    pub queen_deadline: usize,
+    // the queen can't be played as the first piece
+    pub tournament_opening: bool,
+    // number of pieces of every bug, indexed by `Bug as usize`
+    pub bugs_count: [u8; 8],
+    pub pillbug_ability: bool,
+    // a mosquito next to a pillbug may use the pillbug's ability
+    pub mosquito_ability: bool,
+    // the game is drawn once the same position occurs this often
+    pub repetition_draw_limit: Option<usize>,
+    // players without a legal move or spawn pass automatically
+    pub automatic_pass: bool,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self::base()
+    }
+}
+
+impl RuleSet {
+    pub fn new(game_type: GameType, tournament_opening: bool) -> Self {
+        let mut rules = Self::base();
+        rules.tournament_opening = tournament_opening;
+        for (bug, count) in Bug::bugs_count(game_type) {
+            rules.set_bug_count(bug, count as u8);
+        }
+        rules
+    }
+
+    pub fn base() -> Self {
+        let mut rules = Self {
+            queen_deadline: 4,
+            tournament_opening: false,
+            bugs_count: [0; 8],
+            pillbug_ability: true,
+            mosquito_ability: true,
+            // the official rules don't draw on repetition, it's a house rule
+            repetition_draw_limit: None,
+            automatic_pass: false,
+        };
+        for (bug, count) in Bug::bugs_count(GameType::Base) {
+            rules.set_bug_count(bug, count as u8);
+        }
+        rules
+    }
+
+    pub fn base_mlp() -> Self {
+        Self::new(GameType::MLP, false)
+    }
+
+    pub fn bug_count(&self, bug: Bug) -> usize {
+        self.bugs_count[bug as usize] as usize
+    }
+
+    pub fn set_bug_count(&mut self, bug: Bug, count: u8) {
+        self.bugs_count[bug as usize] = count;
+    }
+
+    pub fn queen_required(&self, turn: usize) -> bool {
+        // turn counts the moves of both players, so every player gets every other turn
+        turn / 2 + 1 >= self.queen_deadline
+    }
+
+    pub fn piece_allowed(&self, bug: Bug, order: usize) -> bool {
+        if bug.has_order() {
+            order >= 1 && order <= self.bug_count(bug)
+        } else {
+            self.bug_count(bug) == 1
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), GameError> {
+        let reason = if self.bug_count(Bug::Queen) != 1 {
+            "There has to be exactly one queen per player.".to_string()
+        } else if self.queen_deadline == 0 {
+            "The queen deadline has to be at least one turn.".to_string()
+        } else if self.tournament_opening && self.queen_deadline < 2 {
+            "Tournament opening needs a queen deadline of at least two turns.".to_string()
+        } else if matches!(self.repetition_draw_limit, Some(limit) if limit < 2) {
+            "A position needs to repeat at least twice to draw.".to_string()
+        } else if let Some(bug) = (0..8).map(Bug::from).find(|bug| {
+            // pieces without order can only exist once, the others are numbered 1 to 3
+            self.bug_count(*bug) > if bug.has_order() { 3 } else { 1 }
+        }) {
+            format!("Too many pieces of type {}.", bug.name())
+        } else {
+            return Ok(());
+        };
+        Err(GameError::InvalidRuleSet { reason })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_presets() {
+        assert!(RuleSet::base().validate().is_ok());
+        assert!(RuleSet::base_mlp().validate().is_ok());
+        assert_eq!(RuleSet::base().bug_count(Bug::Mosquito), 0);
+        assert_eq!(RuleSet::base_mlp().bug_count(Bug::Mosquito), 1);
+        assert_eq!(RuleSet::base_mlp().bug_count(Bug::Ant), 3);
+        assert_eq!(RuleSet::new(GameType::Base, false), RuleSet::base());
+    }
+
+    #[test]
+    fn tests_queen_required() {
+        let rules = RuleSet::base();
+        assert!(!rules.queen_required(5));
+        assert!(rules.queen_required(6));
+        assert!(rules.queen_required(7));
+        let mut rules = RuleSet::base();
+        rules.queen_deadline = 3;
+        assert!(rules.queen_required(4));
+        assert!(!rules.queen_required(3));
+    }
+
+    #[test]
+    fn tests_piece_allowed() {
+        let mut rules = RuleSet::base();
+        assert!(rules.piece_allowed(Bug::Ant, 3));
+        assert!(!rules.piece_allowed(Bug::Ant, 0));
+        assert!(!rules.piece_allowed(Bug::Spider, 3));
+        assert!(!rules.piece_allowed(Bug::Pillbug, 0));
+        rules.set_bug_count(Bug::Spider, 3);
+        assert!(rules.piece_allowed(Bug::Spider, 3));
+    }
+
+    #[test]
+    fn tests_validate() {
+        let mut rules = RuleSet::base();
+        rules.set_bug_count(Bug::Ant, 4);
+        assert!(rules.validate().is_err());
+        let mut rules = RuleSet::base();
+        rules.set_bug_count(Bug::Queen, 0);
+        assert!(rules.validate().is_err());
+        let mut rules = RuleSet::base();
+        rules.repetition_draw_limit = Some(1);
+        assert!(rules.validate().is_err());
+    }
+}
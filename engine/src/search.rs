@@ -0,0 +1,590 @@
+use crate::{
+    bug::Bug, color::Color, game_result::GameResult, game_status::GameStatus, move_list::MoveList,
+    piece::Piece, state::State, turn::Turn,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+// Scores are from the point of view of the player to move, wins that are found with more depth
+// left are sooner and score higher
+pub const WIN: i32 = 1_000_000;
+const INFINITY: i32 = 2 * WIN;
+const SHARDS: usize = 64;
+// the entries a table keeps by default, about a million
+const ENTRIES: usize = SHARDS << 14;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub depth: usize,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_turn: Option<Turn>,
+}
+
+// A transposition table that can be shared between threads, it's split into shards so threads
+// rarely wait for each other. Every key has one slot, so the table never holds more entries than
+// its capacity. Positions that share a slot replace each other: entries of earlier searches go
+// first, then shallower ones.
+#[derive(Debug)]
+pub struct TranspositionTable {
+    shards: Vec<Mutex<HashMap<usize, Slot>>>,
+    slots: usize,
+    // counts the searches, the age of an entry is the search that stored it
+    age: AtomicU32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Slot {
+    key: u64,
+    entry: Entry,
+    age: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Weights {
+    // per piece around the opponent's queen minus the pieces around the own queen
+    pub queen_pressure: i32,
+    // per own piece that isn't pinned minus the opponent's
+    pub free_pieces: i32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub weights: Weights,
+    // the search gives up once this is reached
+    pub deadline: Option<Instant>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_turn: Option<Turn>,
+    pub score: i32,
+    pub depth: usize,
+    // searched positions, with several threads this changes from run to run
+    pub nodes: u64,
+}
+
+struct Context<'a> {
+    table: &'a TranspositionTable,
+    weights: Weights,
+    deadline: Option<Instant>,
+    stopped: AtomicBool,
+    nodes: AtomicU64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            queen_pressure: 100,
+            free_pieces: 10,
+        }
+    }
+}
+
+impl<'a> Context<'a> {
+    fn new(table: &'a TranspositionTable, options: &SearchOptions) -> Self {
+        table.age.fetch_add(1, Ordering::Relaxed);
+        Self {
+            table,
+            weights: options.weights,
+            deadline: options.deadline,
+            stopped: AtomicBool::new(false),
+            nodes: AtomicU64::new(0),
+        }
+    }
+
+    fn out_of_time(&self) -> bool {
+        if self.stopped.load(Ordering::Relaxed) {
+            return true;
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.stopped.store(true, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    fn result(self, depth: usize, (best_turn, score): (Option<Turn>, i32)) -> Option<SearchResult> {
+        if self.stopped.into_inner() {
+            return None;
+        }
+        Some(SearchResult {
+            best_turn,
+            score,
+            depth,
+            nodes: self.nodes.into_inner(),
+        })
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TranspositionTable {
+    pub fn new() -> Self {
+        Self::with_capacity(ENTRIES)
+    }
+
+    // A table that keeps at most `entries` entries, at least one per shard
+    pub fn with_capacity(entries: usize) -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            slots: (entries / SHARDS).max(1),
+            age: AtomicU32::new(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots * SHARDS
+    }
+
+    // The turn is part of the key because the queen deadline depends on it, the last move
+    // because the piece that just moved can't move again. Repetition draws are not part of it,
+    // so searching with a repetition_draw_limit is not exact.
+    pub fn key(state: &State) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        state.board.position_key().hash(&mut hasher);
+        state.turn.hash(&mut hasher);
+        state.board.last_move.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        (key / SHARDS as u64 % self.slots as u64) as usize
+    }
+
+    pub fn get(&self, key: u64) -> Option<Entry> {
+        self.shard(key)
+            .get(&self.slot(key))
+            .filter(|slot| slot.key == key)
+            .map(|slot| slot.entry)
+    }
+
+    // Deeper entries of a position are kept over shallower ones, another position only replaces
+    // an entry of the same search that is at most as deep
+    pub fn insert(&self, key: u64, entry: Entry) {
+        let age = self.age.load(Ordering::Relaxed);
+        let slot = self.slot(key);
+        let mut shard = self.shard(key);
+        let replace = match shard.get(&slot) {
+            None => true,
+            Some(old) if old.key == key => old.entry.depth <= entry.depth,
+            Some(old) => old.age != age || old.entry.depth <= entry.depth,
+        };
+        if replace {
+            shard.insert(slot, Slot { key, entry, age });
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().expect("Shard is not poisoned").len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.lock().expect("Shard is not poisoned").clear();
+        }
+    }
+
+    fn shard(&self, key: u64) -> std::sync::MutexGuard<'_, HashMap<usize, Slot>> {
+        self.shards[key as usize % SHARDS]
+            .lock()
+            .expect("Shard is not poisoned")
+    }
+}
+
+// Alpha-beta search on the current thread
+pub fn search(state: &State, depth: usize, table: &TranspositionTable) -> SearchResult {
+    search_with(state, depth, table, &SearchOptions::default())
+        .expect("A search without a deadline finishes")
+}
+
+// Like `search`, but None if the deadline passed before the search finished. A table must only
+// be shared between searches with the same weights.
+pub fn search_with(
+    state: &State,
+    depth: usize,
+    table: &TranspositionTable,
+    options: &SearchOptions,
+) -> Option<SearchResult> {
+    let context = Context::new(table, options);
+    let mut buffers = vec![(MoveList::new(), Vec::new()); depth];
+    let best = negamax(state, depth, -INFINITY, INFINITY, &context, &mut buffers);
+    context.result(depth, best)
+}
+
+// Searches one turn deeper at a time until `max_depth` or the deadline is reached and returns
+// the deepest finished search, the table makes the earlier searches order the later ones
+pub fn iterative_deepening(
+    state: &State,
+    max_depth: usize,
+    table: &TranspositionTable,
+    options: &SearchOptions,
+) -> Option<SearchResult> {
+    let mut result = None;
+    for depth in 1..=max_depth {
+        match search_with(state, depth, table, options) {
+            Some(finished) => result = Some(finished),
+            None => break,
+        }
+    }
+    result
+}
+
+// Splits the search at the root, every root turn is searched with the full window on the
+// current rayon thread pool and all searches share the table. Entries are only used at their
+// exact depth, so the score and the chosen turn (the first of the best turns) don't depend on
+// the order the threads fill the table in and match `search`.
+pub fn search_parallel(state: &State, depth: usize, table: &TranspositionTable) -> SearchResult {
+    if depth == 0 || state.game_status.is_finished() {
+        return search(state, depth, table);
+    }
+    let context = Context::new(table, &SearchOptions::default());
+    context.nodes.fetch_add(1, Ordering::Relaxed);
+    let scores = state
+        .legal_turns()
+        .into_par_iter()
+        .map(|turn| {
+            let mut child = state.clone();
+            child.apply_turn(turn).expect("Generated turns are legal");
+            let mut buffers = vec![(MoveList::new(), Vec::new()); depth - 1];
+            let (_, score) = negamax(
+                &child,
+                depth - 1,
+                -INFINITY,
+                INFINITY,
+                &context,
+                &mut buffers,
+            );
+            if side_to_move(&child) == state.turn_color {
+                (turn, score)
+            } else {
+                (turn, -score)
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut best = (None, -INFINITY);
+    for (turn, score) in scores {
+        if score > best.1 {
+            best = (Some(turn), score);
+        }
+    }
+    table.insert(
+        TranspositionTable::key(state),
+        Entry {
+            depth,
+            score: best.1,
+            bound: Bound::Exact,
+            best_turn: best.0,
+        },
+    );
+    context
+        .result(depth, best)
+        .expect("A search without a deadline finishes")
+}
+
+// The player a position is scored for. Finished games keep the color of the player that made
+// the last turn and automatic passes let a player move twice, so this isn't simply alternating.
+pub fn side_to_move(state: &State) -> Color {
+    if state.game_status.is_finished() {
+        Color::from(state.turn_color.opposite())
+    } else {
+        state.turn_color
+    }
+}
+
+// Static evaluation for the side to move
+pub fn evaluate(state: &State, depth: usize) -> i32 {
+    evaluate_with(state, depth, &Weights::default())
+}
+
+pub fn evaluate_with(state: &State, depth: usize, weights: &Weights) -> i32 {
+    let color = side_to_move(state);
+    match &state.game_status {
+        GameStatus::Finished(GameResult::Winner(winner)) if *winner == color => WIN + depth as i32,
+        GameStatus::Finished(GameResult::Winner(_)) => -WIN - depth as i32,
+        GameStatus::Finished(_) => 0,
+        _ => {
+            let opponent = Color::from(color.opposite());
+            // surrounding the opponent's queen matters most, free pieces help to get there
+            (queen_pressure(state, opponent) - queen_pressure(state, color))
+                * weights.queen_pressure
+                + (free_pieces(state, color) - free_pieces(state, opponent)) * weights.free_pieces
+        }
+    }
+}
+
+fn queen_pressure(state: &State, color: Color) -> i32 {
+    state
+        .board
+        .position_of_piece(Piece::new_from(Bug::Queen, color, 0))
+        .map_or(0, |queen| {
+            state.board.positions_taken_around(queen).count() as i32
+        })
+}
+
+fn free_pieces(state: &State, color: Color) -> i32 {
+    let board = &state.board;
+    board
+        .positions
+        .iter()
+        .enumerate()
+        .filter_map(|(offset, position)| Some((offset, (*position)?)))
+        .filter_map(|(offset, position)| {
+            board
+                .top_piece(position)
+                .filter(|piece| board.piece_to_offset(*piece) == offset)
+        })
+        .filter(|piece| piece.is_color(color) && !board.is_pinned(*piece))
+        .count() as i32
+}
+
+fn negamax(
+    state: &State,
+    depth: usize,
+    mut alpha: i32,
+    beta: i32,
+    context: &Context,
+    buffers: &mut [(MoveList, Vec<Turn>)],
+) -> (Option<Turn>, i32) {
+    context.nodes.fetch_add(1, Ordering::Relaxed);
+    if context.out_of_time() {
+        return (None, 0);
+    }
+    if depth == 0 || state.game_status.is_finished() {
+        return (None, evaluate_with(state, depth, &context.weights));
+    }
+    let key = TranspositionTable::key(state);
+    let entry = context.table.get(key);
+    if let Some(entry) = entry.filter(|entry| entry.depth == depth) {
+        match entry.bound {
+            Bound::Exact => return (entry.best_turn, entry.score),
+            Bound::Lower if entry.score >= beta => return (entry.best_turn, entry.score),
+            Bound::Upper if entry.score <= alpha => return (entry.best_turn, entry.score),
+            _ => {}
+        }
+    }
+    let ((moves, turns), rest) = buffers
+        .split_first_mut()
+        .expect("There is a buffer for every level");
+    state.generate_turns(moves, turns);
+    // the best turn of an earlier search is likely good again
+    if let Some(best_turn) = entry.and_then(|entry| entry.best_turn) {
+        if let Some(i) = turns.iter().position(|turn| *turn == best_turn) {
+            turns[..=i].rotate_right(1);
+        }
+    }
+    let original_alpha = alpha;
+    let mut best = (None, -INFINITY);
+    for turn in turns.iter() {
+        let mut child = state.clone();
+        child.apply_turn(*turn).expect("Generated turns are legal");
+        let score = if side_to_move(&child) == state.turn_color {
+            negamax(&child, depth - 1, alpha, beta, context, rest).1
+        } else {
+            -negamax(&child, depth - 1, -beta, -alpha, context, rest).1
+        };
+        if score > best.1 {
+            best = (Some(*turn), score);
+        }
+        alpha = alpha.max(score);
+        if alpha >= beta {
+            break;
+        }
+    }
+    // the scores of an interrupted search are meaningless
+    if context.out_of_time() {
+        return (None, 0);
+    }
+    let bound = if best.1 <= original_alpha {
+        Bound::Upper
+    } else if best.1 >= beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    };
+    context.table.insert(
+        key,
+        Entry {
+            depth,
+            score: best.1,
+            bound,
+            best_turn: best.0,
+        },
+    );
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game_type::GameType, history::History};
+
+    fn state_from(file: &str, turns: usize) -> State {
+        let mut history = History::from_filepath(file).unwrap();
+        history.moves.truncate(turns);
+        State::new_from_history(&history).unwrap()
+    }
+
+    fn thread_pool(threads: usize) -> rayon::ThreadPool {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn tests_search_finds_win() {
+        let history = History::from_filepath("./test_pgns/valid/pass2.pgn").unwrap();
+        let state = state_from("./test_pgns/valid/pass2.pgn", history.moves.len() - 1);
+        let result = search(&state, 1, &TranspositionTable::new());
+        assert!(result.score >= WIN);
+        let mut next = state.clone();
+        next.apply_turn(result.best_turn.unwrap()).unwrap();
+        assert_eq!(
+            next.game_status,
+            GameStatus::Finished(GameResult::Winner(state.turn_color))
+        );
+        let result =
+            thread_pool(4).install(|| search_parallel(&state, 1, &TranspositionTable::new()));
+        assert!(result.score >= WIN);
+    }
+
+    #[test]
+    fn tests_search_parallel_deterministic() {
+        let state = state_from("./test_pgns/valid/p_game.pgn", 12);
+        let expected = search(&state, 2, &TranspositionTable::new());
+        assert!(expected.best_turn.is_some());
+        for threads in [1, 4] {
+            let pool = thread_pool(threads);
+            // a shared table that is already filled doesn't change the result either
+            let table = TranspositionTable::new();
+            for _ in 0..2 {
+                let result = pool.install(|| search_parallel(&state, 2, &table));
+                assert_eq!(result.best_turn, expected.best_turn);
+                assert_eq!(result.score, expected.score);
+            }
+            assert!(!table.is_empty());
+        }
+    }
+
+    #[test]
+    fn tests_search_deadline() {
+        let state = state_from("./test_pgns/valid/p_game.pgn", 12);
+        let table = TranspositionTable::new();
+        let options = SearchOptions {
+            deadline: Some(Instant::now()),
+            ..SearchOptions::default()
+        };
+        assert!(search_with(&state, 3, &table, &options).is_none());
+        assert!(iterative_deepening(&state, 3, &table, &options).is_none());
+        // nothing from the interrupted searches ends up in the table
+        assert!(table.is_empty());
+        let result = iterative_deepening(&state, 2, &table, &SearchOptions::default()).unwrap();
+        assert_eq!(result.depth, 2);
+        assert_eq!(
+            result.score,
+            search(&state, 2, &TranspositionTable::new()).score
+        );
+    }
+
+    #[test]
+    fn tests_weights() {
+        let state = state_from("./test_pgns/valid/p_game.pgn", 30);
+        let weights = Weights {
+            queen_pressure: 0,
+            free_pieces: 0,
+        };
+        assert_eq!(evaluate_with(&state, 0, &weights), 0);
+        let queen_only = Weights {
+            free_pieces: 0,
+            ..Weights::default()
+        };
+        let free_only = Weights {
+            queen_pressure: 0,
+            ..Weights::default()
+        };
+        assert_eq!(
+            evaluate_with(&state, 0, &queen_only) + evaluate_with(&state, 0, &free_only),
+            evaluate(&state, 0)
+        );
+    }
+
+    #[test]
+    fn tests_transposition_table() {
+        let table = TranspositionTable::new();
+        let entry = Entry {
+            depth: 2,
+            score: 10,
+            bound: Bound::Exact,
+            best_turn: Some(Turn::Pass),
+        };
+        table.insert(1, entry);
+        table.insert(1, Entry { depth: 1, ..entry });
+        assert_eq!(table.get(1), Some(entry));
+        table.insert(1, Entry { depth: 3, ..entry });
+        assert_eq!(table.get(1).map(|entry| entry.depth), Some(3));
+        assert_eq!(table.len(), 1);
+        table.clear();
+        assert!(table.get(1).is_none());
+    }
+
+    #[test]
+    fn tests_transposition_table_capacity() {
+        let table = TranspositionTable::with_capacity(SHARDS);
+        assert_eq!(table.capacity(), SHARDS);
+        let entry = Entry {
+            depth: 3,
+            score: 10,
+            bound: Bound::Exact,
+            best_turn: None,
+        };
+        // keys of the same shard share its only slot
+        let other = SHARDS as u64;
+        table.insert(0, entry);
+        table.insert(other, Entry { depth: 1, ..entry });
+        assert_eq!(table.get(0), Some(entry));
+        assert!(table.get(other).is_none());
+        table.insert(other, entry);
+        assert_eq!(table.get(other), Some(entry));
+        assert!(table.get(0).is_none());
+        // a later search replaces deeper entries of an earlier one
+        let state = State::new(GameType::Base, true);
+        search(&state, 1, &table);
+        table.insert(0, Entry { depth: 1, ..entry });
+        assert_eq!(table.get(0).map(|entry| entry.depth), Some(1));
+        for key in 0..10_000 {
+            table.insert(key, entry);
+        }
+        assert!(table.len() <= table.capacity());
+    }
+}
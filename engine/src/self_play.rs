@@ -0,0 +1,462 @@
+use crate::{
+    color::Color,
+    game_error::GameError,
+    game_result::GameResult,
+    game_status::GameStatus,
+    game_type::GameType,
+    history::History,
+    search::{iterative_deepening, SearchOptions, TranspositionTable, Weights},
+    state::State,
+    turn::Turn,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+// An engine configuration, written as `random[:seed=N]` or
+// `search[:depth=D,queen=Q,free=F]`
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Engine {
+    Random { seed: u64 },
+    Search { depth: usize, weights: Weights },
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Termination {
+    // the board decided the game
+    Normal,
+    // the side to move didn't finish a search within the time limit and lost
+    TimeForfeit,
+    // the game reached the turn limit and was adjudicated a draw
+    TurnLimit,
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchConfig {
+    pub first: Engine,
+    pub second: Engine,
+    pub games: usize,
+    pub time_per_move: Duration,
+    pub max_turns: usize,
+    pub game_type: GameType,
+    // every opening is played twice, once with each engine as white
+    pub openings: Vec<History>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct GameRecord {
+    pub round: usize,
+    pub opening: String,
+    // the color the first engine played
+    pub first_color: Color,
+    pub result: GameResult,
+    pub termination: Termination,
+    pub turns: usize,
+    #[serde(skip)]
+    pub history: History,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct EloEstimate {
+    pub difference: f64,
+    // half the width of the 95% confidence interval, None if the interval is unbounded
+    pub error: Option<f64>,
+}
+
+// Wins, draws and losses are counted from the first engine's point of view
+#[derive(Serialize, Clone, Debug)]
+pub struct MatchReport {
+    pub first: String,
+    pub second: String,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub score: f64,
+    pub elo: Option<EloEstimate>,
+    pub games: Vec<GameRecord>,
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Engine::Random { seed } => write!(f, "random:seed={seed}"),
+            Engine::Search { depth, weights } => write!(
+                f,
+                "search:depth={depth},queen={},free={}",
+                weights.queen_pressure, weights.free_pieces
+            ),
+        }
+    }
+}
+
+impl FromStr for Engine {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || GameError::ParsingError {
+            found: s.to_string(),
+            typ: "engine".to_string(),
+        };
+        let (name, options) = s.split_once(':').unwrap_or((s, ""));
+        let mut engine = match name {
+            "random" => Engine::Random { seed: 0 },
+            "search" => Engine::Search {
+                depth: 2,
+                weights: Weights::default(),
+            },
+            _ => return Err(err()),
+        };
+        for option in options.split(',').filter(|option| !option.is_empty()) {
+            let (key, value) = option.split_once('=').ok_or_else(err)?;
+            match (&mut engine, key) {
+                (Engine::Random { seed }, "seed") => *seed = value.parse().map_err(|_| err())?,
+                (Engine::Search { depth, .. }, "depth") => {
+                    *depth = value.parse().map_err(|_| err())?;
+                    if *depth == 0 {
+                        return Err(err());
+                    }
+                }
+                (Engine::Search { weights, .. }, "queen") => {
+                    weights.queen_pressure = value.parse().map_err(|_| err())?
+                }
+                (Engine::Search { weights, .. }, "free") => {
+                    weights.free_pieces = value.parse().map_err(|_| err())?
+                }
+                _ => return Err(err()),
+            }
+        }
+        Ok(engine)
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let termination = match self {
+            Termination::Normal => "normal",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::TurnLimit => "adjudication",
+        };
+        write!(f, "{termination}")
+    }
+}
+
+impl Engine {
+    // None if the engine ran out of time
+    pub fn choose(
+        &self,
+        state: &State,
+        deadline: Instant,
+        table: &TranspositionTable,
+        rng: &mut StdRng,
+    ) -> Option<Turn> {
+        match self {
+            Engine::Random { .. } => state.legal_turns().choose(rng).copied(),
+            Engine::Search { depth, weights } => {
+                let options = SearchOptions {
+                    weights: *weights,
+                    deadline: Some(deadline),
+                };
+                iterative_deepening(state, *depth, table, &options)
+                    .and_then(|result| result.best_turn)
+            }
+        }
+    }
+
+    fn rng(&self, round: usize) -> StdRng {
+        let seed = match self {
+            Engine::Random { seed } => *seed,
+            Engine::Search { .. } => 0,
+        };
+        StdRng::seed_from_u64(seed.wrapping_add(round as u64))
+    }
+}
+
+impl GameRecord {
+    // 1 for a win of the first engine, 0.5 for a draw and 0 for a loss or an unfinished game
+    pub fn score(&self) -> f64 {
+        match self.result {
+            GameResult::Winner(color) if color == self.first_color => 1.0,
+            GameResult::Draw => 0.5,
+            _ => 0.0,
+        }
+    }
+}
+
+impl EloEstimate {
+    // None if one side scored everything, the difference is unbounded then
+    pub fn new(scores: &[f64]) -> Option<Self> {
+        let games = scores.len() as f64;
+        let score = scores.iter().sum::<f64>() / games;
+        if !(score > 0.0 && score < 1.0) {
+            return None;
+        }
+        let variance = scores.iter().map(|s| s * s).sum::<f64>() / games - score * score;
+        let margin = 1.96 * (variance / games).sqrt();
+        let (low, high) = (score - margin, score + margin);
+        let error =
+            (low > 0.0 && high < 1.0).then(|| (elo_difference(high) - elo_difference(low)) / 2.0);
+        Some(Self {
+            difference: elo_difference(score),
+            error,
+        })
+    }
+}
+
+// The rating difference at which the expected score is `score`
+pub fn elo_difference(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+// Every pair of first turns from the start position. Spawns around a single piece are the same
+// up to rotation, so only one position per black bug is kept.
+pub fn default_openings(game_type: GameType) -> Vec<History> {
+    let mut openings = Vec::new();
+    let mut seen = HashSet::new();
+    let start = State::new(game_type, true);
+    for white in start.legal_turns() {
+        let mut state = start.clone();
+        state.apply_turn(white).expect("Generated turns are legal");
+        for black in state.legal_turns() {
+            if !seen.insert((white.piece(), black.piece())) {
+                continue;
+            }
+            let mut opening = state.clone();
+            opening
+                .apply_turn(black)
+                .expect("Generated turns are legal");
+            opening.history.game_type = game_type;
+            openings.push(opening.history);
+        }
+    }
+    openings
+}
+
+// One opening per line with moves separated by ';', e.g. `wA1 .;bG1 wA1-`
+pub fn parse_openings(openings: &str, game_type: GameType) -> Result<Vec<History>, GameError> {
+    openings
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut history = History::new_from_str(line.to_string())?;
+            history.game_type = game_type;
+            State::new_from_history(&history)?;
+            Ok(history)
+        })
+        .collect()
+}
+
+fn opening_string(opening: &History) -> String {
+    opening
+        .moves
+        .iter()
+        .map(|(piece, pos)| format!("{piece} {pos}").trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+// Plays one game, `round` decides the opening and which engine plays white
+pub fn play_game(config: &MatchConfig, round: usize) -> Result<GameRecord, GameError> {
+    let opening = &config.openings[(round / 2) % config.openings.len()];
+    // the first engine is white in even rounds
+    let first_color = Color::from((round % 2) as u8);
+    let (white, black) = match first_color {
+        Color::White => (config.first, config.second),
+        Color::Black => (config.second, config.first),
+    };
+    let mut state = State::new_from_history(opening)?;
+    let mut rngs = [white.rng(round), black.rng(round)];
+    // the weights are part of an entry's score, so the engines can't share a table
+    let tables = [TranspositionTable::new(), TranspositionTable::new()];
+    let mut termination = Termination::Normal;
+    while !state.game_status.is_finished() {
+        if state.turn >= config.max_turns {
+            state.finish(GameResult::Draw)?;
+            termination = Termination::TurnLimit;
+            break;
+        }
+        let side = state.turn_color as usize;
+        let engine = if side == 0 { white } else { black };
+        let deadline = Instant::now() + config.time_per_move;
+        match engine.choose(&state, deadline, &tables[side], &mut rngs[side]) {
+            Some(turn) => state.apply_turn(turn)?,
+            None => {
+                let winner = Color::from(state.turn_color.opposite());
+                state.finish(GameResult::Winner(winner))?;
+                termination = Termination::TimeForfeit;
+            }
+        }
+    }
+    let mut history = History::new();
+    history.moves = state.history.played_moves().cloned().collect();
+    history.game_type = config.game_type;
+    if let GameStatus::Finished(result) = &state.game_status {
+        history.result = result.clone();
+    }
+    Ok(GameRecord {
+        round,
+        opening: opening_string(opening),
+        first_color,
+        result: history.result.clone(),
+        termination,
+        turns: state.turn,
+        history,
+    })
+}
+
+// Plays all games on the current rayon thread pool
+pub fn play_match(config: &MatchConfig) -> Result<MatchReport, GameError> {
+    if config.openings.is_empty() {
+        return Err(GameError::ParsingError {
+            found: "no openings".to_string(),
+            typ: "openings".to_string(),
+        });
+    }
+    let games = (0..config.games)
+        .into_par_iter()
+        .map(|round| play_game(config, round))
+        .collect::<Result<Vec<_>, _>>()?;
+    let scores = games.iter().map(GameRecord::score).collect::<Vec<_>>();
+    let wins = scores.iter().filter(|score| **score == 1.0).count();
+    let draws = scores.iter().filter(|score| **score == 0.5).count();
+    Ok(MatchReport {
+        first: config.first.to_string(),
+        second: config.second.to_string(),
+        wins,
+        draws,
+        losses: games.len() - wins - draws,
+        score: if games.is_empty() {
+            0.0
+        } else {
+            scores.iter().sum::<f64>() / games.len() as f64
+        },
+        elo: EloEstimate::new(&scores),
+        games,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(first: &str, second: &str, games: usize) -> MatchConfig {
+        MatchConfig {
+            first: first.parse().unwrap(),
+            second: second.parse().unwrap(),
+            games,
+            time_per_move: Duration::from_secs(60),
+            max_turns: 60,
+            game_type: GameType::Base,
+            openings: default_openings(GameType::Base),
+        }
+    }
+
+    #[test]
+    fn tests_engine_from_str() {
+        assert_eq!(
+            "random".parse::<Engine>().unwrap(),
+            Engine::Random { seed: 0 }
+        );
+        let engine = "search:depth=3,free=0".parse::<Engine>().unwrap();
+        assert_eq!(
+            engine,
+            Engine::Search {
+                depth: 3,
+                weights: Weights {
+                    queen_pressure: 100,
+                    free_pieces: 0
+                }
+            }
+        );
+        assert_eq!(engine.to_string().parse::<Engine>().unwrap(), engine);
+        for invalid in [
+            "minimax",
+            "random:depth=2",
+            "search:depth=0",
+            "search:depth",
+            "search:queen=x",
+        ] {
+            assert!(invalid.parse::<Engine>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn tests_elo() {
+        assert!((elo_difference(0.5)).abs() < 1e-9);
+        assert!((elo_difference(0.75) - 190.85).abs() < 0.01);
+        assert!((elo_difference(0.25) + 190.85).abs() < 0.01);
+        assert_eq!(EloEstimate::new(&[1.0, 1.0]), None);
+        assert_eq!(EloEstimate::new(&[0.0, 0.0]), None);
+        let even = EloEstimate::new(&[1.0, 0.0, 0.5, 0.5]).unwrap();
+        assert!(even.difference.abs() < 1e-9);
+        assert!(even.error.unwrap() > 0.0);
+        // more games, narrower interval
+        let scores = [1.0, 0.0, 0.5, 0.5].repeat(25);
+        assert!(EloEstimate::new(&scores).unwrap().error.unwrap() < even.error.unwrap());
+    }
+
+    #[test]
+    fn tests_openings() {
+        let openings = default_openings(GameType::Base);
+        // 4 bugs for white times 4 for black
+        assert_eq!(openings.len(), 16);
+        assert!(openings.iter().all(|opening| opening.moves.len() == 2));
+        let parsed = parse_openings("# comment\nwA1 .;bG1 wA1-\n\nwS1 .\n", GameType::M).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].game_type, GameType::M);
+        assert_eq!(opening_string(&parsed[0]), "wA1 .;bG1 wA1-");
+        assert!(parse_openings("wM .", GameType::Base).is_err());
+        assert!(parse_openings("wA1 .;wG1 wA1-", GameType::Base).is_err());
+    }
+
+    #[test]
+    fn tests_play_match() {
+        let report = play_match(&config("search:depth=1", "random:seed=7", 4)).unwrap();
+        assert_eq!(report.games.len(), 4);
+        assert_eq!(report.wins + report.draws + report.losses, 4);
+        for (round, game) in report.games.iter().enumerate() {
+            assert_eq!(game.round, round);
+            assert_eq!(game.first_color, Color::from((round % 2) as u8));
+            assert_eq!(game.history.result, game.result);
+            // the saved game replays to the same result
+            let state = State::new_from_history(&game.history).unwrap();
+            match game.termination {
+                Termination::Normal => {
+                    assert_eq!(state.game_status, GameStatus::Finished(game.result.clone()))
+                }
+                _ => assert!(!state.game_status.is_finished()),
+            }
+        }
+        // both colors play the same opening
+        assert_eq!(report.games[0].opening, report.games[1].opening);
+        assert_ne!(report.games[0].opening, report.games[2].opening);
+    }
+
+    #[test]
+    fn tests_time_forfeit() {
+        let mut config = config("search:depth=3", "random", 1);
+        config.time_per_move = Duration::ZERO;
+        let report = play_match(&config).unwrap();
+        assert_eq!(report.losses, 1);
+        assert_eq!(report.games[0].termination, Termination::TimeForfeit);
+        assert_eq!(report.games[0].turns, 2);
+    }
+
+    #[test]
+    fn tests_turn_limit() {
+        let mut config = config("random:seed=1", "random:seed=2", 2);
+        config.max_turns = 4;
+        let report = play_match(&config).unwrap();
+        assert_eq!(report.draws, 2);
+        assert!(report
+            .games
+            .iter()
+            .all(|game| game.termination == Termination::TurnLimit && game.turns == 4));
+    }
+}
@@ -0,0 +1,393 @@
+// A state that shares everything it has in common with the states it was played from, for
+// search trees, variations and looking up earlier positions without copying whole games.
+//
+// Cloning is O(1) and a ply only keeps what its turn added: the history entries, the changes to
+// the board and the fields of the state that change from turn to turn. The board is kept every
+// CHECKPOINT plies, a handle keeps the board of its own ply so playing on it doesn't rebuild
+// anything. Going back to an earlier ply replays the changes since the last checkpoint, at most
+// CHECKPOINT - 1 of them. The history and the positions seen for the repetition draw are the
+// chain of plies itself.
+use crate::{
+    board::Board, board_change::BoardChange, color::Color, game_error::GameError,
+    game_status::GameStatus, history::History, last_move::LastMove, last_turn::LastTurn,
+    move_list::Move, piece::Piece, player::Player, position::Position, state::State, turn::Turn,
+};
+use std::sync::Arc;
+
+const CHECKPOINT: usize = 16;
+
+// The fields of the state that change from turn to turn
+#[derive(Clone, Debug)]
+struct Progress {
+    turn: usize,
+    turn_color: Color,
+    players: (Player, Player),
+    game_status: GameStatus,
+    last_turn: LastTurn,
+}
+
+impl Progress {
+    fn of(state: &State) -> Self {
+        Self {
+            turn: state.turn,
+            turn_color: state.turn_color,
+            players: state.players.clone(),
+            game_status: state.game_status.clone(),
+            last_turn: state.last_turn.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Ply {
+    parent: Option<Arc<Ply>>,
+    // plies since the root
+    number: usize,
+    // None for the root
+    turn: Option<Turn>,
+    // the history entries of the turn, the root has the ones it was created with
+    records: Vec<(String, String)>,
+    changes: Vec<BoardChange>,
+    // the board after the ply, the root always has one
+    checkpoint: Option<Arc<Board>>,
+    // the last move of the board after the ply, passes don't change the board but clear it
+    last_move: Option<LastMove>,
+    progress: Progress,
+    // the position the ply counted towards the repetition draw
+    repetition: Option<u64>,
+}
+
+// Lines of thousands of plies would otherwise be dropped recursively
+impl Drop for Ply {
+    fn drop(&mut self) {
+        let mut parent = self.parent.take();
+        while let Some(ply) = parent {
+            parent = match Arc::try_unwrap(ply) {
+                Ok(mut ply) => ply.parent.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SharedState {
+    ply: Arc<Ply>,
+    // the board after `ply`
+    board: Arc<Board>,
+    // the state the line started from without its board and the moves of its history
+    root: Arc<State>,
+}
+
+fn turn_from_notation(board: &Board, piece: &str, position: &str) -> Result<Turn, GameError> {
+    if piece == "pass" {
+        return Ok(Turn::Pass);
+    }
+    let piece: Piece = piece.parse()?;
+    let to = Position::from_string(position, board)?;
+    Ok(match board.position_of_piece(piece) {
+        Some(from) => Turn::Move(Move::new(piece, from, to)),
+        None => Turn::Spawn(piece, to),
+    })
+}
+
+impl SharedState {
+    pub fn new(mut state: State) -> Self {
+        let board = Arc::new(std::mem::take(&mut state.board));
+        let records = std::mem::take(&mut state.history.moves);
+        Self {
+            ply: Arc::new(Ply {
+                parent: None,
+                number: 0,
+                turn: None,
+                records,
+                changes: Vec::new(),
+                checkpoint: Some(Arc::clone(&board)),
+                last_move: board.last_move,
+                progress: Progress::of(&state),
+                repetition: None,
+            }),
+            board,
+            root: Arc::new(state),
+        }
+    }
+
+    pub fn from_history(history: &History) -> Result<Self, GameError> {
+        // replaying the whole game first detects the opening rule
+        let end = State::new_from_history(history)?;
+        let mut shared = Self::new(State::new(history.game_type, end.rules.tournament_opening));
+        for (piece, position) in history.played_moves() {
+            shared.play_turn_from_notation(piece, position)?;
+        }
+        Ok(shared)
+    }
+
+    // The handle of another ply of the line, its board is rebuilt from the last checkpoint
+    fn of_ply(&self, ply: &Arc<Ply>) -> SharedState {
+        let mut plies = vec![ply.as_ref()];
+        while plies[plies.len() - 1].checkpoint.is_none() {
+            let parent = plies[plies.len() - 1]
+                .parent
+                .as_ref()
+                .expect("The root is a checkpoint");
+            plies.push(parent);
+        }
+        let checkpoint = plies
+            .pop()
+            .and_then(|ply| ply.checkpoint.as_ref())
+            .expect("Plies start at one");
+        let board = if plies.is_empty() {
+            Arc::clone(checkpoint)
+        } else {
+            let mut board = Board::clone(checkpoint);
+            for ply in plies.iter().rev() {
+                board.replay(&ply.changes);
+            }
+            board.last_move = ply.last_move;
+            Arc::new(board)
+        };
+        SharedState {
+            ply: Arc::clone(ply),
+            board,
+            root: Arc::clone(&self.root),
+        }
+    }
+
+    pub fn board(&self) -> Board {
+        Board::clone(&self.board)
+    }
+
+    pub fn history(&self) -> History {
+        let mut plies = vec![self.ply.as_ref()];
+        while let Some(parent) = &plies[plies.len() - 1].parent {
+            plies.push(parent);
+        }
+        let mut history = self.root.history.clone();
+        history.moves = plies
+            .iter()
+            .rev()
+            .flat_map(|ply| ply.records.iter().cloned())
+            .collect();
+        history
+    }
+
+    // The state of the ply without the moves of its history, enough to play on it
+    fn playable(&self) -> State {
+        let mut state = State::clone(&self.root);
+        let progress = self.ply.progress.clone();
+        state.turn = progress.turn;
+        state.turn_color = progress.turn_color;
+        state.players = progress.players;
+        state.game_status = progress.game_status;
+        state.last_turn = progress.last_turn;
+        state.board = self.board();
+        if state.rules.repetition_draw_limit.is_none() {
+            return state;
+        }
+        let mut ply = Some(&self.ply);
+        while let Some(current) = ply {
+            if let Some(key) = current.repetition {
+                *state.repetitions.entry(key).or_default() += 1;
+            }
+            ply = current.parent.as_ref();
+        }
+        state
+    }
+
+    // The whole state, put together from the plies of the line
+    pub fn state(&self) -> State {
+        let mut state = self.playable();
+        state.history = self.history();
+        state
+    }
+
+    // Plies played since the root
+    pub fn ply(&self) -> usize {
+        self.ply.number
+    }
+
+    // The changes the last ply made to the board
+    pub fn changes(&self) -> &[BoardChange] {
+        &self.ply.changes
+    }
+
+    pub fn last_turn(&self) -> Option<Turn> {
+        self.ply.turn
+    }
+
+    // Plays the turn, on an error nothing changes
+    pub fn apply_turn(&mut self, turn: Turn) -> Result<(), GameError> {
+        self.play(turn)
+    }
+
+    pub fn play_turn_from_notation(
+        &mut self,
+        piece: &str,
+        position: &str,
+    ) -> Result<(), GameError> {
+        let turn = turn_from_notation(&self.board, piece, position)?;
+        self.play(turn)
+    }
+
+    // Plays the turn on the board of this ply, the history isn't needed to play
+    fn play(&mut self, turn: Turn) -> Result<(), GameError> {
+        let mut state = self.playable();
+        let repetitions = state.repetitions.clone();
+        state.apply_turn(turn)?;
+        let number = self.ply.number + 1;
+        let repetition = state
+            .repetitions
+            .iter()
+            .find(|(key, seen)| repetitions.get(key) != Some(seen))
+            .map(|(key, _)| *key);
+        let board = Arc::new(std::mem::take(&mut state.board));
+        self.ply = Arc::new(Ply {
+            parent: Some(Arc::clone(&self.ply)),
+            number,
+            turn: Some(turn),
+            records: std::mem::take(&mut state.history.moves),
+            changes: self.board.diff(&board),
+            checkpoint: number
+                .is_multiple_of(CHECKPOINT)
+                .then(|| Arc::clone(&board)),
+            last_move: board.last_move,
+            progress: Progress::of(&state),
+            repetition,
+        });
+        self.board = board;
+        Ok(())
+    }
+
+    // The turns from the root to this ply
+    pub fn turns(&self) -> Vec<Turn> {
+        let mut turns = Vec::with_capacity(self.ply());
+        let mut ply = &self.ply;
+        while let (Some(turn), Some(parent)) = (ply.turn, &ply.parent) {
+            turns.push(turn);
+            ply = parent;
+        }
+        turns.reverse();
+        turns
+    }
+
+    // The state after `ply` plies of this line, None if the line is shorter
+    pub fn at(&self, ply: usize) -> Option<SharedState> {
+        if ply > self.ply() {
+            return None;
+        }
+        let mut target = &self.ply;
+        while target.number > ply {
+            target = target.parent.as_ref().expect("Only the root has no parent");
+        }
+        Some(self.of_ply(target))
+    }
+
+    pub fn parent(&self) -> Option<SharedState> {
+        self.ply.parent.as_ref().map(|ply| self.of_ply(ply))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game_result::GameResult, game_type::GameType, rule_set::RuleSet};
+
+    #[test]
+    fn tests_from_history_and_at() {
+        let history = History::from_filepath("./test_pgns/valid/descend.pgn").unwrap();
+        let shared = SharedState::from_history(&history).unwrap();
+        let state = State::new_from_history(&history).unwrap();
+        assert!(shared.ply() > CHECKPOINT);
+        assert_eq!(shared.ply(), history.played_moves().count());
+        assert_eq!(shared.board(), state.board);
+        assert_eq!(shared.history().moves, state.history.moves);
+        assert_eq!(shared.state().game_status, state.game_status);
+        // every earlier state equals the one the line went through
+        let mut line = State::new(history.game_type, state.rules.tournament_opening);
+        let mut states = vec![line.clone()];
+        for turn in shared.turns() {
+            line.apply_turn(turn).unwrap();
+            states.push(line.clone());
+        }
+        for (ply, expected) in states.iter().enumerate() {
+            let at = shared.at(ply).unwrap();
+            assert_eq!(at.ply(), ply);
+            assert_eq!(&at.state(), expected, "ply {ply}");
+            if ply > 0 {
+                assert_eq!(at.changes(), states[ply - 1].board.diff(&expected.board));
+            }
+        }
+        assert!(shared.at(shared.ply() + 1).is_none());
+        assert_eq!(shared.parent().unwrap().ply(), shared.ply() - 1);
+        assert!(shared.at(0).unwrap().parent().is_none());
+    }
+
+    #[test]
+    fn tests_branching() {
+        let mut root = SharedState::new(State::new(GameType::Base, true));
+        root.play_turn_from_notation("wA1", ".").unwrap();
+        root.play_turn_from_notation("bA1", "wA1-").unwrap();
+        let mut left = root.clone();
+        let mut right = root.clone();
+        assert!(Arc::ptr_eq(&left.ply, &right.ply));
+        left.play_turn_from_notation("wQ", "-wA1").unwrap();
+        right.play_turn_from_notation("wQ", "\\wA1").unwrap();
+        assert_ne!(left.board(), right.board());
+        assert_eq!(root.ply(), 2);
+        // the siblings keep their own turn and nothing of their parent's board or history
+        for child in [&left, &right] {
+            assert!(Arc::ptr_eq(child.ply.parent.as_ref().unwrap(), &root.ply));
+            assert_eq!(child.ply.records.len(), 1);
+            assert!(matches!(child.changes(), [BoardChange::Placed { .. }]));
+            assert!(child.ply.checkpoint.is_none());
+            assert!(child.ply.repetition.is_none());
+            assert_eq!(child.history().moves[..2], root.history().moves[..]);
+        }
+        assert!(left.play_turn_from_notation("bQ", "wQ-").is_err());
+        assert_eq!(left.ply(), 3);
+        assert_eq!(left.turns()[..2], right.turns()[..2]);
+        assert_eq!(left.at(2).unwrap().state(), root.state());
+        assert_eq!(left.last_turn(), Some(left.turns()[2]));
+        assert_eq!(left.parent().unwrap().board(), root.board());
+    }
+
+    #[test]
+    fn tests_repetition_draw() {
+        let mut rules = RuleSet::base();
+        rules.repetition_draw_limit = Some(3);
+        let mut state = State::new_from_rules(GameType::Base, rules);
+        let mut shared = SharedState::new(state.clone());
+        let mut play = |piece: &str, q: i32, r: i32| {
+            let piece: Piece = piece.parse().unwrap();
+            let to = Position::new(q, r);
+            state.play_turn(piece, to).unwrap();
+            let turn = match shared.board().position_of_piece(piece) {
+                Some(from) => Turn::Move(Move::new(piece, from, to)),
+                None => Turn::Spawn(piece, to),
+            };
+            shared.apply_turn(turn).unwrap();
+            assert_eq!(shared.state(), state);
+            shared.clone()
+        };
+        for (piece, q) in [("wQ", 0), ("bQ", 1), ("wA1", -1), ("bA1", 2)] {
+            play(piece, q, 0);
+        }
+        // the ants go back and forth until the position was seen three times
+        let mut plies = Vec::new();
+        for _ in 0..2 {
+            plies.push(play("wA1", 0, 1));
+            plies.push(play("bA1", 2, -1));
+            plies.push(play("wA1", -1, 0));
+            plies.push(play("bA1", 2, 0));
+        }
+        let last = plies.last().unwrap();
+        assert_eq!(
+            last.state().game_status,
+            GameStatus::Finished(GameResult::Draw)
+        );
+        // earlier plies of the line only count the positions seen up to them
+        let earlier = last.at(last.ply() - 4).unwrap();
+        assert_eq!(earlier.state(), plies[3].state());
+        assert_eq!(earlier.state().game_status, GameStatus::InProgress);
+    }
+}
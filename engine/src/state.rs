@@ -4,10 +4,17 @@ use crate::game_error::GameError;
 use crate::game_result::GameResult;
 use crate::game_status::GameStatus;
 use crate::history::History;
+use crate::illegal_move_reason::IllegalMoveReason;
+use crate::last_turn::LastTurn;
+use crate::move_explanation::MoveExplanation;
+use crate::move_list::MoveList;
 use crate::piece::Piece;
 use crate::player::Player;
 use crate::position::Position;
+use crate::rule_set::RuleSet;
+use crate::turn::Turn;
 use crate::{board::Board, game_type::GameType};
+use std::collections::HashMap;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct State {
@@ -19,11 +26,17 @@ pub struct State {
     pub players: (Player, Player),
     pub game_status: GameStatus,
     pub game_type: GameType,
-    pub tournament: bool,
+    pub rules: RuleSet,
+    pub last_turn: LastTurn,
+    pub(crate) repetitions: HashMap<u64, usize>,
 }
 
 impl State {
     pub fn new(game_type: GameType, tournament: bool) -> State {
+        State::new_from_rules(game_type, RuleSet::new(game_type, tournament))
+    }
+
+    pub fn new_from_rules(game_type: GameType, rules: RuleSet) -> State {
         State {
             game_id: 1,
             board: Board::new(),
@@ -33,14 +46,16 @@ impl State {
             players: (Player::new(Color::White), Player::new(Color::Black)),
             game_status: GameStatus::NotStarted,
             game_type,
-            tournament,
+            rules,
+            last_turn: LastTurn::None,
+            repetitions: HashMap::new(),
         }
     }
 
     pub fn new_from_history(history: &History) -> Result<Self, GameError> {
         let mut tournament = true;
         // Did white open with a Queen?
-        if let Some((piece_str, _)) = history.moves.get(0) {
+        if let Some((piece_str, _)) = history.moves.first() {
             let piece: Piece = piece_str.parse()?;
             if piece.bug() == Bug::Queen {
                 tournament = false;
@@ -57,11 +72,93 @@ impl State {
         for (piece, pos) in history.moves.iter() {
             state.play_turn_from_notation(piece, pos)?;
         }
+        state.history.annotations = history.annotations.clone();
         Ok(state)
     }
 
     pub fn queen_allowed(&self) -> bool {
-        self.turn > 1 || !self.tournament
+        self.turn > 1 || !self.rules.tournament_opening
+    }
+
+    pub fn explain(&self, piece: Piece, target_position: Option<Position>) -> MoveExplanation {
+        MoveExplanation::new(self, piece, target_position)
+    }
+
+    pub fn has_legal_action(&self) -> bool {
+        let reserve = self.board.reserve(self.turn_color, &self.rules);
+        let spawn_possible = !reserve.is_empty()
+            && self
+                .board
+                .spawnable_positions(self.turn_color)
+                .next()
+                .is_some()
+            && reserve.keys().any(|bug| match bug {
+                Bug::Queen => self.queen_allowed(),
+                _ => !self
+                    .board
+                    .queen_required(self.turn, self.turn_color, &self.rules),
+            });
+        spawn_possible || !self.board.moves(self.turn_color, &self.rules).is_empty()
+    }
+
+    // Writes all legal turns of the player to move into `turns`, `moves` is scratch space that
+    // can be reused between calls
+    pub fn generate_turns(&self, moves: &mut MoveList, turns: &mut Vec<Turn>) {
+        turns.clear();
+        if self.game_status.is_finished() {
+            return;
+        }
+        self.board
+            .generate_moves(self.turn_color, &self.rules, moves);
+        turns.extend(moves.iter().map(|mv| Turn::Move(*mv)));
+        for piece in self.spawnable_pieces() {
+            turns.extend(
+                self.board
+                    .spawnable_positions(self.turn_color)
+                    .map(|position| Turn::Spawn(piece, position)),
+            );
+        }
+        if turns.is_empty() {
+            turns.push(Turn::Pass);
+        }
+    }
+
+    pub fn legal_turns(&self) -> Vec<Turn> {
+        let mut turns = Vec::new();
+        self.generate_turns(&mut MoveList::new(), &mut turns);
+        turns
+    }
+
+    pub fn apply_turn(&mut self, turn: Turn) -> Result<(), GameError> {
+        match turn {
+            Turn::Move(mv) => self.play_turn(mv.piece, mv.to),
+            Turn::Spawn(piece, position) => self.play_turn(piece, position),
+            Turn::Pass => self.play_turn_from_notation("pass", ""),
+        }
+    }
+
+    // Pieces of the same bug are interchangeable, so only the lowest one in the reserve counts
+    fn spawnable_pieces(&self) -> impl Iterator<Item = Piece> + '_ {
+        let color = self.turn_color;
+        let queen_required = self.board.queen_required(self.turn, color, &self.rules);
+        Bug::all().filter_map(move |bug| {
+            let allowed = match bug {
+                Bug::Queen => self.queen_allowed(),
+                _ => !queen_required,
+            };
+            if !allowed {
+                return None;
+            }
+            let orders = if bug.has_order() {
+                1..=self.rules.bug_count(bug)
+            } else {
+                0..=0
+            };
+            orders
+                .map(|order| Piece::new_from(bug, color, order))
+                .filter(|piece| self.rules.piece_allowed(bug, piece.order()))
+                .find(|piece| !self.board.piece_already_played(*piece))
+        })
     }
 
     pub fn play_turn_from_notation(
@@ -71,16 +168,16 @@ impl State {
     ) -> Result<(), GameError> {
         match piece {
             "pass" => {
-                if self.board.moves(self.turn_color).is_empty() {
+                self.ensure_not_finished(piece, position)?;
+                if !self.has_legal_action() {
                     self.pass();
                 } else {
-                    println!("Moves are: {:?}", self.board.moves(self.turn_color));
                     return Err(GameError::InvalidMove {
                         piece: "NA".to_string(),
                         from: "NA".to_string(),
                         to: "NA".to_string(),
                         turn: self.turn,
-                        reason: "Trying to pass when there are available moves.".to_string(),
+                        reason: IllegalMoveReason::MovesAvailable,
                     });
                 }
             }
@@ -109,32 +206,83 @@ impl State {
     }
 
     fn pass(&mut self) {
-        self.history
-            .record_move(self.turn_color.to_string(), "pass");
+        self.history.record_move("pass", "");
         self.turn_color = Color::from(self.turn_color.opposite());
         self.turn += 1;
-        self.board.last_moved = None;
+        self.board.last_move = None;
+        self.last_turn = LastTurn::Pass;
     }
 
-    fn next_turn(&mut self) {
-        if self.turn == 1 {
-            self.game_status = GameStatus::InProgress;
+    fn next_turn(&mut self) -> Result<(), GameError> {
+        // states set up from a position can already be in progress
+        if self.turn == 1 && self.game_status == GameStatus::NotStarted {
+            self.game_status.transition(GameStatus::InProgress)?;
         }
         match self.board.game_result() {
             GameResult::Winner(color) => {
-                self.game_status = GameStatus::Finished(GameResult::Winner(color));
+                self.game_status
+                    .transition(GameStatus::Finished(GameResult::Winner(color)))?;
                 self.history.record_move(color.to_string(), "won");
-                return;
+                return Ok(());
             }
             GameResult::Draw => {
-                self.game_status = GameStatus::Finished(GameResult::Draw);
+                self.game_status
+                    .transition(GameStatus::Finished(GameResult::Draw))?;
                 self.history.record_move("It's a draw", "");
-                return;
+                return Ok(());
             }
             GameResult::Unknown => {}
         }
         self.turn_color = Color::from(self.turn_color.opposite());
         self.turn += 1;
+        if self.repetition_draw() {
+            self.game_status
+                .transition(GameStatus::Finished(GameResult::Draw))?;
+            self.history.record_move("It's a draw", "");
+            return Ok(());
+        }
+        if self.rules.automatic_pass && !self.has_legal_action() {
+            self.pass();
+            self.last_turn = LastTurn::Shutout;
+            // neither player can move or place a piece, the game ends in a draw
+            if !self.has_legal_action() {
+                self.game_status
+                    .transition(GameStatus::Finished(GameResult::Draw))?;
+                self.history.record_move("It's a draw", "");
+            }
+        }
+        Ok(())
+    }
+
+    // Ends the game from outside the board, e.g. by resignation, agreement or adjudication.
+    // Use GameResult::Unknown when the game was stopped without a result.
+    pub fn finish(&mut self, result: GameResult) -> Result<(), GameError> {
+        self.game_status.transition(GameStatus::Finished(result))
+    }
+
+    fn ensure_not_finished(&self, piece: &str, to: &str) -> Result<(), GameError> {
+        if self.game_status.is_finished() {
+            return Err(GameError::InvalidMove {
+                piece: piece.to_string(),
+                from: "NA".to_string(),
+                to: to.to_string(),
+                turn: self.turn,
+                reason: IllegalMoveReason::GameOver,
+            });
+        }
+        Ok(())
+    }
+
+    fn repetition_draw(&mut self) -> bool {
+        if let Some(limit) = self.rules.repetition_draw_limit {
+            // the same pieces in the same places with a different player to move is a
+            // different position
+            let key = self.board.position_key() ^ self.turn_color as u64;
+            let seen = self.repetitions.entry(key).or_default();
+            *seen += 1;
+            return *seen >= limit;
+        }
+        false
     }
 
     fn turn_move(&mut self, piece: Piece, target_position: Position) -> Result<(), GameError> {
@@ -143,75 +291,111 @@ impl State {
             from: "NA".to_string(),
             to: target_position.to_string(),
             turn: self.turn,
-            reason: "NA".to_string(),
+            reason: IllegalMoveReason::NotOnBoard,
         };
-        let current_position = self.board.position_of_piece(piece).ok_or({
-            err.update_reason("This piece is not on the board.");
-            err.clone()
-        })?;
+        let current_position = self.board.position_of_piece(piece).ok_or(err.clone())?;
         err.update_from(current_position.to_string());
+        if !self.board.is_top_piece(piece, current_position) {
+            err.update_reason(IllegalMoveReason::CoveredPiece);
+            return Err(err);
+        }
+        if !self.board.queen_played(self.turn_color) {
+            err.update_reason(IllegalMoveReason::QueenNotPlayed);
+            return Err(err);
+        }
+        if self.board.is_stunned(piece, current_position) {
+            err.update_reason(IllegalMoveReason::JustMoved);
+            return Err(err);
+        }
         if self.board.is_pinned(piece) {
-            err.update_reason("Piece is pinned.");
+            err.update_reason(IllegalMoveReason::Pinned);
             return Err(err);
         }
-        // remove the piece from its current location
-        if !self
-            .board
-            .is_valid_move(self.turn_color, piece, current_position, target_position)
-        {
-            println!("Board state is: {}", self.board);
-            err.update_reason("This move isn't valid.");
+        if !self.board.is_valid_move(
+            self.turn_color,
+            piece,
+            current_position,
+            target_position,
+            &self.rules,
+        ) {
+            let explanation = self.explain(piece, Some(target_position));
+            err.update_reason(
+                explanation
+                    .reason()
+                    .unwrap_or(IllegalMoveReason::DestinationNotReachable),
+            );
             return Err(err);
         }
+        let thrown_by = self.board.thrower(
+            self.turn_color,
+            piece,
+            current_position,
+            target_position,
+            &self.rules,
+        );
         self.board
             .move_piece(piece, current_position, target_position, self.turn)?;
+        if let Some(last_move) = self.board.last_move.as_mut() {
+            last_move.thrown_by = thrown_by;
+        }
         Ok(())
     }
 
     pub fn turn_spawn(&mut self, piece: Piece, target_position: Position) -> Result<(), GameError> {
+        self.ensure_not_finished(&piece.to_string(), &target_position.to_string())?;
         let mut err = GameError::InvalidMove {
             piece: piece.to_string(),
             from: "Reserve".to_string(),
             to: target_position.to_string(),
             turn: self.turn,
-            reason: "NA".to_string(),
+            reason: IllegalMoveReason::DestinationNotReachable,
         };
         if !piece.is_color(self.turn_color) {
-            err.update_reason(format!(
-                "It is {}'s turn, but {} tried to spawn a piece.",
-                self.turn_color,
-                piece.color()
-            ));
+            err.update_reason(IllegalMoveReason::WrongColor);
             return Err(err);
         }
-        if self.turn < 2 && piece.bug() == Bug::Queen && self.tournament {
-            err.update_reason("Can't spawn Queen. Game uses tournament rules");
+        if !self.rules.piece_allowed(piece.bug(), piece.order()) {
+            err.update_reason(IllegalMoveReason::NotInGame);
             return Err(err);
         }
-        if piece.bug() != Bug::Queen && self.board.queen_required(self.turn, piece.color()) {
-            err.update_reason("Can't spawn another piece. Queen is required.");
+        if piece.bug() == Bug::Queen && !self.queen_allowed() {
+            err.update_reason(IllegalMoveReason::TournamentQueenOpening);
+            return Err(err);
+        }
+        if piece.bug() != Bug::Queen
+            && self
+                .board
+                .queen_required(self.turn, piece.color(), &self.rules)
+        {
+            err.update_reason(IllegalMoveReason::QueenRequired);
             return Err(err);
         }
         if self.board.spawnable(piece.color(), target_position) {
             self.board.insert(target_position, piece);
         } else {
-            err.update_reason(format!("{} is not allowed to spawn here.", self.turn_color));
+            // a free position next to the hive that isn't spawnable has to touch the opponent
+            if self.turn > 1 && self.board.is_negative_space(target_position) {
+                err.update_reason(IllegalMoveReason::SpawnTouchesOpponent);
+            } else {
+                err.update_reason(IllegalMoveReason::NotASpawnPosition);
+            }
             return Err(err);
         }
         Ok(())
     }
 
     pub fn play_turn(&mut self, piece: Piece, target_position: Position) -> Result<(), GameError> {
-        // TODO check for GameStatus::Finished
-        if self.board.piece_already_played(piece) {
-            self.turn_move(piece, target_position)?
+        self.ensure_not_finished(&piece.to_string(), &target_position.to_string())?;
+        if let Some(current_position) = self.board.position_of_piece(piece) {
+            self.turn_move(piece, target_position)?;
+            self.last_turn = LastTurn::Move(current_position, target_position);
         } else {
-            self.turn_spawn(piece, target_position)?
+            self.turn_spawn(piece, target_position)?;
+            self.last_turn = LastTurn::Spawn(target_position);
         }
         self.update_history(piece, target_position);
         debug_assert!(self.board.check());
-        self.next_turn();
-        Ok(())
+        self.next_turn()
     }
 
     pub fn check_board(&self) -> bool {
@@ -219,22 +403,439 @@ impl State {
         // issues on every turn
         //true
         // for this remove the return true and then implement your check in the loop
-        for r in 0..32 {
-            for q in 0..32 {
-                let position = Position::new(q, r);
-                let hex = self.board.board.get(position);
-                let neighbor_count = *self.board.neighbor_count.get(position);
-                let counted = self.board.positions_taken_around(position).count();
-                if counted != neighbor_count as usize {
-                    println!("Calculated: {counted} hashed: {neighbor_count}");
-                    println!("turn: {}", self.turn);
-                    println!("pos: {position}");
-                    println!("hex: {hex:?}");
-                    println!("{}", self.board);
-                    return false;
-                }
+        // every occupied position's neighbors have been counted, so they're all in the grid
+        for position in self.board.neighbor_count.positions() {
+            let hex = self.board.board.get(position);
+            let neighbor_count = *self.board.neighbor_count.get(position);
+            let counted = self.board.positions_taken_around(position).count();
+            if counted != neighbor_count as usize {
+                println!("Calculated: {counted} hashed: {neighbor_count}");
+                println!("turn: {}", self.turn);
+                println!("pos: {position}");
+                println!("hex: {hex:?}");
+                println!("{}", self.board);
+                return false;
             }
         }
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_position(state: &mut State) {
+        for (bug, color, order, q) in [
+            (Bug::Queen, Color::White, 0, 0),
+            (Bug::Queen, Color::Black, 0, 1),
+            (Bug::Ant, Color::White, 1, -1),
+            (Bug::Ant, Color::Black, 1, 2),
+        ] {
+            let piece = Piece::new_from(bug, color, order);
+            assert!(state.play_turn(piece, Position::new(q, 0)).is_ok());
+        }
+    }
+
+    #[test]
+    fn tests_repetition_draw() {
+        let mut rules = RuleSet::base();
+        rules.repetition_draw_limit = Some(3);
+        let mut state = State::new_from_rules(GameType::Base, rules);
+        open_position(&mut state);
+        let white_ant = Piece::new_from(Bug::Ant, Color::White, 1);
+        let black_ant = Piece::new_from(Bug::Ant, Color::Black, 1);
+        for _ in 0..2 {
+            assert_eq!(state.game_status, GameStatus::InProgress);
+            assert!(state.play_turn(white_ant, Position::new(0, 1)).is_ok());
+            assert!(state.play_turn(black_ant, Position::new(2, -1)).is_ok());
+            assert!(state.play_turn(white_ant, Position::new(-1, 0)).is_ok());
+            assert!(state.play_turn(black_ant, Position::new(2, 0)).is_ok());
+        }
+        assert_eq!(state.game_status, GameStatus::Finished(GameResult::Draw));
+
+        let mut state = State::new(GameType::Base, false);
+        open_position(&mut state);
+        for _ in 0..2 {
+            assert!(state.play_turn(white_ant, Position::new(0, 1)).is_ok());
+            assert!(state.play_turn(black_ant, Position::new(2, -1)).is_ok());
+            assert!(state.play_turn(white_ant, Position::new(-1, 0)).is_ok());
+            assert!(state.play_turn(black_ant, Position::new(2, 0)).is_ok());
+        }
+        assert_eq!(state.game_status, GameStatus::InProgress);
+    }
+
+    #[test]
+    fn tests_house_rule_bugs_count() {
+        let third_spider = Piece::new_from(Bug::Spider, Color::White, 3);
+        let mut state = State::new(GameType::Base, false);
+        assert!(state
+            .play_turn(third_spider, Position::initial_spawn_position())
+            .is_err());
+        let mosquito = Piece::new_from(Bug::Mosquito, Color::White, 0);
+        assert!(state
+            .play_turn(mosquito, Position::initial_spawn_position())
+            .is_err());
+
+        let mut rules = RuleSet::base();
+        rules.set_bug_count(Bug::Spider, 3);
+        let mut state = State::new_from_rules(GameType::Base, rules);
+        assert!(state
+            .play_turn(third_spider, Position::initial_spawn_position())
+            .is_ok());
+    }
+
+    #[test]
+    fn tests_queen_deadline() {
+        let mut rules = RuleSet::base();
+        rules.queen_deadline = 2;
+        let mut state = State::new_from_rules(GameType::Base, rules);
+        let white_ant = Piece::new_from(Bug::Ant, Color::White, 1);
+        let black_ant = Piece::new_from(Bug::Ant, Color::Black, 1);
+        assert!(state.play_turn(white_ant, Position::new(0, 0)).is_ok());
+        assert!(state.play_turn(black_ant, Position::new(1, 0)).is_ok());
+        let white_spider = Piece::new_from(Bug::Spider, Color::White, 1);
+        assert!(state.play_turn(white_spider, Position::new(-1, 0)).is_err());
+        let white_queen = Piece::new_from(Bug::Queen, Color::White, 0);
+        assert!(state.play_turn(white_queen, Position::new(-1, 0)).is_ok());
+    }
+
+    fn shutout_position(rules: RuleSet) -> State {
+        // the black queen is covered by the white beetle and the black beetle is pinned
+        let mut state = State::new_from_rules(GameType::Base, rules);
+        for (bug, color, q) in [
+            (Bug::Queen, Color::Black, 0),
+            (Bug::Beetle, Color::White, 0),
+            (Bug::Beetle, Color::Black, 1),
+            (Bug::Queen, Color::White, 2),
+        ] {
+            let order = if bug.has_order() { 1 } else { 0 };
+            let piece = Piece::new_from(bug, color, order);
+            state.board.insert(Position::new(q, 0), piece);
+        }
+        state.board.last_move = None;
+        state.turn = 10;
+        state.game_status = GameStatus::InProgress;
+        state
+    }
+
+    #[test]
+    fn tests_automatic_pass() {
+        let mut rules = RuleSet::base();
+        rules.bugs_count = [0; 8];
+        rules.set_bug_count(Bug::Queen, 1);
+        rules.set_bug_count(Bug::Beetle, 1);
+        rules.automatic_pass = true;
+        let mut state = shutout_position(rules);
+        let white_queen = Piece::new_from(Bug::Queen, Color::White, 0);
+        assert!(state.play_turn(white_queen, Position::new(2, -1)).is_ok());
+        assert_eq!(state.last_turn, LastTurn::Shutout);
+        assert_eq!(state.turn, 12);
+        assert_eq!(state.turn_color, Color::White);
+        assert_eq!(
+            state.history.moves.last(),
+            Some(&("pass".to_string(), "".to_string()))
+        );
+        assert!(state.play_turn(white_queen, Position::new(2, 0)).is_ok());
+        assert_eq!(state.last_turn, LastTurn::Shutout);
+
+        rules.automatic_pass = false;
+        let mut state = shutout_position(rules);
+        assert!(state.play_turn(white_queen, Position::new(2, -1)).is_ok());
+        assert_eq!(
+            state.last_turn,
+            LastTurn::Move(Position::new(2, 0), Position::new(2, -1))
+        );
+        assert_eq!(state.turn_color, Color::Black);
+        assert!(!state.has_legal_action());
+        assert!(state.play_turn_from_notation("pass", "").is_ok());
+        assert_eq!(state.last_turn, LastTurn::Pass);
+        assert_eq!(state.turn_color, Color::White);
+    }
+
+    #[test]
+    fn tests_double_shutout() {
+        // without queens no piece may move, once both ants are placed nobody can act
+        let mut rules = RuleSet::base();
+        rules.bugs_count = [0; 8];
+        rules.set_bug_count(Bug::Ant, 1);
+        rules.automatic_pass = true;
+        let mut state = State::new_from_rules(GameType::Base, rules);
+        state.play_turn_from_notation("wA1", ".").unwrap();
+        assert!(!state.game_status.is_finished());
+        state.play_turn_from_notation("bA1", "wA1-").unwrap();
+        assert_eq!(state.last_turn, LastTurn::Shutout);
+        assert_eq!(state.game_status, GameStatus::Finished(GameResult::Draw));
+        assert_eq!(
+            state.history.moves[2..],
+            [
+                ("pass".to_string(), "".to_string()),
+                ("It's a draw".to_string(), "".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn tests_pass_with_spawns_available() {
+        let mut state = State::new(GameType::Base, false);
+        assert_eq!(
+            illegal_move_reason(state.play_turn_from_notation("pass", "")),
+            Some(IllegalMoveReason::MovesAvailable)
+        );
+        assert!(state.play_turn_from_notation("wA1", ".").is_ok());
+        assert_eq!(state.last_turn, LastTurn::Spawn(Position::new(0, 0)));
+    }
+
+    #[test]
+    fn tests_game_over() {
+        let mut state = State::new(GameType::Base, false);
+        open_position(&mut state);
+        assert!(state.finish(GameResult::Unknown).is_ok());
+        assert_eq!(state.game_status, GameStatus::Finished(GameResult::Unknown));
+        let white_ant = Piece::new_from(Bug::Ant, Color::White, 1);
+        assert_eq!(
+            illegal_move_reason(state.play_turn(white_ant, Position::new(0, 1))),
+            Some(IllegalMoveReason::GameOver)
+        );
+        assert_eq!(
+            illegal_move_reason(state.play_turn_from_notation("pass", "")),
+            Some(IllegalMoveReason::GameOver)
+        );
+        assert!(matches!(
+            state.finish(GameResult::Draw),
+            Err(GameError::InvalidStatusTransition { .. })
+        ));
+        let white_spider = Piece::new_from(Bug::Spider, Color::White, 1);
+        assert_eq!(
+            illegal_move_reason(state.turn_spawn(white_spider, Position::new(-2, 0))),
+            Some(IllegalMoveReason::GameOver)
+        );
+        assert_eq!(state.turn, 4);
+
+        // once a queen is surrounded nothing can be played
+        let history = History::from_filepath("./test_pgns/valid/pass2.pgn").unwrap();
+        let mut state = State::new_from_history(&history).unwrap();
+        assert_eq!(
+            state.game_status,
+            GameStatus::Finished(GameResult::Winner(Color::Black))
+        );
+        assert_eq!(
+            illegal_move_reason(state.play_turn_from_notation("wA1", "wQ-")),
+            Some(IllegalMoveReason::GameOver)
+        );
+        assert_eq!(
+            illegal_move_reason(state.play_turn_from_notation("pass", "")),
+            Some(IllegalMoveReason::GameOver)
+        );
+    }
+
+    #[test]
+    fn tests_play_from_turn_one_position() {
+        let mut state = State::new(GameType::Base, true);
+        state.play_turn_from_notation("wA1", ".").unwrap();
+        let mut state = State::from_position_string(&state.to_position_string()).unwrap();
+        state.play_turn_from_notation("bA1", "wA1-").unwrap();
+        assert_eq!((state.turn, state.turn_color), (2, Color::White));
+        assert_eq!(state.game_status, GameStatus::InProgress);
+    }
+
+    fn illegal_move_reason(result: Result<(), GameError>) -> Option<IllegalMoveReason> {
+        match result {
+            Err(GameError::InvalidMove { reason, .. }) => Some(reason),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn tests_check_board() {
+        let mut state = State::new(GameType::Base, false);
+        open_position(&mut state);
+        assert!(state.check_board());
+        // a wrong count at negative coordinates is found too
+        state.board.neighbor_count.set(Position::new(-3, -3), 1);
+        assert!(!state.check_board());
+    }
+
+    #[test]
+    fn tests_pillbug_rules() {
+        let mut state = State::new(GameType::P, false);
+        let white_pillbug = Piece::new_from(Bug::Pillbug, Color::White, 0);
+        let black_queen = Piece::new_from(Bug::Queen, Color::Black, 0);
+        let black_ant = Piece::new_from(Bug::Ant, Color::Black, 1);
+        for (piece, q, r) in [
+            (white_pillbug, 0, 0),
+            (black_queen, 1, 0),
+            (Piece::new_from(Bug::Queen, Color::White, 0), -1, 0),
+            (black_ant, 2, 0),
+            (Piece::new_from(Bug::Ant, Color::White, 1), -2, 0),
+            (Piece::new_from(Bug::Ant, Color::Black, 2), 2, -1),
+            (Piece::new_from(Bug::Ant, Color::White, 1), -1, 1),
+            // the black ant ends up next to the white pillbug
+            (black_ant, 1, -1),
+        ] {
+            assert!(state.play_turn(piece, Position::new(q, r)).is_ok());
+        }
+        // the pillbug can't throw the piece black just moved
+        assert_eq!(
+            illegal_move_reason(state.play_turn(black_ant, Position::new(0, -1))),
+            Some(IllegalMoveReason::JustMoved)
+        );
+        assert!(!state
+            .board
+            .moves(Color::White, &state.rules)
+            .contains_key(&(black_ant, Position::new(1, -1))));
+        // but it can throw the black queen
+        assert!(state.play_turn(black_queen, Position::new(0, 1)).is_ok());
+        let last_move = state.board.last_move.unwrap();
+        assert_eq!(last_move.piece, black_queen);
+        assert_eq!(last_move.from, Some(Position::new(1, 0)));
+        assert_eq!(last_move.thrown_by, Some(white_pillbug));
+        // the thrown queen can't move on black's turn
+        assert_eq!(
+            illegal_move_reason(state.play_turn(black_queen, Position::new(-1, 1))),
+            Some(IllegalMoveReason::JustMoved)
+        );
+        let black_ant = Piece::new_from(Bug::Ant, Color::Black, 2);
+        assert!(state.play_turn(black_ant, Position::new(0, -1)).is_ok());
+        assert!(!state.board.last_move.unwrap().was_thrown());
+        // the stun only lasts one turn
+        assert!(state
+            .board
+            .moves(Color::White, &state.rules)
+            .contains_key(&(black_queen, Position::new(0, 1))));
+    }
+
+    // The tests_rulebook_* tests reproduce the examples of the pillbug and mosquito rules of the
+    // rulebook, each one quotes the rule it shows
+    fn piece(s: &str) -> Piece {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn tests_rulebook_pillbug_throw() {
+        // "The Pillbug may move an adjacent unstacked piece (friend or enemy) two spaces: up onto
+        // itself and then down into an empty space adjacent to itself."
+        let mut state = State::from_position_string(
+            "Base+P;4;w;0,0:wP -1,0:wQ 1,0:bQ 1,-1:bA1;\
+             wA1 wA2 wA3 wB1 wB2 wG1 wG2 wG3 wS1 wS2;\
+             bA2 bA3 bB1 bB2 bG1 bG2 bG3 bP bS1 bS2;-",
+        )
+        .unwrap();
+        state.play_turn(piece("bA1"), Position::new(0, -1)).unwrap();
+        let last_move = state.board.last_move.unwrap();
+        assert_eq!(last_move.thrown_by, Some(piece("wP")));
+        // "Any piece which physically moved (directly or by the Pillbug) is rendered immobile on
+        // the next player's turn."
+        assert_eq!(
+            illegal_move_reason(state.play_turn(piece("bA1"), Position::new(1, -1))),
+            Some(IllegalMoveReason::JustMoved)
+        );
+        state.play_turn(piece("bG1"), Position::new(2, 0)).unwrap();
+        // after that turn the ant can be thrown again
+        assert!(state
+            .board
+            .moves(Color::White, &state.rules)
+            .contains_key(&(piece("bA1"), Position::new(0, -1))));
+    }
+
+    #[test]
+    fn tests_rulebook_pillbug_exceptions() {
+        // "The Pillbug may not move the piece most recently moved by your opponent, a piece in a
+        // stack of pieces, or a piece whose removal would split the hive."
+        let mut state = State::from_position_string(
+            "Base+P;10;w;0,0:wP -1,0:wQ/bB1 1,0:bQ 2,0:bA1 1,-1:bA2 -1,1:bG1 -2,0:wA1 -3,0:wA2 \
+             -2,1:wG1;wA3 wB1 wB2 wG2 wG3 wS1 wS2;bA3 bB2 bG2 bG3 bP bS1 bS2;bA2 from 2,-1",
+        )
+        .unwrap();
+        let moves = state.board.moves(Color::White, &state.rules);
+        assert!(!moves.contains_key(&(piece("bA2"), Position::new(1, -1))));
+        assert!(!moves.contains_key(&(piece("bB1"), Position::new(-1, 0))));
+        assert!(!moves.contains_key(&(piece("bQ"), Position::new(1, 0))));
+        // the grasshopper next to the pillbug is none of these
+        assert!(moves[&(piece("bG1"), Position::new(-1, 1))].contains(&Position::new(0, 1)));
+        assert_eq!(
+            illegal_move_reason(state.play_turn(piece("bA2"), Position::new(0, -1))),
+            Some(IllegalMoveReason::JustMoved)
+        );
+    }
+
+    #[test]
+    fn tests_rulebook_moved_pillbug_and_mosquito() {
+        // the black pillbug throws the white pillbug next to the white mosquito
+        let mut state = State::from_position_string(
+            "Base+MP;7;b;-1,0:wQ 0,0:wP -1,1:wM 0,-1:wA1 1,0:bP 2,0:bQ 1,-1:bA1;\
+             wA2 wA3 wB1 wB2 wG1 wG2 wG3 wS1 wS2;bA2 bA3 bB1 bB2 bG1 bG2 bG3 bM bS1 bS2;-",
+        )
+        .unwrap();
+        state.play_turn(piece("wP"), Position::new(0, 1)).unwrap();
+        assert_eq!(state.board.last_move.unwrap().thrown_by, Some(piece("bP")));
+        // "... is rendered immobile on the next player's turn: it cannot move or be moved, nor
+        // use its special ability."
+        let abilities = |position| {
+            Bug::available_abilities(Position::new(-1, 1), &state.board, &state.rules)
+                .contains_key(&position)
+        };
+        assert!(
+            Bug::available_abilities(Position::new(0, 1), &state.board, &state.rules).is_empty()
+        );
+        // "The Mosquito can mimic either the movement or special ability of the Pillbug, even
+        // when the Pillbug it is touching has been rendered immobile by another Pillbug."
+        assert!(abilities(Position::new(-1, 0)));
+        // but the immobile pillbug can't be moved by it either
+        assert!(!abilities(Position::new(0, 1)));
+    }
+
+    #[test]
+    fn tests_illegal_move_reasons() {
+        let mut state = State::new(GameType::Base, true);
+        let white_queen = Piece::new_from(Bug::Queen, Color::White, 0);
+        assert_eq!(
+            illegal_move_reason(state.play_turn(white_queen, Position::new(0, 0))),
+            Some(IllegalMoveReason::TournamentQueenOpening)
+        );
+        let black_ant = Piece::new_from(Bug::Ant, Color::Black, 1);
+        assert_eq!(
+            illegal_move_reason(state.play_turn(black_ant, Position::new(0, 0))),
+            Some(IllegalMoveReason::WrongColor)
+        );
+
+        let mut state = State::new(GameType::Base, false);
+        open_position(&mut state);
+        // the black queen connects the two halves of the hive
+        let black_queen = Piece::new_from(Bug::Queen, Color::Black, 0);
+        assert!(state
+            .play_turn(
+                Piece::new_from(Bug::Spider, Color::White, 1),
+                Position::new(-2, 0)
+            )
+            .is_ok());
+        assert_eq!(
+            illegal_move_reason(state.play_turn(black_queen, Position::new(1, -1))),
+            Some(IllegalMoveReason::Pinned)
+        );
+        let black_spider = Piece::new_from(Bug::Spider, Color::Black, 1);
+        assert_eq!(
+            illegal_move_reason(state.play_turn(black_spider, Position::new(-3, 0))),
+            Some(IllegalMoveReason::SpawnTouchesOpponent)
+        );
+        let white_spider = Piece::new_from(Bug::Spider, Color::White, 2);
+        assert_eq!(
+            illegal_move_reason(state.play_turn(white_spider, Position::new(-3, 0))),
+            Some(IllegalMoveReason::WrongColor)
+        );
+        let white_spider = Piece::new_from(Bug::Spider, Color::White, 1);
+        assert!(state.play_turn(black_spider, Position::new(3, 0)).is_ok());
+        assert_eq!(
+            illegal_move_reason(state.play_turn(white_spider, Position::new(-1, -1))),
+            Some(IllegalMoveReason::DestinationNotReachable)
+        );
+        // occupied and far away positions aren't spawn positions either
+        let white_spider = Piece::new_from(Bug::Spider, Color::White, 2);
+        for position in [Position::new(-2, 0), Position::new(-6, 0)] {
+            assert_eq!(
+                illegal_move_reason(state.play_turn(white_spider, position)),
+                Some(IllegalMoveReason::NotASpawnPosition)
+            );
+        }
+    }
+}
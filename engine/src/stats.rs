@@ -0,0 +1,256 @@
+use crate::{
+    color::Color, game_result::GameResult, game_status::GameStatus, game_type::GameType,
+    history::History, piece::Piece, state::State, validation::replay,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Outcomes {
+    pub games: usize,
+    pub white_wins: usize,
+    pub black_wins: usize,
+    pub draws: usize,
+    // games that ended without a result on the board
+    pub unfinished: usize,
+    // rates are relative to the finished games
+    pub white_win_rate: f64,
+    pub black_win_rate: f64,
+    pub draw_rate: f64,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct CorpusStats {
+    pub games: usize,
+    // games that could not be replayed, they are not part of any other number
+    pub skipped: usize,
+    pub opening_depth: usize,
+    pub outcomes: Outcomes,
+    pub outcomes_by_game_type: BTreeMap<String, Outcomes>,
+    // bug of white's first piece
+    pub first_moves: BTreeMap<String, usize>,
+    // the first `opening_depth` moves, e.g. "wL .; bL wL-"
+    pub openings: BTreeMap<String, usize>,
+    pub average_length: f64,
+    pub passes: usize,
+    pub games_with_passes: usize,
+    // passes per played turn
+    pub pass_rate: f64,
+    // bug of the piece that made the winning move
+    pub final_surround: BTreeMap<String, usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct GameStats {
+    game_type: GameType,
+    game_status: GameStatus,
+    turns: usize,
+    passes: usize,
+    first_move: Option<String>,
+    opening: Option<String>,
+    final_surround: Option<String>,
+}
+
+impl Outcomes {
+    fn add(&mut self, game_status: &GameStatus) {
+        self.games += 1;
+        match game_status {
+            GameStatus::Finished(GameResult::Winner(Color::White)) => self.white_wins += 1,
+            GameStatus::Finished(GameResult::Winner(Color::Black)) => self.black_wins += 1,
+            GameStatus::Finished(GameResult::Draw) => self.draws += 1,
+            _ => self.unfinished += 1,
+        }
+        let finished = (self.games - self.unfinished) as f64;
+        if finished > 0.0 {
+            self.white_win_rate = self.white_wins as f64 / finished;
+            self.black_win_rate = self.black_wins as f64 / finished;
+            self.draw_rate = self.draws as f64 / finished;
+        }
+    }
+}
+
+impl GameStats {
+    fn new(path: &Path, opening_depth: usize) -> Option<Self> {
+        let history = History::from_filepath(&path.display().to_string()).ok()?;
+        let state = replay(&history).ok()?;
+        let moves = &history.moves;
+        let first_move = moves
+            .first()
+            .and_then(|(piece, _)| piece.parse::<Piece>().ok())
+            .map(|piece| piece.bug().name().to_string());
+        let opening = (opening_depth > 0 && moves.len() >= opening_depth).then(|| {
+            moves[..opening_depth]
+                .iter()
+                .map(|(piece, position)| format!("{piece} {position}").trim().to_string())
+                .collect::<Vec<_>>()
+                .join("; ")
+        });
+        let final_surround = final_surround(&state);
+        Some(Self {
+            game_type: history.game_type,
+            game_status: state.game_status,
+            turns: moves.len(),
+            passes: moves.iter().filter(|(piece, _)| piece == "pass").count(),
+            first_move,
+            opening,
+            final_surround,
+        })
+    }
+}
+
+// The bug that closed the last gap around a queen, a thrown piece is credited to the pillbug or
+// mosquito that threw it
+fn final_surround(state: &State) -> Option<String> {
+    match state.game_status {
+        GameStatus::Finished(GameResult::Winner(_)) => state.board.last_move.map(|last_move| {
+            let piece = last_move.thrown_by.unwrap_or(last_move.piece);
+            piece.bug().name().to_string()
+        }),
+        _ => None,
+    }
+}
+
+impl CorpusStats {
+    // Replays all games in parallel, games that fail to replay are skipped
+    pub fn new(paths: Vec<PathBuf>, opening_depth: usize) -> Self {
+        let games = paths
+            .par_iter()
+            .map(|path| GameStats::new(path, opening_depth))
+            .collect::<Vec<_>>();
+        let mut stats = CorpusStats {
+            opening_depth,
+            ..Default::default()
+        };
+        let mut turns = 0;
+        for game in games {
+            let Some(game) = game else {
+                stats.skipped += 1;
+                continue;
+            };
+            stats.games += 1;
+            stats.outcomes.add(&game.game_status);
+            stats
+                .outcomes_by_game_type
+                .entry(game.game_type.to_string())
+                .or_default()
+                .add(&game.game_status);
+            for (key, counts) in [
+                (game.first_move, &mut stats.first_moves),
+                (game.opening, &mut stats.openings),
+                (game.final_surround, &mut stats.final_surround),
+            ] {
+                if let Some(key) = key {
+                    *counts.entry(key).or_default() += 1;
+                }
+            }
+            turns += game.turns;
+            stats.passes += game.passes;
+            if game.passes > 0 {
+                stats.games_with_passes += 1;
+            }
+        }
+        if stats.games > 0 {
+            stats.average_length = turns as f64 / stats.games as f64;
+        }
+        if turns > 0 {
+            stats.pass_rate = stats.passes as f64 / turns as f64;
+        }
+        stats
+    }
+
+    // Most played openings first, ties are ordered by their moves
+    pub fn top_openings(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut openings = self
+            .openings
+            .iter()
+            .map(|(opening, count)| (opening.as_str(), *count))
+            .collect::<Vec<_>>();
+        openings.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        openings.truncate(n);
+        openings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{corpus::pgn_files, position::Position};
+
+    #[test]
+    fn tests_corpus_stats() {
+        let valid = pgn_files(&["./test_pgns/valid"]).unwrap();
+        let invalid = pgn_files(&["./test_pgns/invalid"]).unwrap();
+        let stats = CorpusStats::new([valid.clone(), invalid.clone()].concat(), 2);
+        assert_eq!(stats.games, valid.len());
+        assert_eq!(stats.skipped, invalid.len());
+        assert_eq!(stats.outcomes.games, stats.games);
+        assert_eq!(stats.first_moves.values().sum::<usize>(), stats.games);
+        assert_eq!(stats.openings.values().sum::<usize>(), stats.games);
+        assert_eq!(
+            stats
+                .outcomes_by_game_type
+                .values()
+                .map(|outcomes| outcomes.games)
+                .sum::<usize>(),
+            stats.games
+        );
+        assert_eq!(
+            stats.final_surround.values().sum::<usize>(),
+            stats.outcomes.white_wins + stats.outcomes.black_wins
+        );
+        assert!(stats.passes > 0 && stats.games_with_passes > 0);
+        assert!(stats.average_length > 0.0);
+        let outcomes = &stats.outcomes;
+        let rates = outcomes.white_win_rate + outcomes.black_win_rate + outcomes.draw_rate;
+        assert!(outcomes.unfinished == outcomes.games || (rates - 1.0).abs() < 1e-9);
+        assert!(serde_json::to_string(&stats).is_ok());
+    }
+
+    #[test]
+    fn tests_final_surround_by_throw() {
+        // the black pillbug throws the white grasshopper into the last gap around the white queen
+        let mut state = State::from_position_string(
+            "Base+P;9;b;0,0:wQ -1,0:wA1 0,1:wA2 -1,1:wA3 2,0:wG1 0,-1:bA1 1,-1:bA2 2,-1:bP \
+             2,-2:bQ;wB1 wB2 wG2 wG3 wP wS1 wS2;bA3 bB1 bB2 bG1 bG2 bG3 bS1 bS2;-",
+        )
+        .unwrap();
+        assert_eq!(final_surround(&state), None);
+        state
+            .play_turn("wG1".parse().unwrap(), Position::new(1, 0))
+            .unwrap();
+        assert_eq!(
+            state.game_status,
+            GameStatus::Finished(GameResult::Winner(Color::Black))
+        );
+        assert_eq!(final_surround(&state), Some("Pillbug".to_string()));
+    }
+
+    #[test]
+    fn tests_outcomes() {
+        let mut outcomes = Outcomes::default();
+        outcomes.add(&GameStatus::Finished(GameResult::Winner(Color::White)));
+        outcomes.add(&GameStatus::Finished(GameResult::Draw));
+        outcomes.add(&GameStatus::InProgress);
+        assert_eq!(outcomes.games, 3);
+        assert_eq!(outcomes.unfinished, 1);
+        assert_eq!(outcomes.white_win_rate, 0.5);
+        assert_eq!(outcomes.draw_rate, 0.5);
+        assert_eq!(outcomes.black_win_rate, 0.0);
+    }
+
+    #[test]
+    fn tests_top_openings() {
+        let mut stats = CorpusStats::default();
+        stats.openings.insert("wL .; bL wL-".to_string(), 1);
+        stats.openings.insert("wG .; bG wG-".to_string(), 3);
+        stats.openings.insert("wA1 .; bA1 wA1-".to_string(), 1);
+        assert_eq!(
+            stats.top_openings(2),
+            vec![("wG .; bG wG-", 3), ("wA1 .; bA1 wA1-", 1)]
+        );
+    }
+}
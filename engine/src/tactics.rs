@@ -0,0 +1,187 @@
+// Immediate tactics around the queens: which moves win on the spot, which wins the opponent
+// threatens and how free the queens and their neighbors are. Only moves can win, a placed
+// piece never touches the opposing queen.
+use crate::{
+    board::Board, bug::Bug, color::Color, game_result::GameResult, move_list::Move, piece::Piece,
+    rule_set::RuleSet, state::State,
+};
+use serde::Serialize;
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Tactics {
+    pub side_to_move: Color,
+    // moves that surround the opposing queen right away, moves that surround both queens draw
+    // and aren't part of it
+    pub winning_moves: Vec<Move>,
+    // moves the opponent would win with if it was their turn
+    pub threats: Vec<Move>,
+    // both are false while the queen is in the reserve
+    pub queen_pinned: bool,
+    pub queen_immobile: bool,
+    // pieces of the side to move next to its own queen that can't move, covered ones included
+    pub stuck_beside_queen: Vec<Piece>,
+}
+
+impl Board {
+    pub fn winning_moves(&self, color: Color, rules: &RuleSet) -> Vec<Move> {
+        let opponent = Color::from(color.opposite());
+        let Some(queen) = self.position_of_piece(Piece::new_from(Bug::Queen, opponent, 0)) else {
+            return Vec::new();
+        };
+        // a move can only surround the queen if it ends next to it or frees a piece on top of it
+        let mut moves = self
+            .moves(color, rules)
+            .into_iter()
+            .flat_map(|((piece, from), targets)| {
+                targets
+                    .into_iter()
+                    .map(move |to| Move::new(piece, from, to))
+            })
+            .filter(|mv| mv.to.is_neighbor(queen) || mv.from == queen)
+            .filter(|mv| {
+                let mut board = self.clone();
+                board
+                    .move_piece(mv.piece, mv.from, mv.to, 0)
+                    .expect("Generated moves are legal");
+                board.game_result() == GameResult::Winner(color)
+            })
+            .collect::<Vec<_>>();
+        moves.sort_by_key(|mv| (mv.piece.to_string(), mv.from, mv.to));
+        moves
+    }
+
+    // Pieces of `color` next to its queen that can't move
+    pub fn stuck_beside_queen(&self, color: Color, rules: &RuleSet) -> Vec<Piece> {
+        let Some(queen) = self.position_of_piece(Piece::new_from(Bug::Queen, color, 0)) else {
+            return Vec::new();
+        };
+        let moves = self.moves(color, rules);
+        let mut stuck = queen
+            .positions_around()
+            .flat_map(|position| {
+                let stack = self.board.get(position).clone();
+                let top = stack.top_piece();
+                stack.pieces[..stack.len()]
+                    .iter()
+                    .filter(|piece| piece.is_color(color))
+                    .filter(|piece| {
+                        Some(**piece) != top
+                            || moves
+                                .get(&(**piece, position))
+                                .is_none_or(|targets| targets.is_empty())
+                    })
+                    .copied()
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        stuck.sort_by_key(Piece::to_string);
+        stuck
+    }
+}
+
+impl State {
+    pub fn tactics(&self) -> Tactics {
+        let color = self.turn_color;
+        let queen = Piece::new_from(Bug::Queen, color, 0);
+        let queen_position = self.board.position_of_piece(queen);
+        let queen_moves = queen_position.map(|position| {
+            self.board
+                .moves(color, &self.rules)
+                .get(&(queen, position))
+                .map_or(0, Vec::len)
+        });
+        // the opponent moves after this turn, nothing they move now is stunned then
+        let mut opponent_board = self.board.clone();
+        opponent_board.last_move = None;
+        Tactics {
+            side_to_move: color,
+            winning_moves: self.board.winning_moves(color, &self.rules),
+            threats: opponent_board.winning_moves(Color::from(color.opposite()), &self.rules),
+            queen_pinned: queen_position.is_some() && self.board.is_pinned(queen),
+            queen_immobile: queen_moves == Some(0),
+            stuck_beside_queen: self.board.stuck_beside_queen(color, &self.rules),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game_type::GameType, position::Position};
+
+    // The black queen has one free neighbor, the white ant can crawl into it
+    const ALMOST_SURROUNDED: &str = "0,-1:wQ 1,-1:bA1 -1,1:wB1 0,0:bQ 1,0:bG1 0,1:bS1 -1,2:wA1";
+
+    fn state(color: &str) -> State {
+        let turn = if color == "w" { 10 } else { 11 };
+        State::from_position_string(&format!(
+            "Base;{turn};{color};{ALMOST_SURROUNDED};\
+             wA2 wA3 wB2 wG1 wG2 wG3 wS1 wS2;bA2 bA3 bB1 bB2 bG2 bG3 bS2;-"
+        ))
+        .unwrap()
+    }
+
+    fn piece(s: &str) -> Piece {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn tests_winning_moves() {
+        let white = state("w");
+        let win = Move::new(piece("wA1"), Position::new(-1, 2), Position::new(-1, 0));
+        let tactics = white.tactics();
+        assert_eq!(tactics.side_to_move, Color::White);
+        assert_eq!(tactics.winning_moves, vec![win]);
+        assert!(tactics.threats.is_empty());
+        let mut won = white.clone();
+        won.play_turn(win.piece, win.to).unwrap();
+        assert_eq!(won.board.game_result(), GameResult::Winner(Color::White));
+
+        // with black to move the same move is a threat
+        let tactics = state("b").tactics();
+        assert!(tactics.winning_moves.is_empty());
+        assert_eq!(tactics.threats, vec![win]);
+        assert!(!tactics.queen_pinned);
+        assert!(tactics.queen_immobile);
+        let black = state("b");
+        let moves = black.board.moves(Color::Black, &black.rules);
+        for stuck in tactics.stuck_beside_queen.iter() {
+            let position = black.board.position_of_piece(*stuck).unwrap();
+            assert!(moves.get(&(*stuck, position)).is_none_or(Vec::is_empty));
+        }
+    }
+
+    #[test]
+    fn tests_queen_and_neighbors() {
+        let mut state = State::new(GameType::Base, true);
+        for (piece, position) in [
+            ("wA1", "."),
+            ("bA1", "wA1-"),
+            ("wQ", "-wA1"),
+            ("bQ", "bA1-"),
+        ] {
+            state.play_turn_from_notation(piece, position).unwrap();
+        }
+        // white's queen is at the end of the line and can slide
+        let tactics = state.tactics();
+        assert!(!tactics.queen_pinned && !tactics.queen_immobile);
+        assert!(tactics.winning_moves.is_empty() && tactics.threats.is_empty());
+        // the ant between the queens holds the hive together
+        assert_eq!(tactics.stuck_beside_queen, vec![piece("wA1")]);
+        // a beetle on top of the ant next to the queen covers it
+        state.play_turn_from_notation("wB1", "wQ/").unwrap();
+        state.play_turn_from_notation("bB1", "bQ-").unwrap();
+        state.play_turn_from_notation("wB1", "wA1").unwrap();
+        state.play_turn_from_notation("bG1", "bB1-").unwrap();
+        let tactics = state.tactics();
+        assert_eq!(tactics.side_to_move, Color::White);
+        assert!(tactics.stuck_beside_queen.contains(&piece("wA1")));
+        assert!(!tactics.stuck_beside_queen.contains(&piece("wB1")));
+        // nothing is stuck while the queen is in the reserve
+        let mut state = State::new(GameType::Base, true);
+        state.play_turn_from_notation("wA1", ".").unwrap();
+        let tactics = state.tactics();
+        assert!(!tactics.queen_pinned && !tactics.queen_immobile);
+        assert!(tactics.stuck_beside_queen.is_empty());
+    }
+}
@@ -0,0 +1,426 @@
+// Positions from replayed games as fixed-size samples for training evaluation functions.
+//
+// Every sample is seen from the side to move: "own" pieces are the ones of the side to move.
+// The board is encoded as planes over a `window` x `window` grid of axial coordinates, plane
+// (owner * BUGS + bug) * LEVELS + level - 1 is 1 where that piece is at that level, pieces
+// above LEVELS share the top plane. The hive is turned into its canonical orientation, see
+// `Board::canonical_orientation`, so the same position always gets the same encoding.
+use crate::{
+    color::Color,
+    game_result::GameResult,
+    game_status::GameStatus,
+    history::History,
+    last_turn::LastTurn,
+    piece::Piece,
+    position::{self, Position},
+    state::State,
+    validation::replay,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+pub const BUGS: usize = 8;
+pub const LEVELS: usize = 4;
+pub const PLANES: usize = 2 * BUGS * LEVELS;
+
+// What was played from the position
+pub const SPAWN: i16 = 0;
+pub const MOVE: i16 = 1;
+pub const PASS: i16 = 2;
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub game: String,
+    pub turn: usize,
+    // 0 for white, 1 for black
+    pub side_to_move: u8,
+    // PLANES x window x window, rows are r and columns are q
+    pub planes: Vec<u8>,
+    // pieces left in the reserve per bug, own first
+    pub reserves: [[u8; BUGS]; 2],
+    // kind, owner, bug, from q, from r, to q, to r in window coordinates, -1 where it doesn't
+    // apply, destinations next to the hive can be outside the window
+    pub played: [i16; 7],
+    // 1 if the side to move won the game, -1 if it lost and 0 for a draw
+    pub result: i8,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrainingData {
+    pub window: usize,
+    pub games: usize,
+    // games that could not be replayed or have no result
+    pub skipped_games: usize,
+    // positions with a hive wider than the window
+    pub skipped_positions: usize,
+    pub samples: Vec<Sample>,
+}
+
+// The canonical orientation of the hive followed by a translation into the window
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Orientation {
+    canonical: position::Orientation,
+    offset: (i32, i32),
+}
+
+impl Orientation {
+    fn apply(&self, position: Position) -> (i32, i32) {
+        let turned = self.canonical.apply(position);
+        (turned.q + self.offset.0, turned.r + self.offset.1)
+    }
+
+    // None if the hive doesn't fit into the window
+    fn new(state: &State, window: usize) -> Option<Self> {
+        let key = |piece: Piece| (owner(state, piece.color()), piece.bug() as u8);
+        let canonical = state.board.canonical_orientation(key);
+        let cells = state.board.oriented_cells(&canonical, key);
+        let max_q = cells.iter().map(|(_, q, _)| *q).max().unwrap_or(-1);
+        let max_r = cells.iter().map(|(r, _, _)| *r).max().unwrap_or(-1);
+        let window = window as i32;
+        if max_q >= window || max_r >= window {
+            return None;
+        }
+        Some(Self {
+            canonical,
+            offset: ((window - (max_q + 1)) / 2, (window - (max_r + 1)) / 2),
+        })
+    }
+}
+
+fn owner(state: &State, color: Color) -> usize {
+    usize::from(color != state.turn_color)
+}
+
+fn encode(state: &State, orientation: &Orientation, window: usize) -> Vec<u8> {
+    let mut planes = vec![0; PLANES * window * window];
+    for position in state.board.all_taken_positions() {
+        let (q, r) = orientation.apply(position);
+        let cell = r as usize * window + q as usize;
+        let bug_stack = state.board.board.get(position);
+        for (i, piece) in bug_stack.pieces[..bug_stack.len()].iter().enumerate() {
+            let level = i.min(LEVELS - 1);
+            let plane =
+                (owner(state, piece.color()) * BUGS + piece.bug() as usize) * LEVELS + level;
+            planes[plane * window * window + cell] = 1;
+        }
+    }
+    planes
+}
+
+fn reserves(state: &State) -> [[u8; BUGS]; 2] {
+    let mut reserves = [[0; BUGS]; 2];
+    for color in [Color::White, Color::Black] {
+        for (bug, pieces) in state.board.reserve(color, &state.rules) {
+            reserves[owner(state, color)][bug as usize] = pieces.len() as u8;
+        }
+    }
+    reserves
+}
+
+// What was played to get from `before` to `after`
+fn played(before: &State, after: &State, orientation: &Orientation) -> [i16; 7] {
+    let last_move = match after.last_turn {
+        LastTurn::Pass | LastTurn::Shutout => None,
+        _ => after.board.last_move,
+    };
+    let Some(last_move) = last_move else {
+        return [PASS, -1, -1, -1, -1, -1, -1];
+    };
+    let (kind, (from_q, from_r)) = match last_move.from {
+        Some(from) => (MOVE, orientation.apply(from)),
+        None => (SPAWN, (-1, -1)),
+    };
+    let (to_q, to_r) = orientation.apply(last_move.to);
+    [
+        kind,
+        owner(before, last_move.piece.color()) as i16,
+        last_move.piece.bug() as i16,
+        from_q as i16,
+        from_r as i16,
+        to_q as i16,
+        to_r as i16,
+    ]
+}
+
+// The samples of one game and the number of positions that didn't fit, None if the game is
+// skipped
+fn game_samples(path: &Path, window: usize) -> Option<(Vec<Sample>, usize)> {
+    let history = History::from_filepath(&path.display().to_string()).ok()?;
+    let end = replay(&history).ok()?;
+    let result = match (&end.game_status, &history.result) {
+        (GameStatus::Finished(result), _) if *result != GameResult::Unknown => result.clone(),
+        (_, GameResult::Unknown) => return None,
+        (_, reported) => reported.clone(),
+    };
+    let mut state = State::new(history.game_type, end.rules.tournament_opening);
+    let mut samples = Vec::new();
+    let mut skipped = 0;
+    for (piece, position) in history.moves.iter() {
+        if state.game_status.is_finished() {
+            break;
+        }
+        let before = state.clone();
+        state.play_turn_from_notation(piece, position).ok()?;
+        let Some(orientation) = Orientation::new(&before, window) else {
+            skipped += 1;
+            continue;
+        };
+        samples.push(Sample {
+            game: path.display().to_string(),
+            turn: before.turn,
+            side_to_move: before.turn_color as u8,
+            planes: encode(&before, &orientation, window),
+            reserves: reserves(&before),
+            played: played(&before, &state, &orientation),
+            result: match result {
+                GameResult::Winner(color) if color == before.turn_color => 1,
+                GameResult::Winner(_) => -1,
+                _ => 0,
+            },
+        });
+    }
+    Some((samples, skipped))
+}
+
+impl TrainingData {
+    // Replays all games in parallel, the samples keep the order of `paths`
+    pub fn new(paths: Vec<PathBuf>, window: usize) -> Self {
+        let games = paths
+            .par_iter()
+            .map(|path| game_samples(path, window))
+            .collect::<Vec<_>>();
+        let mut data = TrainingData {
+            window,
+            ..Default::default()
+        };
+        for game in games {
+            let Some((samples, skipped)) = game else {
+                data.skipped_games += 1;
+                continue;
+            };
+            data.games += 1;
+            data.skipped_positions += skipped;
+            data.samples.extend(samples);
+        }
+        data
+    }
+
+    // One JSON object per sample and line
+    pub fn write_jsonl<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = BufWriter::new(writer);
+        for sample in self.samples.iter() {
+            serde_json::to_writer(&mut writer, sample)?;
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+
+    // One .npy file per field in `dir`, the first axis is the sample, games.txt has the game of
+    // every sample
+    pub fn write_npy(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let n = self.samples.len();
+        let w = self.window;
+        let samples = self.samples.iter();
+        write_npy(
+            &dir.join("planes.npy"),
+            "|u1",
+            &[n, PLANES, w, w],
+            samples
+                .clone()
+                .flat_map(|s| s.planes.iter().copied())
+                .collect(),
+        )?;
+        write_npy(
+            &dir.join("side_to_move.npy"),
+            "|u1",
+            &[n],
+            samples.clone().map(|s| s.side_to_move).collect(),
+        )?;
+        write_npy(
+            &dir.join("reserves.npy"),
+            "|u1",
+            &[n, 2, BUGS],
+            samples.clone().flat_map(|s| s.reserves.concat()).collect(),
+        )?;
+        write_npy(
+            &dir.join("played.npy"),
+            "<i2",
+            &[n, 7],
+            samples
+                .clone()
+                .flat_map(|s| s.played)
+                .flat_map(i16::to_le_bytes)
+                .collect(),
+        )?;
+        write_npy(
+            &dir.join("turns.npy"),
+            "<u4",
+            &[n],
+            samples
+                .clone()
+                .flat_map(|s| (s.turn as u32).to_le_bytes())
+                .collect(),
+        )?;
+        write_npy(
+            &dir.join("results.npy"),
+            "|i1",
+            &[n],
+            samples.clone().map(|s| s.result as u8).collect(),
+        )?;
+        let mut games = BufWriter::new(File::create(dir.join("games.txt"))?);
+        for sample in samples {
+            writeln!(games, "{}", sample.game)?;
+        }
+        games.flush()
+    }
+}
+
+// Version 1.0 of the NumPy array format
+fn write_npy(path: &Path, descr: &str, shape: &[usize], data: Vec<u8>) -> io::Result<()> {
+    let shape = match shape {
+        [n] => format!("({n},)"),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+    // magic, version and header length take 10 bytes and the data starts 64 byte aligned
+    let padding = 63 - (10 + header.len()) % 64;
+    header += &" ".repeat(padding);
+    header.push('\n');
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(b"\x93NUMPY\x01\x00")?;
+    file.write_all(&(header.len() as u16).to_le_bytes())?;
+    file.write_all(header.as_bytes())?;
+    file.write_all(&data)?;
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::Board, corpus::pgn_files};
+
+    fn state_from(file: &str, turns: usize) -> State {
+        let mut history = History::from_filepath(file).unwrap();
+        history.moves.truncate(turns);
+        State::new_from_history(&history).unwrap()
+    }
+
+    // The same position turned by 60 degrees and mirrored somewhere else on the board
+    fn turned(state: &State) -> State {
+        let mut board = Board::new();
+        let mut positions = state.board.all_taken_positions().collect::<Vec<_>>();
+        positions.sort();
+        positions.dedup();
+        for position in positions {
+            let (q, r) = (position.r, position.q);
+            let turned = Position::new(-r + 7, q + r - 3);
+            let bug_stack = state.board.board.get(position);
+            for piece in bug_stack.pieces[..bug_stack.len()].iter() {
+                board.insert(turned, *piece);
+            }
+        }
+        let mut turned = state.clone();
+        turned.board = board;
+        turned
+    }
+
+    #[test]
+    fn tests_canonical_orientation() {
+        for (file, turns) in [
+            ("./test_pgns/valid/p_game.pgn", 20),
+            ("./test_pgns/valid/descend.pgn", 40),
+            ("./test_pgns/valid/plm_draw.pgn", 30),
+        ] {
+            let state = state_from(file, turns);
+            let other = turned(&state);
+            let encode_state =
+                |state: &State| encode(state, &Orientation::new(state, 24).unwrap(), 24);
+            let planes = encode_state(&state);
+            assert_eq!(planes, encode_state(&other), "{file}");
+            let pieces = state.board.all_taken_positions().count();
+            assert_eq!(planes.iter().filter(|cell| **cell == 1).count(), pieces);
+        }
+        assert_eq!(
+            Orientation::new(&state_from("./test_pgns/valid/p_game.pgn", 20), 2),
+            None
+        );
+    }
+
+    #[test]
+    fn tests_training_data() {
+        let files = pgn_files(&["./test_pgns/valid"]).unwrap();
+        let data = TrainingData::new(files.clone(), 24);
+        assert_eq!(data.games + data.skipped_games, files.len());
+        assert!(data.games > 0);
+        assert!(!data.samples.is_empty());
+        let w = data.window;
+        for sample in data.samples.iter() {
+            assert_eq!(sample.planes.len(), PLANES * w * w);
+            assert!([-1, 0, 1].contains(&sample.result));
+            let [kind, owner, bug, from_q, from_r, _, _] = sample.played.map(|x| x as i32);
+            if kind == MOVE as i32 {
+                // the moved piece is in the planes at its starting cell
+                let cell = (from_r * w as i32 + from_q) as usize;
+                assert!((0..LEVELS).any(|level| {
+                    let plane = ((owner * BUGS as i32 + bug) as usize) * LEVELS + level;
+                    sample.planes[plane * w * w + cell] == 1
+                }));
+            }
+        }
+        // a game's result is the same for both players, just from the other side
+        let first = &data.samples[0];
+        let second = &data.samples[1];
+        assert_eq!(first.game, second.game);
+        assert_eq!(first.result, -second.result);
+        assert_eq!(first.played[0], SPAWN);
+    }
+
+    #[test]
+    fn tests_writers() {
+        let data = TrainingData::new(vec![PathBuf::from("./test_pgns/valid/p_game.pgn")], 16);
+        let n = data.samples.len();
+        let mut jsonl = Vec::new();
+        data.write_jsonl(&mut jsonl).unwrap();
+        let lines = String::from_utf8(jsonl).unwrap();
+        assert_eq!(lines.lines().count(), n);
+        let value: serde_json::Value = serde_json::from_str(lines.lines().next().unwrap()).unwrap();
+        assert_eq!(value["planes"].as_array().unwrap().len(), PLANES * 16 * 16);
+
+        let dir = std::env::temp_dir().join(format!("training_{}", std::process::id()));
+        data.write_npy(&dir).unwrap();
+        for (file, shape, size) in [
+            (
+                "planes.npy",
+                format!("({n}, {PLANES}, 16, 16)"),
+                n * PLANES * 256,
+            ),
+            ("played.npy", format!("({n}, 7)"), n * 7 * 2),
+            ("results.npy", format!("({n},)"), n),
+        ] {
+            let bytes = fs::read(dir.join(file)).unwrap();
+            assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+            let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+            assert_eq!((10 + header_len) % 64, 0);
+            let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+            assert!(header.contains(&format!("'shape': {shape}")), "{header}");
+            assert!(header.ends_with('\n'));
+            assert_eq!(bytes.len() - 10 - header_len, size);
+        }
+        let games = fs::read_to_string(dir.join("games.txt")).unwrap();
+        assert_eq!(games.lines().count(), n);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
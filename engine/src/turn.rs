@@ -0,0 +1,39 @@
+use crate::{move_list::Move, piece::Piece, position::Position};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Everything a player can do on their turn
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Turn {
+    Move(Move),
+    Spawn(Piece, Position),
+    Pass,
+}
+
+impl Turn {
+    pub fn piece(&self) -> Option<Piece> {
+        match self {
+            Turn::Move(mv) => Some(mv.piece),
+            Turn::Spawn(piece, _) => Some(*piece),
+            Turn::Pass => None,
+        }
+    }
+
+    pub fn to(&self) -> Option<Position> {
+        match self {
+            Turn::Move(mv) => Some(mv.to),
+            Turn::Spawn(_, position) => Some(*position),
+            Turn::Pass => None,
+        }
+    }
+}
+
+impl fmt::Display for Turn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Turn::Move(mv) => write!(f, "{} {} -> {}", mv.piece, mv.from, mv.to),
+            Turn::Spawn(piece, position) => write!(f, "{piece} {position}"),
+            Turn::Pass => write!(f, "pass"),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{
+    game_error::GameError, game_result::GameResult, game_status::GameStatus, game_type::GameType,
+    history::History, illegal_move_reason::IllegalMoveReason, state::State,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{collections::BTreeMap, path::PathBuf};
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct IllegalMove {
+    // turn as numbered in the PGN, starting at 1
+    pub turn: usize,
+    pub piece: String,
+    pub from: String,
+    pub to: String,
+    pub reason: IllegalMoveReason,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct GameValidation {
+    pub path: PathBuf,
+    pub game_type: GameType,
+    pub reported_result: GameResult,
+    // None if the game could not be replayed
+    pub game_status: Option<GameStatus>,
+    pub turns: usize,
+    pub illegal_move: Option<IllegalMove>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub games: usize,
+    pub valid: usize,
+    pub invalid: usize,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationSummary {
+    pub total: Counts,
+    pub by_game_type: BTreeMap<String, Counts>,
+    // keyed by the result the PGN reports
+    pub by_result: BTreeMap<String, Counts>,
+    pub by_reason: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub games: Vec<GameValidation>,
+    pub summary: ValidationSummary,
+}
+
+impl Counts {
+    fn add(&mut self, valid: bool) {
+        self.games += 1;
+        if valid {
+            self.valid += 1;
+        } else {
+            self.invalid += 1;
+        }
+    }
+}
+
+impl GameValidation {
+    pub fn new(path: PathBuf) -> Self {
+        let history = match History::from_filepath(&path.display().to_string()) {
+            Ok(history) => history,
+            Err(e) => {
+                return Self {
+                    path,
+                    game_type: GameType::default(),
+                    reported_result: GameResult::Unknown,
+                    game_status: None,
+                    turns: 0,
+                    illegal_move: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        };
+        let mut validation = Self {
+            path,
+            game_type: history.game_type,
+            reported_result: history.result.clone(),
+            game_status: None,
+            turns: history.moves.len(),
+            illegal_move: None,
+            error: None,
+        };
+        match replay(&history) {
+            Ok(state) => validation.game_status = Some(state.game_status),
+            Err(e) => {
+                if let GameError::InvalidMove {
+                    piece,
+                    from,
+                    to,
+                    turn,
+                    reason,
+                } = &e
+                {
+                    validation.illegal_move = Some(IllegalMove {
+                        turn: turn + 1,
+                        piece: piece.clone(),
+                        from: from.clone(),
+                        to: to.clone(),
+                        reason: *reason,
+                    });
+                }
+                validation.error = Some(e.to_string());
+            }
+        }
+        validation
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+// Replays the history and checks that the final position matches the reported result
+pub fn replay(history: &History) -> Result<State, GameError> {
+    let state = State::new_from_history(history)?;
+    if let GameStatus::Finished(actual_result) = &state.game_status {
+        if history.result != GameResult::Unknown
+            && *actual_result != GameResult::Unknown
+            && *actual_result != history.result
+        {
+            return Err(GameError::ResultMismatch {
+                reported_result: history.result.clone(),
+                actual_result: actual_result.clone(),
+            });
+        }
+    }
+    Ok(state)
+}
+
+impl ValidationReport {
+    // Validates all games in parallel, the report keeps the order of `paths`
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let games = paths
+            .into_par_iter()
+            .map(GameValidation::new)
+            .collect::<Vec<_>>();
+        let summary = ValidationSummary::new(&games);
+        Self { games, summary }
+    }
+
+    pub fn invalid(&self) -> impl Iterator<Item = &GameValidation> {
+        self.games.iter().filter(|game| !game.is_valid())
+    }
+}
+
+impl ValidationSummary {
+    pub fn new(games: &[GameValidation]) -> Self {
+        let mut summary = Self::default();
+        for game in games {
+            let valid = game.is_valid();
+            summary.total.add(valid);
+            summary
+                .by_game_type
+                .entry(game.game_type.to_string())
+                .or_default()
+                .add(valid);
+            summary
+                .by_result
+                .entry(game.reported_result.to_string())
+                .or_default()
+                .add(valid);
+            if let Some(illegal_move) = &game.illegal_move {
+                *summary
+                    .by_reason
+                    .entry(format!("{:?}", illegal_move.reason))
+                    .or_default() += 1;
+            }
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus::pgn_files;
+
+    #[test]
+    fn tests_validation_report() {
+        let valid = pgn_files(&["./test_pgns/valid"]).unwrap();
+        let invalid = pgn_files(&["./test_pgns/invalid"]).unwrap();
+        let report = ValidationReport::new(
+            pgn_files(&["./test_pgns/valid", "./test_pgns/invalid"]).unwrap(),
+        );
+        assert_eq!(report.summary.total.games, valid.len() + invalid.len());
+        assert_eq!(report.summary.total.valid, valid.len());
+        assert_eq!(report.summary.total.invalid, invalid.len());
+        assert!(report
+            .invalid()
+            .all(|game| game.path.starts_with("./test_pgns/invalid")));
+        assert_eq!(
+            report
+                .summary
+                .by_game_type
+                .values()
+                .map(|counts| counts.games)
+                .sum::<usize>(),
+            report.summary.total.games
+        );
+        assert_eq!(report.summary.by_reason.get("JustMoved"), Some(&2));
+        assert!(serde_json::to_string(&report).is_ok());
+    }
+
+    #[test]
+    fn tests_first_illegal_move() {
+        let game = GameValidation::new(PathBuf::from("./test_pgns/invalid/move_before_q.pgn"));
+        let illegal_move = game.illegal_move.expect("Game has an illegal move");
+        assert_eq!(illegal_move.reason, IllegalMoveReason::QueenNotPlayed);
+        assert!(illegal_move.turn >= 1 && illegal_move.turn <= game.turns);
+        assert!(game.game_status.is_none());
+
+        let game = GameValidation::new(PathBuf::from("./test_pgns/valid/plm_draw.pgn"));
+        assert!(game.is_valid());
+        assert_eq!(
+            game.game_status,
+            Some(GameStatus::Finished(GameResult::Draw))
+        );
+    }
+}